@@ -0,0 +1,2 @@
+DROP INDEX song_lyrics_search;
+ALTER TABLE song DROP COLUMN lyrics;
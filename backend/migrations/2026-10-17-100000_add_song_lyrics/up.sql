@@ -0,0 +1,4 @@
+ALTER TABLE song ADD COLUMN lyrics TEXT;
+
+-- The 'simple' configuration doesn't stem words, since lyrics come in many languages.
+CREATE INDEX song_lyrics_search ON song USING GIN (to_tsvector('simple', coalesce(lyrics, '')));
@@ -0,0 +1,18 @@
+CREATE TABLE queue_session (
+	id SERIAL PRIMARY KEY,
+	name TEXT NOT NULL,
+	owner TEXT NOT NULL
+);
+
+CREATE TABLE queue_entry (
+	id SERIAL PRIMARY KEY,
+	session_id INTEGER NOT NULL REFERENCES queue_session(id) ON DELETE CASCADE,
+	song_hash TEXT NOT NULL REFERENCES song(song_hash),
+	singer TEXT NOT NULL,
+	singer_cid TEXT,
+	position INTEGER NOT NULL,
+	state TEXT NOT NULL DEFAULT 'queued' CHECK (state IN ('queued', 'done', 'skipped')),
+
+	-- deferred, since reordering a queue swaps the positions of its entries one at a time
+	CONSTRAINT queue_entry_position UNIQUE (session_id, position) DEFERRABLE INITIALLY DEFERRED
+);
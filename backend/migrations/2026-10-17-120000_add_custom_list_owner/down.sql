@@ -0,0 +1,12 @@
+ALTER TABLE custom_list_entry DROP CONSTRAINT custom_list_entry_list_id_fkey;
+ALTER TABLE custom_list_entry ADD CONSTRAINT custom_list_entry_list_id_fkey
+	FOREIGN KEY (list_id) REFERENCES custom_list(id);
+
+-- only one list per user fits in the old schema, keep the oldest one
+DELETE FROM custom_list_entry WHERE list_id NOT IN (SELECT min(id) FROM custom_list GROUP BY owner);
+DELETE FROM custom_list WHERE id NOT IN (SELECT min(id) FROM custom_list GROUP BY owner);
+UPDATE custom_list SET name = owner;
+
+DROP INDEX custom_list_owner;
+ALTER TABLE custom_list ADD CONSTRAINT custom_list_name_key UNIQUE (name);
+ALTER TABLE custom_list DROP COLUMN owner;
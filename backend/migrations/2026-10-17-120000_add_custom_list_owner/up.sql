@@ -0,0 +1,12 @@
+ALTER TABLE custom_list ADD COLUMN owner TEXT;
+ALTER TABLE custom_list DROP CONSTRAINT custom_list_name_key;
+
+-- lists used to be named after the CID of their owner
+UPDATE custom_list SET owner = name, name = 'Min lista';
+
+ALTER TABLE custom_list ALTER COLUMN owner SET NOT NULL;
+CREATE INDEX custom_list_owner ON custom_list(owner);
+
+ALTER TABLE custom_list_entry DROP CONSTRAINT custom_list_entry_list_id_fkey;
+ALTER TABLE custom_list_entry ADD CONSTRAINT custom_list_entry_list_id_fkey
+	FOREIGN KEY (list_id) REFERENCES custom_list(id) ON DELETE CASCADE;
@@ -0,0 +1,6 @@
+CREATE TABLE custom_list_editor (
+	list_id INTEGER NOT NULL REFERENCES custom_list(id) ON DELETE CASCADE,
+	kind TEXT NOT NULL CHECK (kind IN ('user', 'group')),
+	name TEXT NOT NULL,
+	PRIMARY KEY (list_id, kind, name)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE custom_list DROP COLUMN share_token;
+ALTER TABLE custom_list DROP COLUMN visibility;
@@ -0,0 +1,4 @@
+ALTER TABLE custom_list ADD COLUMN visibility TEXT NOT NULL DEFAULT 'public'
+	CHECK (visibility IN ('private', 'unlisted', 'public'));
+ALTER TABLE custom_list ADD COLUMN share_token TEXT NOT NULL
+	DEFAULT replace(gen_random_uuid()::text, '-', '');
@@ -0,0 +1,2 @@
+DROP INDEX custom_list_entry_position;
+ALTER TABLE custom_list_entry DROP COLUMN position;
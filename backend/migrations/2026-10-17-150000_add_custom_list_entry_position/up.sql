@@ -0,0 +1,12 @@
+ALTER TABLE custom_list_entry ADD COLUMN position INTEGER;
+
+-- keep the existing entries in the order they were inserted
+UPDATE custom_list_entry AS entry SET position = ordered.position
+FROM (
+	SELECT list_id, song_hash, row_number() OVER (PARTITION BY list_id ORDER BY ctid) - 1 AS position
+	FROM custom_list_entry
+) AS ordered
+WHERE entry.list_id = ordered.list_id AND entry.song_hash = ordered.song_hash;
+
+ALTER TABLE custom_list_entry ALTER COLUMN position SET NOT NULL;
+CREATE INDEX custom_list_entry_position ON custom_list_entry(list_id, position);
@@ -0,0 +1,4 @@
+DELETE FROM custom_list_entry WHERE song_hash IN (SELECT song_hash FROM song WHERE removed_at IS NOT NULL);
+DELETE FROM queue_entry WHERE song_hash IN (SELECT song_hash FROM song WHERE removed_at IS NOT NULL);
+DELETE FROM song WHERE removed_at IS NOT NULL;
+ALTER TABLE song DROP COLUMN removed_at;
@@ -0,0 +1,3 @@
+-- songs are kept when they're removed from the song list, since custom lists and queues may
+-- still refer to them
+ALTER TABLE song ADD COLUMN removed_at TIMESTAMPTZ;
@@ -0,0 +1,2 @@
+ALTER TABLE song DROP COLUMN cover_hash;
+ALTER TABLE song DROP COLUMN content_hash;
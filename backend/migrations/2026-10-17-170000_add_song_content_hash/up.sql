@@ -0,0 +1,2 @@
+ALTER TABLE song ADD COLUMN content_hash TEXT;
+ALTER TABLE song ADD COLUMN cover_hash TEXT;
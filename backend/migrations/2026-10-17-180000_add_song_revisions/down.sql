@@ -0,0 +1,2 @@
+DROP TABLE song_revision_change;
+DROP TABLE song_revision;
@@ -0,0 +1,22 @@
+CREATE TABLE song_revision (
+	id SERIAL PRIMARY KEY,
+	created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+	-- CID of the admin that made the change, or ADMIN_TOKEN
+	created_by TEXT NOT NULL,
+
+	-- the revision that the song list was rolled back to, if this was a rollback. not a foreign
+	-- key, since 0 means the song list before the first revision
+	rollback_of INTEGER
+);
+
+CREATE TABLE song_revision_change (
+	revision_id INTEGER NOT NULL REFERENCES song_revision(id) ON DELETE CASCADE,
+	song_hash TEXT NOT NULL,
+
+	-- the song before and after the change, null if it wasn't in the song list
+	old JSONB,
+	new JSONB,
+
+	PRIMARY KEY (revision_id, song_hash)
+);
@@ -0,0 +1,25 @@
+-- Users who are admins, in addition to the members of ADMIN_GROUPS
+CREATE TABLE admin_user (
+	cid TEXT PRIMARY KEY,
+	added_by TEXT NOT NULL,
+
+	-- in UTC, since the table is shared with SQLite which has no time zones
+	added_at TIMESTAMP NOT NULL
+);
+
+-- Tokens that scripts such as ultrascraper use to make admin requests
+CREATE TABLE api_token (
+	id SERIAL PRIMARY KEY,
+	name TEXT NOT NULL,
+
+	-- SHA-256 of the token, which is only shown when it's created
+	token_hash TEXT NOT NULL UNIQUE,
+
+	-- what the token may be used for, comma separated, e.g. 'songs,covers'
+	scopes TEXT NOT NULL,
+
+	created_by TEXT NOT NULL,
+	created_at TIMESTAMP NOT NULL,
+	last_used_at TIMESTAMP,
+	revoked_at TIMESTAMP
+);
@@ -0,0 +1,23 @@
+-- Tokens that users create to script against their own custom lists and queues
+CREATE TABLE personal_token (
+	id SERIAL PRIMARY KEY,
+
+	-- CID and nick of the user that the token authenticates as
+	owner TEXT NOT NULL,
+	owner_nick TEXT NOT NULL,
+
+	name TEXT NOT NULL,
+
+	-- SHA-256 of the token, which is only shown when it's created
+	token_hash TEXT NOT NULL UNIQUE,
+
+	-- in UTC, like the times of API tokens
+	created_at TIMESTAMP NOT NULL,
+	last_used_at TIMESTAMP,
+	revoked_at TIMESTAMP,
+
+	-- the token can't be used after this
+	expires_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX personal_token_owner ON personal_token(owner);
@@ -0,0 +1,4 @@
+DROP TABLE custom_list_entry;
+DROP TABLE custom_list_editor;
+DROP TABLE custom_list;
+DROP TABLE song;
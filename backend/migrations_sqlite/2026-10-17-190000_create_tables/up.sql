@@ -0,0 +1,44 @@
+-- The SQLite database has the same tables as the Postgres one, except for queues and revisions.
+
+CREATE TABLE song (
+	song_hash TEXT NOT NULL PRIMARY KEY,
+	title TEXT NOT NULL,
+	artist TEXT NOT NULL,
+	cover TEXT,
+	language TEXT,
+	video TEXT,
+	year TEXT,
+	genre TEXT,
+	bpm TEXT NOT NULL,
+	duet_singer_1 TEXT,
+	duet_singer_2 TEXT,
+	lyrics TEXT,
+	removed_at TIMESTAMP,
+	content_hash TEXT,
+	cover_hash TEXT
+);
+
+CREATE TABLE custom_list (
+	id INTEGER PRIMARY KEY,
+	name TEXT NOT NULL,
+	owner TEXT NOT NULL,
+	visibility TEXT NOT NULL DEFAULT 'public'
+		CHECK (visibility IN ('private', 'unlisted', 'public')),
+	share_token TEXT NOT NULL DEFAULT (lower(hex(randomblob(16))))
+);
+CREATE INDEX custom_list_owner ON custom_list(owner);
+
+CREATE TABLE custom_list_editor (
+	list_id INTEGER NOT NULL REFERENCES custom_list(id) ON DELETE CASCADE,
+	kind TEXT NOT NULL CHECK (kind IN ('user', 'group')),
+	name TEXT NOT NULL,
+	PRIMARY KEY (list_id, kind, name)
+);
+
+CREATE TABLE custom_list_entry (
+	list_id INTEGER NOT NULL REFERENCES custom_list(id) ON DELETE CASCADE,
+	song_hash TEXT NOT NULL REFERENCES song(song_hash),
+	position INTEGER NOT NULL,
+	PRIMARY KEY (list_id, song_hash)
+);
+CREATE INDEX custom_list_entry_position ON custom_list_entry(list_id, position);
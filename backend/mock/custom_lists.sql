@@ -1,253 +1,255 @@
-insert into custom_list(id, name) VALUES (1, 'tux');
-insert into custom_list_entry(list_id, song_hash) VALUES
-(1, '415e7f2a9ca15306f462493dea011328'),
-(1, 'c2ada59d4465e891565ed6480c95402b'),
-(1, 'b64e1a6f02bc3dbc9cf42ec51465f8a4'),
-(1, '61611e2ae7da9f5fa307c677aa768749'),
-(1, '331b7741323bb299558d2889662bc90c'),
-(1, '83c8eb8a644c01085406611a54c746b3'),
-(1, '5305707bae7a4072d6f72d2c83c6fe19'),
-(1, '2a405748c627c30bfdf029361d09618c'),
-(1, '24bf0d992eb9bfe83f0355574e52cd66'),
-(1, '128d3a0b6109b61e7c5266942e04e45a'),
-(1, '4cda857d6a4d1365a98f0f4b0f6292da'),
-(1, '8fd5f78019ef7cdfa595d5c51a147ba7'),
-(1, '138a95285762edcfb7916b9aef329083'),
-(1, '8e9c0d935b93569858817a4ae9513875'),
-(1, 'b865f24ced688b6932c6131d644f7975'),
-(1, 'f83893a4322289818328b42468728314'),
-(1, '92d04dbea98658cd07d9b2c29356ab52'),
-(1, '9e2975f1cb67bd0ff76c83e9d2b3c7a4'),
-(1, 'cf9eaf021b3d78cd2491be771abbe13b'),
-(1, '3a04e1eec93c224af777c7f951dd2d98'),
-(1, 'b20b9c8f2bdb43086ec9d46b9a6bada0'),
-(1, '85ad23ce6d40d27656a9cbd54e3f6ab4'),
-(1, 'ab32d01ba3d2a557c51c7b26f08f61f0'),
-(1, '989118dd28898b84962858ec44e2f7e2'),
-(1, 'bf7a666e3d37a1d7388ed838bb6f020a'),
-(1, 'd30cf94ad68c50f660a31bcf637c76bd'),
-(1, '053161d0a98c27ea4a9ee5e0e404e848'),
-(1, 'c9cf0764a339f79b783087db605fe8f0'),
-(1, 'd5c4c3513cb8869643f42e1350474ad3'),
-(1, '0a8a99dc8ffafaf837f0755fa2e54009'),
-(1, 'fcea59940c64e1257ce9264254ba8e03'),
-(1, '63c18435bbeef45e6fafc947ca5b1ff5'),
-(1, '6620e5b50f4f1ee7358bbe0d609edfe2'),
-(1, '265a22dc97ee56e2e1a4ce525f3e79c2'),
-(1, '9adb1f7ec660e0f2643cd151fad69b82'),
-(1, '6443a7047288aab14ee4694ebe87b79b'),
-(1, '80a356c273b7d52049a23e91e36aa9f0'),
-(1, '0f73fa30f7f20d5235dcac8eabff472d'),
-(1, '5a4b69e047a949744a81715a83f20ee3'),
-(1, '3407c6f6ee7b1970c094b0c3144bf54a'),
-(1, '9a4fbaee92151f345680abe2fc9ee747'),
-(1, '1a049f2fe3a59d840c5157600c8743b5'),
-(1, 'd6f4b7dd36e9ddb4e654d2d6836ff15e'),
-(1, 'be090ec6b8d3dcd206c29c2620b673d6'),
-(1, '2de6e630dc402b3f1dd565e25376fa6e'),
-(1, '8527cc85dfb71405e0c509d60d530fa1'),
-(1, '495b964e442823eb695e6e73d59ed100'),
-(1, '09bfeb3ac6fc284b0ef8ab47c055e2b0'),
-(1, 'a5f09a3e3aae08d5576d8c24656ea1da'),
-(1, 'e7110b6577a733df0ef96559c72fdc3a'),
-(1, '61a8dacb968a51d010af72cd9ba0fcc7'),
-(1, '126c7aa03977ba6179c14d1286d95987'),
-(1, '7bafa94e8b192112fcc13285733fed21'),
-(1, '55efe418039d6b4c1a0a3033a505a6d5'),
-(1, 'd6cd321817e8d2ed7ebb2a7282d7ba08'),
-(1, '56f7f540f406a8f4feabdc976ffc02a5'),
-(1, '5bd19f7edc9a5aceb165bf982777fbef'),
-(1, 'e8f3382284dd8f3c14f74e543cbe1365'),
-(1, '8b1bef88f60a945f23695a98ab461a32'),
-(1, '34340558fe5254ec8d632b28412c1df6'),
-(1, '133c04455080e1e700ea9168eb6e9154'),
-(1, '40b939fea1cc7bca69f94ba98a9a8966'),
-(1, '17110ec3c9490c2d7212bac578178f82'),
-(1, '4013a6b73babbe4ddb7c94308f607981'),
-(1, '606aa3c2fd587ba49e06e2ba96634d3e'),
-(1, 'f3ddf171f30f886bb65f2eb9b5e9b631'),
-(1, 'b441c2178c3ea7b470905972a8542a45'),
-(1, '8c0bcc859b936e07125a8a1496fc5cff'),
-(1, '37903e02cd6f096b7c18130ef322f245'),
-(1, '246a6ba918b31591b8a3bb39e89e57ca'),
-(1, 'df864a07605f68f58d454991035887ad'),
-(1, '4407108e5fcce7999b4ed6a02235829e'),
-(1, '8504e0862ec12cca41caa1f78681ec03'),
-(1, '75a3f6fbf821d473bf17025e043a5964'),
-(1, '0bc6e109374f04db38ec70d292371d19'),
-(1, '5fb96e336c571545a9e3a1afa0ce25c2'),
-(1, 'def1a11db7a5899c9b0ba357fc71a37f'),
-(1, '581e463cee62d8adafd84b13deeb507d'),
-(1, '503a857d480a4ee4c30b132b80e895d1'),
-(1, 'cf60d6a32e7a1256f1e69b518fe158ff'),
-(1, '980817c5a574eb50f3ab26f390b84b4f'),
-(1, '2aa8f43167062239fa955a6fd5bf209b'),
-(1, '16318fbae71adefaf80991849a181d11'),
-(1, '43fd446c68528a56931f755525b8bafc'),
-(1, 'abf514722acd55a48f5c1ec8badd899d'),
-(1, 'fb3dd61449de05c2b84d3d75cf346d81'),
-(1, '55a069e51045adab0adf4d81ca36011e'),
-(1, '5694db971c54e0872defef4a300343f9'),
-(1, 'd67f527b382394095a3ca40ac8449904'),
-(1, 'db52ed089f2008181515e3f8be54bf90'),
-(1, 'f2fffd34cef9a1878754640688487c82'),
-(1, 'd6ddd5009d865979febbfb36bc2d99b7'),
-(1, '362c2a15b7e8aaf8f1dfefd717fe7e29'),
-(1, 'b2c13e3d09706d5fde0243a44058f81c'),
-(1, '37d139647cca8827ad1205695a3daa33'),
-(1, 'c5732364b28b208d22e0f4cab71b5907'),
-(1, '236197f68795dea908d97fa61d8bb1c5'),
-(1, 'f54e7bc914d7c5fab66b371399acfd41'),
-(1, 'c2563f5a17677676ec45a0d487a2a871'),
-(1, '08f30c15ce2135cdf575e71aa9fadee8'),
-(1, 'ebd1f487cdb42b7d444b23c68a79918b'),
-(1, 'ae2739faa6e4b4c9de6962071be9fec6'),
-(1, '29c04079f0a8cdd22d95ff605847ccce'),
-(1, '3a0ffffb65779005d55f8d8f3d0a7091'),
-(1, '14c06f589bb0a772387888641b52454b'),
-(1, 'e0f3f3e0afff4e71d8a2e3a959a8effa'),
-(1, '2b843c3751dde9cfadfd392cac900487'),
-(1, '8c876d8344b468cfb89a5cc91c4a5218'),
-(1, '15917f7b815dce661db415ee1c488813'),
-(1, '54fb8b43f54e36e9a6a546d3c45aa4cf'),
-(1, 'a9430cf8d75c891c6c27ca6750faee8e'),
-(1, 'eb50f5eee7f570e976ab423fb44edde9'),
-(1, 'ed48d149f7ffa48d8c7eed5270f48949'),
-(1, '6fa37a33a1baea4875c9e342f8091dc3'),
-(1, '9ce446680bea61864df9d9fec5d54a93'),
-(1, 'f7ea025406d2645f86da52dde64807d0'),
-(1, '114fb59b3fee8ebf8c36fd2bf7e59a55'),
-(1, 'c1729df7dc9452a496c7be7f043ce88d'),
-(1, '92db823664bfa36f7fdde309fe69eb26'),
-(1, 'df833bc0ae6fd919e0db280a8f0f5b9e'),
-(1, '7d88370d2be9063ef347351781e3ad38'),
-(1, '1dbcab635439ff5d3432407fedb94da1'),
-(1, 'c79b580b5ba99bdf4669a61379747740'),
-(1, 'ce3722d28640ab845396d37933c66b1a'),
-(1, 'dd55c007590f65a011febd5c5ec17262'),
-(1, 'bb51d1b9fb2ca91fab7e63066f797c97'),
-(1, '597e0dc97c39604cbac73a887f89bf94'),
-(1, '2ed7517269cece052f7cbe4d8ec6f50c'),
-(1, '9c4adad2919051e6fe586354b9af132b'),
-(1, '2f9cc67c261ee31a0ecdd154168a987a'),
-(1, 'bb1a82b02f0de5a90fcb44edd2cccdf9'),
-(1, '9ec7df7febfce66dc43941e2c259904b'),
-(1, '1fd38f24222630b30cca1978e2acdb85'),
-(1, '9d051739db959a5dfb5b98890b4e6ae6'),
-(1, '9ae26319e307759e6e27c85ddff8f5fa'),
-(1, '3e3ac2c042b18fcc7f9610f654778740'),
-(1, '92aeb6900e0e06603dd846889c5d8ded'),
-(1, '096d2a19ddf0911a66c4fe4b41f992c7'),
-(1, 'cde2d982ab2caee5f0715c341bcae991'),
-(1, 'aa545b027a6fa39e5c278fc366ad9016'),
-(1, '1c5b8e85a66a415e39d996b967f92e67'),
-(1, '1b4673e2376ecfa02bae442cdc5b3c90'),
-(1, '9e542ff1cb9fb17f5f7a60af1fe9e66b'),
-(1, '8aa0b348759b2c56e17074b65a50f04a'),
-(1, '4931f242aeb4b1b641d813c6a2fbfadd'),
-(1, '2e3cfa5057486d0759cee8ee477a1678'),
-(1, '40444256345769c7a911e8710f0f5733'),
-(1, '54f85dd1796f5a8f5797c3965e8736f4'),
-(1, 'd8b2250234456105c3f1452afcffbfd5'),
-(1, '038e1f5299c2e7c4c87b54f655429666'),
-(1, '2ba74b69dc4671aa0fa705306c7cf072'),
-(1, 'fef173c794fec61b65089321673d0370'),
-(1, '366eff873b9665852f08b3d134dfd87c'),
-(1, '1bdc4632029c88612f481ab75ebc4062'),
-(1, 'b3acb1a516ee6949dd0f764200e80287'),
-(1, '00d59e67b764386960d8b423567cf9bc'),
-(1, 'b78fa5394c788492c5ddcfdbdfe4ae15'),
-(1, 'e8a7d9cb2aca242bf3f0c59640ddd620'),
-(1, '6059a22be14860cff767d679c98ed1df'),
-(1, 'b23ce25c9b6d789953b43d6a7f6f9266'),
-(1, '0499534b7a0545e81354fd6e814f4ad3'),
-(1, '77ea72cb592979d8aa617f1a06181600'),
-(1, '482de10e7a8d0688dd9ac27eef4cddb8'),
-(1, '570b9443265e8614befdb3c84bf66095'),
-(1, '5f572916554e6053ee9a59c2fbadb7c7'),
-(1, '79a9316e23134dc7632c51ec305af756'),
-(1, '4656a71e7802cff7757cc3e4a9281927'),
-(1, 'f986b0490311d17c909d8b8cceb6756d'),
-(1, '11c183d403657cafd9fe8f22921686cc'),
-(1, 'cbbf9ec130e723aa24e600d16471570b'),
-(1, 'e9a45f7858204bfc7b9ae196c3d9d7a7'),
-(1, '47097d94df0cb3b5f4c281e4d1077ce5'),
-(1, '09241a765e64d1f2280a888f255add7a'),
-(1, 'fb8f0d2dd52a205aa367d3f1b549f9c1'),
-(1, '97f260fceebdedcdc9f5ab99fef8a78e'),
-(1, '86a91d11239bc96df9c917c99bb19050'),
-(1, 'a1bac8f28c3378e56d0485cc880098d2'),
-(1, 'f563a6a7d1a20fc4dddca1dab785bd1f'),
-(1, '49159657f8644de0ea1c2dca29b11482'),
-(1, 'd94065d725bbdeedeebe1c5a8693873f'),
-(1, '03563f756de4a637b2bfa6c33b9ba3cd'),
-(1, '97b51c9df42b8e8754a6127073b6cf82'),
-(1, '865a527fc94bf609d2a86e6e7c2ca481'),
-(1, 'cb877f34c2a65534a458bf54ea823f08'),
-(1, '9d3b3d092d27b3ed0b76281225f98bf7'),
-(1, 'b27e8ad3b0d538abe256169cb3363b4a'),
-(1, '51c3819eb7ced02c46c22acda2c74205'),
-(1, '7ba6a2f608295d678aea4195b46a0219'),
-(1, 'db9785d69f04ab00266814b8e992da91'),
-(1, '2db8d58d0f3062f9d7e1259492652c1d'),
-(1, 'e2065c855899f8ed49e6ce89162683e8'),
-(1, '1319c9749c2ffdc6832e5aa98b54e659'),
-(1, 'fd34b1095c30bde6ce7aab8ec0683bb7'),
-(1, '7ad81d348b958714dea07ece9fd522c2'),
-(1, '2fb87de8954d03f91e6771846e2a8d6f'),
-(1, '6597cb67e75e5991ceb2afe448db3c35'),
-(1, '6c2619df4ae1e9377d47116d88eeeb5d'),
-(1, 'd25074818bd0a543482f55ff21330676'),
-(1, '8adf0a8c2be11dac6ec490bfebcc8d30'),
-(1, 'b1b6e78d3e26e1aed5a408ddb975547b'),
-(1, '1c8437de40bafcd6ba379ac53f1b8169'),
-(1, 'bb03494e04ca2cd3918de70f74498fcf'),
-(1, 'e00752cbe957fb707c6a6ac574452cd5'),
-(1, 'e1360e0256b6ce58c3f8591bb93a70dc'),
-(1, 'fd6c48fc6006b8276d12f8859daec350'),
-(1, '75b513f7b143b20a53c9a6d415bb02d1'),
-(1, 'ed2eff479f6e1333c83561125f8e64b9'),
-(1, '90a269caf890d1fae8fe57dc4ed19e56'),
-(1, 'b553b566dbb94a712641f346db436ad4'),
-(1, '76f9f24634d3590cbdc3dbfc2d84cb6c'),
-(1, '30c94206e5aac9e6936c42a2f60cc426'),
-(1, '692915e5a20c90c4c0d73f3db9c1154a'),
-(1, '836c2fa67a8d62c3b8c04bccd2ae31be'),
-(1, 'a514416bfa9ec0a46b98de23194398a4'),
-(1, '6abd9038780dd1f005df9fc31feb1560'),
-(1, 'e35237b0b1595f75b6e641669d67cfce'),
-(1, 'cbd15cc24b2892beb3444780fdbb19be'),
-(1, '26c91da37713481245b08bd74565f06b'),
-(1, '95cbbc0c9529592bee188dd2f3e7b8c7'),
-(1, 'f5d7f236ed25c850ec5e2f462eed7c4f'),
-(1, 'e5d7e266169ff20581413412b970c66c'),
-(1, 'e1e60351f1d5c9b62ad373e6343ccb78'),
-(1, '8c4a8899f75122dece210f306fc50de7'),
-(1, '70ec05034e36531f8cc15a995ee2a1ca'),
-(1, '1a9f9cbdf1adedcba49592fb48fb23f1'),
-(1, '1369618c25eb074761f2f9dd12cd082e'),
-(1, 'bcdd94a0c273aa1b0700de7e5e4213d0'),
-(1, '65248f0a7165ab82ee03e312f20526e7'),
-(1, '4c494372395a62e213ebecfe4dff643b'),
-(1, 'c270582f05844d3adef31a226386100f'),
-(1, '97047972139aa66b73ad3af35ca45b44'),
-(1, '40242bfc27f4b1a1af097bf88cc9619d'),
-(1, '604cabd06bd3b31fc5b8dd3802fcde2c'),
-(1, '687dc02afb37f19b29ceb2339eb52676'),
-(1, '581294986a59b3403d05de69c6be96d7'),
-(1, '38ec3bbef46ad213aef4731fe049f5f2'),
-(1, 'b50c594fc7e5603d5178b33109f6fb02'),
-(1, '7988cb4fd66dd5acf3c8e4ed87a1ec5f'),
-(1, '53a805ec4780ff7641acdfe374373e80'),
-(1, '226b3556a1804e38029fd237a66b1db0'),
-(1, 'b36725f672acde360df83bb877028d73'),
-(1, '6ba01a6240c18488040f9385de557081'),
-(1, '3b35edddcab0cac1aec5615dc86cd1f8'),
-(1, '8a9d0cf19bd5111f45b75ac99ef298e8'),
-(1, '1fa8e36ea62adfd1dbe8943a5cbd7457'),
-(1, '38304b8fd2f1552ee09b14be3c1d3f2e'),
-(1, 'acc5ce8a0c88cb4c5f74140e36f83e1f'),
-(1, '948fb19cc466d95cd9fc7188ad5411bf'),
-(1, 'c5024d345c3453b92b627bfc212dbf0e'),
-(1, 'ef5ca491b2c6298026a4eb425ec63c6b'),
-(1, '9b661821b671d44f7221a4a36ab80d8d');
+insert into custom_list(id, name, owner) VALUES (1, 'Min lista', 'tux');
+insert into custom_list_entry(list_id, song_hash, position) VALUES
+(1, '415e7f2a9ca15306f462493dea011328', 0),
+(1, 'c2ada59d4465e891565ed6480c95402b', 1),
+(1, 'b64e1a6f02bc3dbc9cf42ec51465f8a4', 2),
+(1, '61611e2ae7da9f5fa307c677aa768749', 3),
+(1, '331b7741323bb299558d2889662bc90c', 4),
+(1, '83c8eb8a644c01085406611a54c746b3', 5),
+(1, '5305707bae7a4072d6f72d2c83c6fe19', 6),
+(1, '2a405748c627c30bfdf029361d09618c', 7),
+(1, '24bf0d992eb9bfe83f0355574e52cd66', 8),
+(1, '128d3a0b6109b61e7c5266942e04e45a', 9),
+(1, '4cda857d6a4d1365a98f0f4b0f6292da', 10),
+(1, '8fd5f78019ef7cdfa595d5c51a147ba7', 11),
+(1, '138a95285762edcfb7916b9aef329083', 12),
+(1, '8e9c0d935b93569858817a4ae9513875', 13),
+(1, 'b865f24ced688b6932c6131d644f7975', 14),
+(1, 'f83893a4322289818328b42468728314', 15),
+(1, '92d04dbea98658cd07d9b2c29356ab52', 16),
+(1, '9e2975f1cb67bd0ff76c83e9d2b3c7a4', 17),
+(1, 'cf9eaf021b3d78cd2491be771abbe13b', 18),
+(1, '3a04e1eec93c224af777c7f951dd2d98', 19),
+(1, 'b20b9c8f2bdb43086ec9d46b9a6bada0', 20),
+(1, '85ad23ce6d40d27656a9cbd54e3f6ab4', 21),
+(1, 'ab32d01ba3d2a557c51c7b26f08f61f0', 22),
+(1, '989118dd28898b84962858ec44e2f7e2', 23),
+(1, 'bf7a666e3d37a1d7388ed838bb6f020a', 24),
+(1, 'd30cf94ad68c50f660a31bcf637c76bd', 25),
+(1, '053161d0a98c27ea4a9ee5e0e404e848', 26),
+(1, 'c9cf0764a339f79b783087db605fe8f0', 27),
+(1, 'd5c4c3513cb8869643f42e1350474ad3', 28),
+(1, '0a8a99dc8ffafaf837f0755fa2e54009', 29),
+(1, 'fcea59940c64e1257ce9264254ba8e03', 30),
+(1, '63c18435bbeef45e6fafc947ca5b1ff5', 31),
+(1, '6620e5b50f4f1ee7358bbe0d609edfe2', 32),
+(1, '265a22dc97ee56e2e1a4ce525f3e79c2', 33),
+(1, '9adb1f7ec660e0f2643cd151fad69b82', 34),
+(1, '6443a7047288aab14ee4694ebe87b79b', 35),
+(1, '80a356c273b7d52049a23e91e36aa9f0', 36),
+(1, '0f73fa30f7f20d5235dcac8eabff472d', 37),
+(1, '5a4b69e047a949744a81715a83f20ee3', 38),
+(1, '3407c6f6ee7b1970c094b0c3144bf54a', 39),
+(1, '9a4fbaee92151f345680abe2fc9ee747', 40),
+(1, '1a049f2fe3a59d840c5157600c8743b5', 41),
+(1, 'd6f4b7dd36e9ddb4e654d2d6836ff15e', 42),
+(1, 'be090ec6b8d3dcd206c29c2620b673d6', 43),
+(1, '2de6e630dc402b3f1dd565e25376fa6e', 44),
+(1, '8527cc85dfb71405e0c509d60d530fa1', 45),
+(1, '495b964e442823eb695e6e73d59ed100', 46),
+(1, '09bfeb3ac6fc284b0ef8ab47c055e2b0', 47),
+(1, 'a5f09a3e3aae08d5576d8c24656ea1da', 48),
+(1, 'e7110b6577a733df0ef96559c72fdc3a', 49),
+(1, '61a8dacb968a51d010af72cd9ba0fcc7', 50),
+(1, '126c7aa03977ba6179c14d1286d95987', 51),
+(1, '7bafa94e8b192112fcc13285733fed21', 52),
+(1, '55efe418039d6b4c1a0a3033a505a6d5', 53),
+(1, 'd6cd321817e8d2ed7ebb2a7282d7ba08', 54),
+(1, '56f7f540f406a8f4feabdc976ffc02a5', 55),
+(1, '5bd19f7edc9a5aceb165bf982777fbef', 56),
+(1, 'e8f3382284dd8f3c14f74e543cbe1365', 57),
+(1, '8b1bef88f60a945f23695a98ab461a32', 58),
+(1, '34340558fe5254ec8d632b28412c1df6', 59),
+(1, '133c04455080e1e700ea9168eb6e9154', 60),
+(1, '40b939fea1cc7bca69f94ba98a9a8966', 61),
+(1, '17110ec3c9490c2d7212bac578178f82', 62),
+(1, '4013a6b73babbe4ddb7c94308f607981', 63),
+(1, '606aa3c2fd587ba49e06e2ba96634d3e', 64),
+(1, 'f3ddf171f30f886bb65f2eb9b5e9b631', 65),
+(1, 'b441c2178c3ea7b470905972a8542a45', 66),
+(1, '8c0bcc859b936e07125a8a1496fc5cff', 67),
+(1, '37903e02cd6f096b7c18130ef322f245', 68),
+(1, '246a6ba918b31591b8a3bb39e89e57ca', 69),
+(1, 'df864a07605f68f58d454991035887ad', 70),
+(1, '4407108e5fcce7999b4ed6a02235829e', 71),
+(1, '8504e0862ec12cca41caa1f78681ec03', 72),
+(1, '75a3f6fbf821d473bf17025e043a5964', 73),
+(1, '0bc6e109374f04db38ec70d292371d19', 74),
+(1, '5fb96e336c571545a9e3a1afa0ce25c2', 75),
+(1, 'def1a11db7a5899c9b0ba357fc71a37f', 76),
+(1, '581e463cee62d8adafd84b13deeb507d', 77),
+(1, '503a857d480a4ee4c30b132b80e895d1', 78),
+(1, 'cf60d6a32e7a1256f1e69b518fe158ff', 79),
+(1, '980817c5a574eb50f3ab26f390b84b4f', 80),
+(1, '2aa8f43167062239fa955a6fd5bf209b', 81),
+(1, '16318fbae71adefaf80991849a181d11', 82),
+(1, '43fd446c68528a56931f755525b8bafc', 83),
+(1, 'abf514722acd55a48f5c1ec8badd899d', 84),
+(1, 'fb3dd61449de05c2b84d3d75cf346d81', 85),
+(1, '55a069e51045adab0adf4d81ca36011e', 86),
+(1, '5694db971c54e0872defef4a300343f9', 87),
+(1, 'd67f527b382394095a3ca40ac8449904', 88),
+(1, 'db52ed089f2008181515e3f8be54bf90', 89),
+(1, 'f2fffd34cef9a1878754640688487c82', 90),
+(1, 'd6ddd5009d865979febbfb36bc2d99b7', 91),
+(1, '362c2a15b7e8aaf8f1dfefd717fe7e29', 92),
+(1, 'b2c13e3d09706d5fde0243a44058f81c', 93),
+(1, '37d139647cca8827ad1205695a3daa33', 94),
+(1, 'c5732364b28b208d22e0f4cab71b5907', 95),
+(1, '236197f68795dea908d97fa61d8bb1c5', 96),
+(1, 'f54e7bc914d7c5fab66b371399acfd41', 97),
+(1, 'c2563f5a17677676ec45a0d487a2a871', 98),
+(1, '08f30c15ce2135cdf575e71aa9fadee8', 99),
+(1, 'ebd1f487cdb42b7d444b23c68a79918b', 100),
+(1, 'ae2739faa6e4b4c9de6962071be9fec6', 101),
+(1, '29c04079f0a8cdd22d95ff605847ccce', 102),
+(1, '3a0ffffb65779005d55f8d8f3d0a7091', 103),
+(1, '14c06f589bb0a772387888641b52454b', 104),
+(1, 'e0f3f3e0afff4e71d8a2e3a959a8effa', 105),
+(1, '2b843c3751dde9cfadfd392cac900487', 106),
+(1, '8c876d8344b468cfb89a5cc91c4a5218', 107),
+(1, '15917f7b815dce661db415ee1c488813', 108),
+(1, '54fb8b43f54e36e9a6a546d3c45aa4cf', 109),
+(1, 'a9430cf8d75c891c6c27ca6750faee8e', 110),
+(1, 'eb50f5eee7f570e976ab423fb44edde9', 111),
+(1, 'ed48d149f7ffa48d8c7eed5270f48949', 112),
+(1, '6fa37a33a1baea4875c9e342f8091dc3', 113),
+(1, '9ce446680bea61864df9d9fec5d54a93', 114),
+(1, 'f7ea025406d2645f86da52dde64807d0', 115),
+(1, '114fb59b3fee8ebf8c36fd2bf7e59a55', 116),
+(1, 'c1729df7dc9452a496c7be7f043ce88d', 117),
+(1, '92db823664bfa36f7fdde309fe69eb26', 118),
+(1, 'df833bc0ae6fd919e0db280a8f0f5b9e', 119),
+(1, '7d88370d2be9063ef347351781e3ad38', 120),
+(1, '1dbcab635439ff5d3432407fedb94da1', 121),
+(1, 'c79b580b5ba99bdf4669a61379747740', 122),
+(1, 'ce3722d28640ab845396d37933c66b1a', 123),
+(1, 'dd55c007590f65a011febd5c5ec17262', 124),
+(1, 'bb51d1b9fb2ca91fab7e63066f797c97', 125),
+(1, '597e0dc97c39604cbac73a887f89bf94', 126),
+(1, '2ed7517269cece052f7cbe4d8ec6f50c', 127),
+(1, '9c4adad2919051e6fe586354b9af132b', 128),
+(1, '2f9cc67c261ee31a0ecdd154168a987a', 129),
+(1, 'bb1a82b02f0de5a90fcb44edd2cccdf9', 130),
+(1, '9ec7df7febfce66dc43941e2c259904b', 131),
+(1, '1fd38f24222630b30cca1978e2acdb85', 132),
+(1, '9d051739db959a5dfb5b98890b4e6ae6', 133),
+(1, '9ae26319e307759e6e27c85ddff8f5fa', 134),
+(1, '3e3ac2c042b18fcc7f9610f654778740', 135),
+(1, '92aeb6900e0e06603dd846889c5d8ded', 136),
+(1, '096d2a19ddf0911a66c4fe4b41f992c7', 137),
+(1, 'cde2d982ab2caee5f0715c341bcae991', 138),
+(1, 'aa545b027a6fa39e5c278fc366ad9016', 139),
+(1, '1c5b8e85a66a415e39d996b967f92e67', 140),
+(1, '1b4673e2376ecfa02bae442cdc5b3c90', 141),
+(1, '9e542ff1cb9fb17f5f7a60af1fe9e66b', 142),
+(1, '8aa0b348759b2c56e17074b65a50f04a', 143),
+(1, '4931f242aeb4b1b641d813c6a2fbfadd', 144),
+(1, '2e3cfa5057486d0759cee8ee477a1678', 145),
+(1, '40444256345769c7a911e8710f0f5733', 146),
+(1, '54f85dd1796f5a8f5797c3965e8736f4', 147),
+(1, 'd8b2250234456105c3f1452afcffbfd5', 148),
+(1, '038e1f5299c2e7c4c87b54f655429666', 149),
+(1, '2ba74b69dc4671aa0fa705306c7cf072', 150),
+(1, 'fef173c794fec61b65089321673d0370', 151),
+(1, '366eff873b9665852f08b3d134dfd87c', 152),
+(1, '1bdc4632029c88612f481ab75ebc4062', 153),
+(1, 'b3acb1a516ee6949dd0f764200e80287', 154),
+(1, '00d59e67b764386960d8b423567cf9bc', 155),
+(1, 'b78fa5394c788492c5ddcfdbdfe4ae15', 156),
+(1, 'e8a7d9cb2aca242bf3f0c59640ddd620', 157),
+(1, '6059a22be14860cff767d679c98ed1df', 158),
+(1, 'b23ce25c9b6d789953b43d6a7f6f9266', 159),
+(1, '0499534b7a0545e81354fd6e814f4ad3', 160),
+(1, '77ea72cb592979d8aa617f1a06181600', 161),
+(1, '482de10e7a8d0688dd9ac27eef4cddb8', 162),
+(1, '570b9443265e8614befdb3c84bf66095', 163),
+(1, '5f572916554e6053ee9a59c2fbadb7c7', 164),
+(1, '79a9316e23134dc7632c51ec305af756', 165),
+(1, '4656a71e7802cff7757cc3e4a9281927', 166),
+(1, 'f986b0490311d17c909d8b8cceb6756d', 167),
+(1, '11c183d403657cafd9fe8f22921686cc', 168),
+(1, 'cbbf9ec130e723aa24e600d16471570b', 169),
+(1, 'e9a45f7858204bfc7b9ae196c3d9d7a7', 170),
+(1, '47097d94df0cb3b5f4c281e4d1077ce5', 171),
+(1, '09241a765e64d1f2280a888f255add7a', 172),
+(1, 'fb8f0d2dd52a205aa367d3f1b549f9c1', 173),
+(1, '97f260fceebdedcdc9f5ab99fef8a78e', 174),
+(1, '86a91d11239bc96df9c917c99bb19050', 175),
+(1, 'a1bac8f28c3378e56d0485cc880098d2', 176),
+(1, 'f563a6a7d1a20fc4dddca1dab785bd1f', 177),
+(1, '49159657f8644de0ea1c2dca29b11482', 178),
+(1, 'd94065d725bbdeedeebe1c5a8693873f', 179),
+(1, '03563f756de4a637b2bfa6c33b9ba3cd', 180),
+(1, '97b51c9df42b8e8754a6127073b6cf82', 181),
+(1, '865a527fc94bf609d2a86e6e7c2ca481', 182),
+(1, 'cb877f34c2a65534a458bf54ea823f08', 183),
+(1, '9d3b3d092d27b3ed0b76281225f98bf7', 184),
+(1, 'b27e8ad3b0d538abe256169cb3363b4a', 185),
+(1, '51c3819eb7ced02c46c22acda2c74205', 186),
+(1, '7ba6a2f608295d678aea4195b46a0219', 187),
+(1, 'db9785d69f04ab00266814b8e992da91', 188),
+(1, '2db8d58d0f3062f9d7e1259492652c1d', 189),
+(1, 'e2065c855899f8ed49e6ce89162683e8', 190),
+(1, '1319c9749c2ffdc6832e5aa98b54e659', 191),
+(1, 'fd34b1095c30bde6ce7aab8ec0683bb7', 192),
+(1, '7ad81d348b958714dea07ece9fd522c2', 193),
+(1, '2fb87de8954d03f91e6771846e2a8d6f', 194),
+(1, '6597cb67e75e5991ceb2afe448db3c35', 195),
+(1, '6c2619df4ae1e9377d47116d88eeeb5d', 196),
+(1, 'd25074818bd0a543482f55ff21330676', 197),
+(1, '8adf0a8c2be11dac6ec490bfebcc8d30', 198),
+(1, 'b1b6e78d3e26e1aed5a408ddb975547b', 199),
+(1, '1c8437de40bafcd6ba379ac53f1b8169', 200),
+(1, 'bb03494e04ca2cd3918de70f74498fcf', 201),
+(1, 'e00752cbe957fb707c6a6ac574452cd5', 202),
+(1, 'e1360e0256b6ce58c3f8591bb93a70dc', 203),
+(1, 'fd6c48fc6006b8276d12f8859daec350', 204),
+(1, '75b513f7b143b20a53c9a6d415bb02d1', 205),
+(1, 'ed2eff479f6e1333c83561125f8e64b9', 206),
+(1, '90a269caf890d1fae8fe57dc4ed19e56', 207),
+(1, 'b553b566dbb94a712641f346db436ad4', 208),
+(1, '76f9f24634d3590cbdc3dbfc2d84cb6c', 209),
+(1, '30c94206e5aac9e6936c42a2f60cc426', 210),
+(1, '692915e5a20c90c4c0d73f3db9c1154a', 211),
+(1, '836c2fa67a8d62c3b8c04bccd2ae31be', 212),
+(1, 'a514416bfa9ec0a46b98de23194398a4', 213),
+(1, '6abd9038780dd1f005df9fc31feb1560', 214),
+(1, 'e35237b0b1595f75b6e641669d67cfce', 215),
+(1, 'cbd15cc24b2892beb3444780fdbb19be', 216),
+(1, '26c91da37713481245b08bd74565f06b', 217),
+(1, '95cbbc0c9529592bee188dd2f3e7b8c7', 218),
+(1, 'f5d7f236ed25c850ec5e2f462eed7c4f', 219),
+(1, 'e5d7e266169ff20581413412b970c66c', 220),
+(1, 'e1e60351f1d5c9b62ad373e6343ccb78', 221),
+(1, '8c4a8899f75122dece210f306fc50de7', 222),
+(1, '70ec05034e36531f8cc15a995ee2a1ca', 223),
+(1, '1a9f9cbdf1adedcba49592fb48fb23f1', 224),
+(1, '1369618c25eb074761f2f9dd12cd082e', 225),
+(1, 'bcdd94a0c273aa1b0700de7e5e4213d0', 226),
+(1, '65248f0a7165ab82ee03e312f20526e7', 227),
+(1, '4c494372395a62e213ebecfe4dff643b', 228),
+(1, 'c270582f05844d3adef31a226386100f', 229),
+(1, '97047972139aa66b73ad3af35ca45b44', 230),
+(1, '40242bfc27f4b1a1af097bf88cc9619d', 231),
+(1, '604cabd06bd3b31fc5b8dd3802fcde2c', 232),
+(1, '687dc02afb37f19b29ceb2339eb52676', 233),
+(1, '581294986a59b3403d05de69c6be96d7', 234),
+(1, '38ec3bbef46ad213aef4731fe049f5f2', 235),
+(1, 'b50c594fc7e5603d5178b33109f6fb02', 236),
+(1, '7988cb4fd66dd5acf3c8e4ed87a1ec5f', 237),
+(1, '53a805ec4780ff7641acdfe374373e80', 238),
+(1, '226b3556a1804e38029fd237a66b1db0', 239),
+(1, 'b36725f672acde360df83bb877028d73', 240),
+(1, '6ba01a6240c18488040f9385de557081', 241),
+(1, '3b35edddcab0cac1aec5615dc86cd1f8', 242),
+(1, '8a9d0cf19bd5111f45b75ac99ef298e8', 243),
+(1, '1fa8e36ea62adfd1dbe8943a5cbd7457', 244),
+(1, '38304b8fd2f1552ee09b14be3c1d3f2e', 245),
+(1, 'acc5ce8a0c88cb4c5f74140e36f83e1f', 246),
+(1, '948fb19cc466d95cd9fc7188ad5411bf', 247),
+(1, 'c5024d345c3453b92b627bfc212dbf0e', 248),
+(1, 'ef5ca491b2c6298026a4eb425ec63c6b', 249),
+(1, '9b661821b671d44f7221a4a36ab80d8d', 250);
+
+select setval('custom_list_id_seq', (select max(id) from custom_list));
@@ -0,0 +1,296 @@
+//! Who may change the song list and covers.
+//!
+//! Admins are either logged in users who are members of one of `ADMIN_GROUPS` or listed in the
+//! `admin_user` table, or scripts that send an API token as `Authorization: Bearer <token>`. API
+//! tokens are limited to the [Scope]s that they were created with, and can be revoked. Personal
+//! access tokens can't be used for admin requests.
+//!
+//! The groups of a user are only fetched from Gamma when they log in, so they only make the user
+//! an admin for [GROUP_MAX_AGE_MINUTES](crate::route::auth::GROUP_MAX_AGE_MINUTES) after that.
+
+use std::{fmt, marker::PhantomData, sync::Arc};
+
+use actix_web::{
+    dev::Payload,
+    error::{ErrorForbidden, ErrorInternalServerError, ErrorUnauthorized},
+    web, FromRequest, HttpRequest,
+};
+use chrono::NaiveDateTime;
+use clap::{Args, ValueEnum};
+use diesel::{
+    backend::Backend,
+    deserialize::{FromSql, FromSqlRow},
+    sql_types::Text,
+    Queryable, Selectable,
+};
+use futures::future::LocalBoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error,
+    route::auth::User,
+    storage::{self, Storage},
+    token::{self, PERSONAL_TOKEN_PREFIX},
+    Opt,
+};
+
+/// Prefix of API tokens, to make them easy to recognize.
+const API_TOKEN_PREFIX: &str = "singit_api_";
+
+/// Something that an API token may be used for.
+#[derive(Serialize, Deserialize, ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// Replacing and syncing the song list.
+    Songs,
+
+    /// Uploading and deleting song covers.
+    Covers,
+
+    /// Listing song list revisions, and rolling back to them.
+    Revisions,
+}
+
+/// The scopes of an API token, stored as comma separated text, i.e. as [Scopes::to_string].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, FromSqlRow)]
+#[serde(transparent)]
+pub struct Scopes(pub Vec<Scope>);
+
+/// A token that lets scripts make admin requests.
+#[derive(Serialize, Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = crate::schema::api_token)]
+pub struct ApiToken {
+    pub id: i32,
+    pub name: String,
+    pub scopes: Scopes,
+
+    /// CID of the admin that created the token, or `cli` if it was made with `create-token`.
+    pub created_by: String,
+
+    /// In UTC, like the other times.
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+/// A user that has been made admin, regardless of their groups.
+#[derive(Serialize, Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = crate::schema::admin_user)]
+pub struct AdminUser {
+    pub cid: String,
+
+    /// CID of the admin that added them.
+    pub added_by: String,
+
+    /// In UTC.
+    pub added_at: NaiveDateTime,
+}
+
+/// Says which [Scope] an API token needs to be used as an [Admin].
+pub trait RequiredScope {
+    /// `None` if API tokens may not be used at all.
+    const SCOPE: Option<Scope>;
+}
+
+/// Markers for [Admin], one for each [Scope].
+pub mod scope {
+    use super::{RequiredScope, Scope};
+
+    /// Only logged in admins, e.g. for managing admins and API tokens.
+    pub struct LoggedIn;
+
+    pub struct Songs;
+    pub struct Covers;
+    pub struct Revisions;
+
+    impl RequiredScope for LoggedIn {
+        const SCOPE: Option<Scope> = None;
+    }
+
+    impl RequiredScope for Songs {
+        const SCOPE: Option<Scope> = Some(Scope::Songs);
+    }
+
+    impl RequiredScope for Covers {
+        const SCOPE: Option<Scope> = Some(Scope::Covers);
+    }
+
+    impl RequiredScope for Revisions {
+        const SCOPE: Option<Scope> = Some(Scope::Revisions);
+    }
+}
+
+/// An admin, or an API token with the scope `S`.
+///
+/// Extracting this fails with 401 if the request isn't authenticated, and with 403 if it is, but
+/// not as an admin or with the right scope.
+pub struct Admin<S = scope::LoggedIn> {
+    /// CID of the admin, or `token:<name>` for API tokens. Recorded as who made changes.
+    pub name: String,
+
+    scope: PhantomData<S>,
+}
+
+impl<S: RequiredScope> FromRequest for Admin<S> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+
+        Box::pin(async move {
+            let storage = web::Data::<dyn Storage>::extract(&req).await?;
+
+            let name = match token::bearer(&req) {
+                Some(token) if token.starts_with(PERSONAL_TOKEN_PREFIX) => {
+                    return Err(ErrorForbidden(
+                        "Personal access tokens can't be used for admin requests",
+                    ));
+                }
+                Some(token) => authorize_token(&**storage, token, S::SCOPE).await?,
+                None => {
+                    let user = User::extract(&req).await?;
+                    authorize_user(&req, &**storage, user).await?
+                }
+            };
+
+            Ok(Admin {
+                name,
+                scope: PhantomData,
+            })
+        })
+    }
+}
+
+/// Check that an API token is valid and has `scope`, and return its admin name.
+async fn authorize_token(
+    storage: &dyn Storage,
+    token: &str,
+    scope: Option<Scope>,
+) -> actix_web::Result<String> {
+    let Some(scope) = scope else {
+        return Err(ErrorForbidden("This requires logging in as an admin"));
+    };
+
+    let token = storage
+        .use_api_token(&token::hash(token))
+        .await
+        .map_err(error::Error::from)?
+        .ok_or_else(|| ErrorUnauthorized("Invalid API token"))?;
+
+    if !token.scopes.0.contains(&scope) {
+        return Err(ErrorForbidden(format!(
+            "API token {:?} doesn't have the {scope} scope",
+            token.name
+        )));
+    }
+
+    Ok(format!("token:{}", token.name))
+}
+
+/// Check that a logged in user is an admin, and return their CID.
+async fn authorize_user(
+    req: &HttpRequest,
+    storage: &dyn Storage,
+    user: User,
+) -> actix_web::Result<String> {
+    let opt = req
+        .app_data::<web::Data<Arc<Opt>>>()
+        .ok_or_else(|| ErrorInternalServerError("Missing server config"))?;
+
+    let in_admin_group = (user.groups.iter()).any(|group| opt.admin_groups.contains(group));
+    let groups_are_recent = user.groups_are_recent();
+
+    let cid = user.info.cid;
+    if (in_admin_group && groups_are_recent)
+        || storage.is_admin(&cid).await.map_err(error::Error::from)?
+    {
+        Ok(cid)
+    } else if in_admin_group {
+        log::info!("User {cid:?} needs to log in again to be admin through their groups");
+        Err(ErrorForbidden(
+            "Log in again to be admin through your Gamma groups",
+        ))
+    } else {
+        log::warn!("User {cid:?} tried to do something that requires admin");
+        Err(ErrorForbidden("Not an admin"))
+    }
+}
+
+#[derive(Args)]
+pub struct CreateTokenOpt {
+    /// What the token is for, e.g. `ultrascraper`.
+    pub name: String,
+
+    /// What the token may be used for. Can be given more than once.
+    #[clap(long = "scope", value_enum, required = true)]
+    pub scopes: Vec<Scope>,
+}
+
+/// Run the `create-token` subcommand, which prints the new token.
+pub async fn create_token(opt: &Opt, create: &CreateTokenOpt) -> eyre::Result<()> {
+    let (storage, _) = storage::setup(opt).await?;
+
+    let token = generate_token();
+    let scopes = Scopes(create.scopes.clone());
+    let info = storage
+        .create_api_token(
+            create.name.clone(),
+            scopes,
+            token::hash(&token),
+            "cli".into(),
+        )
+        .await?;
+
+    eprintln!("Created API token {:?} with id {}", info.name, info.id);
+    println!("{token}");
+
+    Ok(())
+}
+
+/// Make a new random API token.
+pub fn generate_token() -> String {
+    token::generate(API_TOKEN_PREFIX)
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scope = match self {
+            Scope::Songs => "songs",
+            Scope::Covers => "covers",
+            Scope::Revisions => "revisions",
+        };
+        f.write_str(scope)
+    }
+}
+
+impl fmt::Display for Scopes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, scope) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{scope}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<DB> FromSql<Text, DB> for Scopes
+where
+    DB: Backend,
+    String: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> diesel::deserialize::Result<Self> {
+        let scopes = String::from_sql(bytes)?;
+        let scopes = (scopes.split(',').filter(|scope| !scope.is_empty()))
+            .map(|scope| match scope {
+                "songs" => Ok(Scope::Songs),
+                "covers" => Ok(Scope::Covers),
+                "revisions" => Ok(Scope::Revisions),
+                _ => Err("Unrecognized API token scope"),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Scopes(scopes))
+    }
+}
@@ -0,0 +1,122 @@
+//! Validating and resizing uploaded song covers.
+
+use std::path::PathBuf;
+
+use actix_web::mime::{self, Mime};
+use image::{
+    codecs::{jpeg::JpegEncoder, webp::WebPEncoder},
+    DynamicImage,
+};
+
+/// The sizes, in pixels, of the thumbnails that are made of every cover.
+pub const THUMBNAIL_SIZES: [u32; 3] = [64, 128, 256];
+
+const JPEG_QUALITY: u8 = 85;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThumbnailFormat {
+    Jpeg,
+    WebP,
+}
+
+/// One of the stored versions of a cover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverVariant {
+    /// The cover as it was uploaded.
+    Original,
+
+    /// A downscaled copy, at most `size` pixels wide and high.
+    Thumbnail { size: u32, format: ThumbnailFormat },
+}
+
+/// The file of one of the versions of a cover, for serving.
+pub struct CoverFile {
+    pub path: PathBuf,
+    pub mime_type: Mime,
+}
+
+/// An uploaded cover, and its thumbnails.
+///
+/// Every thumbnail size exists as JPEG, and as WebP if that is smaller.
+pub struct ProcessedCover {
+    /// MD5 hash of the uploaded cover, as a hex string.
+    pub hash: String,
+
+    pub original: Vec<u8>,
+
+    pub thumbnails: Vec<(CoverVariant, Vec<u8>)>,
+}
+
+impl ThumbnailFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "jpg",
+            ThumbnailFormat::WebP => "webp",
+        }
+    }
+}
+
+impl CoverVariant {
+    /// Pick the smallest thumbnail that is at least `size` pixels, or the original if none is.
+    pub fn for_size(size: Option<u32>, format: ThumbnailFormat) -> Self {
+        let Some(size) = size else {
+            return CoverVariant::Original;
+        };
+
+        THUMBNAIL_SIZES
+            .into_iter()
+            .find(|&thumbnail_size| thumbnail_size >= size)
+            .map_or(CoverVariant::Original, |size| CoverVariant::Thumbnail {
+                size,
+                format,
+            })
+    }
+
+    /// Name of the file that the variant is stored in.
+    pub fn file_name(&self) -> String {
+        match self {
+            CoverVariant::Original => "original".to_string(),
+            CoverVariant::Thumbnail { size, format } => format!("{size}.{}", format.extension()),
+        }
+    }
+}
+
+/// Check that `original` is an image, and make thumbnails of it.
+///
+/// This is slow, so don't call it on an async thread.
+pub fn process(original: Vec<u8>) -> image::ImageResult<ProcessedCover> {
+    let image = image::load_from_memory(&original)?;
+
+    let mut thumbnails = vec![];
+    for size in THUMBNAIL_SIZES {
+        let thumbnail = DynamicImage::ImageRgb8(image.thumbnail(size, size).into_rgb8());
+
+        let mut jpeg = vec![];
+        thumbnail.write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY))?;
+
+        // only lossless WebP encoding is available, which often loses to JPEG for photos
+        let mut webp = vec![];
+        thumbnail.write_with_encoder(WebPEncoder::new_lossless(&mut webp))?;
+        if webp.len() < jpeg.len() {
+            let format = ThumbnailFormat::WebP;
+            thumbnails.push((CoverVariant::Thumbnail { size, format }, webp));
+        }
+
+        let format = ThumbnailFormat::Jpeg;
+        thumbnails.push((CoverVariant::Thumbnail { size, format }, jpeg));
+    }
+
+    Ok(ProcessedCover {
+        hash: format!("{:?}", md5::compute(&original)),
+        original,
+        thumbnails,
+    })
+}
+
+/// Guess the MIME type of a stored cover from its first few bytes.
+pub fn mime_type(header: &[u8]) -> Mime {
+    image::guess_format(header)
+        .ok()
+        .and_then(|format| format.to_mime_type().parse().ok())
+        .unwrap_or(mime::APPLICATION_OCTET_STREAM)
+}
@@ -1,24 +1,76 @@
 use std::fmt::{Debug, Display};
 
-use actix_web::{http::StatusCode, ResponseError};
+use actix_web::{
+    http::{header::LOCATION, StatusCode},
+    HttpResponse, ResponseError,
+};
 use diesel_async::pooled_connection::deadpool::PoolError;
 
 pub type Result<R> = core::result::Result<R, Error>;
 
 pub struct Error {
     error: eyre::Report,
+
+    /// Set if the error happened while logging in. These redirect back to the frontend, instead
+    /// of responding with 500.
+    login_error: Option<LoginError>,
+}
+
+/// Why logging in failed. Sent to the frontend as `/?login_error=<code>`, which it shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginError {
+    /// Gamma couldn't be reached, or didn't accept our requests.
+    GammaUnavailable,
+
+    /// The user didn't let singIT log them in with Gamma.
+    Denied,
+
+    /// There was no login in progress, e.g. because the session expired, or because an old tab
+    /// was used.
+    Expired,
+
+    /// The redirect from Gamma didn't match the login in progress.
+    Rejected,
+
+    /// The session cookie couldn't be read or written.
+    Session,
+}
+
+impl Error {
+    /// Make an error that sends the user back to the frontend with `kind`.
+    pub fn login(kind: LoginError, error: eyre::Report) -> Self {
+        Self {
+            error,
+            login_error: Some(kind),
+        }
+    }
+}
+
+impl LoginError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            LoginError::GammaUnavailable => "gamma_unavailable",
+            LoginError::Denied => "denied",
+            LoginError::Expired => "expired",
+            LoginError::Rejected => "rejected",
+            LoginError::Session => "session",
+        }
+    }
 }
 
 impl From<eyre::Report> for Error {
     fn from(error: eyre::Report) -> Self {
-        Self { error }
+        Self {
+            error,
+            login_error: None,
+        }
     }
 }
 
 impl From<PoolError> for Error {
     fn from(error: PoolError) -> Self {
         let error = eyre::Report::new(error).wrap_err("Failed to get database connection");
-        Self { error }
+        Self::from(error)
     }
 }
 
@@ -36,11 +88,20 @@ impl Display for Error {
 
 impl ResponseError for Error {
     fn status_code(&self) -> StatusCode {
-        StatusCode::INTERNAL_SERVER_ERROR
+        match self.login_error {
+            Some(_) => StatusCode::TEMPORARY_REDIRECT,
+            None => StatusCode::INTERNAL_SERVER_ERROR,
+        }
     }
 
     fn error_response(&self) -> actix_web::HttpResponse<actix_web::body::BoxBody> {
         log::error!("{:?}", self.error);
-        actix_web::HttpResponse::new(self.status_code())
+
+        match self.login_error {
+            Some(kind) => HttpResponse::build(self.status_code())
+                .insert_header((LOCATION, format!("/?login_error={}", kind.code())))
+                .finish(),
+            None => HttpResponse::new(self.status_code()),
+        }
     }
 }
@@ -0,0 +1,57 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// How many events may be buffered for a slow client before it has to resync.
+const CAPACITY: usize = 64;
+
+/// A change that connected clients should know about.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// Sent first on every new subscription, so that reconnecting clients know to reload.
+    Connected,
+
+    /// The song list was replaced.
+    SongsReplaced,
+
+    /// A custom list was created, renamed or deleted.
+    CustomListsChanged,
+
+    /// A song was added to or removed from a custom list.
+    CustomListChanged { list: i32 },
+
+    /// A queue session or its entries were changed.
+    QueueChanged { session: i32 },
+
+    /// Some events were missed, and the client should reload everything.
+    Resync,
+}
+
+/// Broadcasts [Event]s to everyone that is subscribed to `/events`.
+#[derive(Clone)]
+pub struct Events {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Events {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        Events { sender }
+    }
+
+    /// Send an event to all subscribers. Does nothing if no one is listening.
+    pub fn send(&self, event: Event) {
+        log::debug!("broadcasting {event:?}");
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,47 @@
+//! Requests to the client API of Gamma, authenticated with `GAMMA_API_KEY`.
+
+use eyre::Context;
+use gamma_rust_client::config::GammaConfig;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GammaGroup {
+    name: String,
+    super_group: GammaSuperGroup,
+}
+
+#[derive(Deserialize)]
+struct GammaSuperGroup {
+    name: String,
+}
+
+/// Get the names of the groups that a user is a member of, and of their super groups.
+///
+/// E.g. a member of `digit23` gets both `"digit23"` and `"digit"`.
+pub async fn user_groups(config: &GammaConfig, user_id: &str) -> eyre::Result<Vec<String>> {
+    let url = format!("{}/api/client/v1/groups/for/{user_id}", config.gamma_url);
+
+    let groups: Vec<GammaGroup> = reqwest::Client::new()
+        .get(&url)
+        .header(
+            "Authorization",
+            format!("pre-shared {}", config.gamma_api_key),
+        )
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .wrap_err("Failed to request groups from gamma")?
+        .json()
+        .await
+        .wrap_err("Failed to deserialize groups from gamma")?;
+
+    let mut names: Vec<String> = groups
+        .into_iter()
+        .flat_map(|group| [group.name, group.super_group.name])
+        .collect();
+    names.sort();
+    names.dedup();
+
+    Ok(names)
+}
@@ -1,38 +1,53 @@
+pub mod admin;
+pub mod cover;
 pub mod db;
 pub mod error;
+pub mod events;
+pub mod gamma;
+pub mod revision;
 pub mod route;
 pub mod schema;
 pub mod serialize;
+pub mod storage;
+pub mod token;
 pub mod util;
 
 use std::{
-    fs::{self, remove_file},
-    future::{ready, Ready},
+    io::ErrorKind,
     path::{Path, PathBuf},
-    sync::Arc,
 };
 
 use actix_files::NamedFile;
 use actix_web::{
-    delete,
-    error::{ErrorInternalServerError, ErrorUnauthorized},
-    get, put,
+    delete, get,
+    http::header::{Accept, Header, HeaderValue, VARY},
+    put, routes,
+    rt::task::spawn_blocking,
     web::{self, Json, Query},
-    FromRequest, HttpRequest,
+    HttpRequest, HttpResponse,
 };
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 use diesel::{
-    prelude::Insertable, upsert::excluded, ExpressionMethods, QueryDsl, Queryable, Selectable,
-    SelectableHelper,
+    backend::Backend,
+    deserialize::{FromSql, FromSqlRow},
+    expression::AsExpression,
+    prelude::Insertable,
+    serialize::{Output, ToSql},
+    sql_types::Text,
+    upsert::excluded,
+    ExpressionMethods, Queryable, Selectable,
 };
-use diesel_async::{AsyncConnection, RunQueryDsl};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use eyre::Context;
 use serde::{Deserialize, Serialize};
 use serialize::Ser;
-use singit_lib::PutSongs;
+use singit_lib::{CoverManifest, DiffSong, PutSongs, SongsDiff};
 use util::PathSafeString;
 
-use crate::db::DbPool;
+use crate::admin::{scope, Admin};
+use crate::cover::{CoverVariant, ThumbnailFormat};
+use crate::events::{Event, Events};
+use crate::storage::Storage;
 
 #[derive(
     Serialize,
@@ -66,16 +81,237 @@ pub struct Song {
     pub duet_singer_2: Option<String>,
 }
 
+/// A song as uploaded to `PUT /songs`.
+///
+/// The lyrics are only used for searching, and aren't included in the song list.
+#[derive(
+    Serialize, Deserialize, Queryable, Selectable, Insertable, Debug, Clone, Default, PartialEq,
+)]
+#[diesel(table_name = crate::schema::song)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewSong {
+    #[serde(flatten)]
+    #[diesel(embed)]
+    pub song: Song,
+    pub lyrics: Option<String>,
+
+    /// A hash of the metadata and cover of the song, computed by the uploader.
+    ///
+    /// Used by `POST /songs/sync` to tell which songs have changed.
+    pub content_hash: Option<String>,
+
+    /// A hash of the cover image, computed by the uploader.
+    pub cover_hash: Option<String>,
+}
+
+impl From<&Song> for DiffSong {
+    fn from(song: &Song) -> Self {
+        DiffSong {
+            song_hash: song.song_hash.clone(),
+            title: song.title.clone(),
+            artist: song.artist.clone(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Queryable, Selectable, Debug, Clone, Default)]
 #[diesel(table_name = crate::schema::custom_list)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct CustomList {
     pub id: i32,
     pub name: String,
+
+    /// CID of the user that created the list, and may edit it.
+    pub owner: String,
+
+    pub visibility: Visibility,
+
+    /// Random token that lets anyone with a link see the list while it's unlisted. Only shown to
+    /// the users that may edit the list, see [route::custom_list::ListInfo].
+    #[serde(skip)]
+    pub share_token: String,
+}
+
+#[derive(
+    Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    /// Only the owner and invited editors may see the list.
+    Private,
+
+    /// Anyone with a link may see the list, but it isn't shown in the index.
+    Unlisted,
+
+    /// Anyone may see the list, and it's shown in the index.
+    #[default]
+    Public,
+}
+
+/// Someone other than the owner that may edit a custom list.
+#[derive(Serialize, Deserialize, Queryable, Selectable, Insertable, Debug, Clone)]
+#[diesel(table_name = crate::schema::custom_list_editor)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct CustomListEditor {
+    #[serde(skip)]
+    pub list_id: i32,
+
+    pub kind: EditorKind,
+
+    /// A CID or the name of a Gamma group, depending on `kind`.
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "snake_case")]
+pub enum EditorKind {
+    /// A single user, identified by their CID.
+    User,
+
+    /// All members of a Gamma group or super group, e.g. `digit23` or `digit`.
+    Group,
+}
+
+#[derive(Serialize, Deserialize, Queryable, Selectable, Debug, Clone, Default)]
+#[diesel(table_name = crate::schema::queue_session)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct QueueSession {
+    pub id: i32,
+    pub name: String,
+
+    /// CID of the user that created the session, and may manage its queue.
+    pub owner: String,
 }
 
-/// Get index.html on `/`
+#[derive(Serialize, Deserialize, Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = crate::schema::queue_entry)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct QueueEntry {
+    pub id: i32,
+    pub session_id: i32,
+    pub song_hash: String,
+
+    /// Display name of the singer.
+    pub singer: String,
+
+    /// CID of the singer, if they were logged in when queueing.
+    pub singer_cid: Option<String>,
+
+    /// Entries are sung in ascending order of position.
+    pub position: i32,
+
+    pub state: QueueEntryState,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueEntryState {
+    /// The entry is waiting to be sung.
+    Queued,
+
+    /// The entry has been sung.
+    Done,
+
+    /// The entry was skipped, e.g. because the singer wasn't around.
+    Skipped,
+}
+
+impl<DB> ToSql<Text, DB> for QueueEntryState
+where
+    DB: Backend,
+    str: ToSql<Text, DB>,
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> diesel::serialize::Result {
+        let state = match self {
+            QueueEntryState::Queued => "queued",
+            QueueEntryState::Done => "done",
+            QueueEntryState::Skipped => "skipped",
+        };
+        state.to_sql(out)
+    }
+}
+
+impl<DB> FromSql<Text, DB> for QueueEntryState
+where
+    DB: Backend,
+    String: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> diesel::deserialize::Result<Self> {
+        match String::from_sql(bytes)?.as_str() {
+            "queued" => Ok(QueueEntryState::Queued),
+            "done" => Ok(QueueEntryState::Done),
+            "skipped" => Ok(QueueEntryState::Skipped),
+            _ => Err("Unrecognized queue entry state".into()),
+        }
+    }
+}
+
+impl<DB> ToSql<Text, DB> for Visibility
+where
+    DB: Backend,
+    str: ToSql<Text, DB>,
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> diesel::serialize::Result {
+        let visibility = match self {
+            Visibility::Private => "private",
+            Visibility::Unlisted => "unlisted",
+            Visibility::Public => "public",
+        };
+        visibility.to_sql(out)
+    }
+}
+
+impl<DB> FromSql<Text, DB> for Visibility
+where
+    DB: Backend,
+    String: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> diesel::deserialize::Result<Self> {
+        match String::from_sql(bytes)?.as_str() {
+            "private" => Ok(Visibility::Private),
+            "unlisted" => Ok(Visibility::Unlisted),
+            "public" => Ok(Visibility::Public),
+            _ => Err("Unrecognized custom list visibility".into()),
+        }
+    }
+}
+
+impl<DB> ToSql<Text, DB> for EditorKind
+where
+    DB: Backend,
+    str: ToSql<Text, DB>,
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> diesel::serialize::Result {
+        let kind = match self {
+            EditorKind::User => "user",
+            EditorKind::Group => "group",
+        };
+        kind.to_sql(out)
+    }
+}
+
+impl<DB> FromSql<Text, DB> for EditorKind
+where
+    DB: Backend,
+    String: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> diesel::deserialize::Result<Self> {
+        match String::from_sql(bytes)?.as_str() {
+            "user" => Ok(EditorKind::User),
+            "group" => Ok(EditorKind::Group),
+            _ => Err("Unrecognized custom list editor kind".into()),
+        }
+    }
+}
+
+/// Get index.html on `/`, and on `/song/<hash>` so that links to the page of a song can be opened
+/// directly
+#[routes]
 #[get("/")]
+#[get("/song/{song_hash}")]
 pub async fn root() -> actix_web::Result<NamedFile> {
     let path: &Path = "dist/index.html".as_ref();
     Ok(NamedFile::open(path)?)
@@ -90,155 +326,220 @@ pub async fn index(req: HttpRequest) -> actix_web::Result<NamedFile> {
 
 /// Serve song image directory
 #[get("/songs")]
-pub async fn songs(pool: web::Data<DbPool>) -> error::Result<Ser<Song>> {
-    use schema::song::dsl::*;
+pub async fn songs(storage: web::Data<dyn Storage>) -> error::Result<Ser<Song>> {
+    Ok(Ser(storage.songs().await?))
+}
 
-    let mut db = pool.get().await?;
+/// Get songs that have been removed from the song list.
+///
+/// They're kept so that custom lists and queues that contain them can still show them, so only
+/// the ones that are still in a list or queue are returned.
+#[get("/songs/removed")]
+pub async fn removed_songs(storage: web::Data<dyn Storage>) -> error::Result<Ser<Song>> {
+    Ok(Ser(storage.removed_songs().await?))
+}
 
-    let songs = song
-        .select(Song::as_select())
-        .load(&mut db)
-        .await
-        .wrap_err("Failed to query db for songs")?;
+#[derive(Deserialize)]
+pub struct PutSongsQuery {
+    /// Don't change anything, only respond with what would have changed.
+    #[serde(default)]
+    dry_run: bool,
+}
 
-    Ok(Ser(songs))
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum PutSongsResponse {
+    Done(PutSongs),
+    DryRun(SongsDiff),
 }
 
-/// Replace the song list, i.e. delete and add new songs.
+/// Replace the song list, i.e. remove and add new songs.
+///
+/// Removed songs aren't deleted, but marked with `removed_at` so that custom lists and queues
+/// keep working. They're restored if they are uploaded again.
 ///
-/// This route requires ADMIN_TOKEN.
+/// Responds with [PutSongs], or with a [SongsDiff] if `dry_run` is set.
+///
+/// This route requires an admin, or an API token with the `songs` scope.
 #[put("/songs")]
 pub async fn put_songs(
-    _token: Admin,
-    pool: web::Data<DbPool>,
-    new_songs: web::Json<Vec<Song>>,
-) -> error::Result<Json<PutSongs>> {
-    use schema::song::dsl::*;
-    let mut db = pool.get().await.unwrap();
-
-    let response = db
-        .transaction(|mut db| {
-            Box::pin(async move {
-                // Get list of existing songs
-                let old_songs = song
-                    .select(Song::as_select())
-                    .load(&mut db)
-                    .await
-                    .wrap_err("Failed to select all songs")?;
-
-                // Delete songs which do not appear in songies
-                let mut to_delete = vec![];
-                let new_songs = new_songs.into_inner();
-                let mut songs_updated = 0;
-                for old in old_songs {
-                    let mut delete = true;
-                    for new in &new_songs {
-                        if old.song_hash == *new.song_hash {
-                            delete = false;
-                            songs_updated += 1;
-                            break;
-                        }
-                    }
-                    if delete {
-                        to_delete.push(old.song_hash)
-                    }
-                }
-
-                let songs_added = new_songs.len() - songs_updated;
-
-                // FIXME: this won't work in any custom lists exist with these songs
-                let songs_deleted = diesel::delete(song.filter(song_hash.eq_any(to_delete)))
-                    .execute(&mut db)
-                    .await
-                    .wrap_err("Failed to delete removed songs")?;
-
-                // Upsert remaining songs into the table
-                diesel::insert_into(song)
-                    .values(new_songs)
-                    .on_conflict(song_hash)
-                    .do_update()
-                    .set((
-                        artist.eq(excluded(artist)),
-                        title.eq(excluded(title)),
-                        language.eq(excluded(language)),
-                        genre.eq(excluded(genre)),
-                        year.eq(excluded(year)),
-                        cover.eq(excluded(cover)),
-                        song_hash.eq(excluded(song_hash)),
-                        video.eq(excluded(video)),
-                        bpm.eq(excluded(bpm)),
-                    ))
-                    .execute(&mut db)
-                    .await
-                    .wrap_err("Failed to insert new songs")?;
-
-                eyre::Ok(PutSongs {
-                    songs_added,
-                    songs_deleted,
-                    songs_updated,
-                })
-            })
-        })
+    admin: Admin<scope::Songs>,
+    storage: web::Data<dyn Storage>,
+    events: web::Data<Events>,
+    query: Query<PutSongsQuery>,
+    new_songs: web::Json<Vec<NewSong>>,
+) -> error::Result<Json<PutSongsResponse>> {
+    let dry_run = query.dry_run;
+    let response = storage
+        .replace_songs(new_songs.into_inner(), admin.name, dry_run)
         .await?;
 
+    if !dry_run {
+        events.send(Event::SongsReplaced);
+    }
+
     Ok(Json(response))
 }
 
+/// Insert songs, or update them if they already exist. Songs that were removed are restored.
+pub async fn upsert_songs(db: &mut AsyncPgConnection, new_songs: &[NewSong]) -> eyre::Result<()> {
+    use schema::song::dsl::*;
+
+    // postgres allows at most 65535 parameters per query, so split up large lists
+    for chunk in new_songs.chunks(1000) {
+        diesel::insert_into(song)
+            .values(chunk)
+            .on_conflict(song_hash)
+            .do_update()
+            .set((
+                artist.eq(excluded(artist)),
+                title.eq(excluded(title)),
+                language.eq(excluded(language)),
+                genre.eq(excluded(genre)),
+                year.eq(excluded(year)),
+                cover.eq(excluded(cover)),
+                song_hash.eq(excluded(song_hash)),
+                video.eq(excluded(video)),
+                bpm.eq(excluded(bpm)),
+                duet_singer_1.eq(excluded(duet_singer_1)),
+                duet_singer_2.eq(excluded(duet_singer_2)),
+                lyrics.eq(excluded(lyrics)),
+                content_hash.eq(excluded(content_hash)),
+                cover_hash.eq(excluded(cover_hash)),
+                // always null, which restores songs that were previously removed
+                removed_at.eq(excluded(removed_at)),
+            ))
+            .execute(db)
+            .await
+            .wrap_err("Failed to insert new songs")?;
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct CoverQuery {
+    /// The size in pixels that the cover will be shown at. Omit to get the original.
+    size: Option<u32>,
+}
+
 /// Get song image
+///
+/// Responds with the smallest thumbnail that is at least `size` pixels, as WebP if the client
+/// accepts it.
 #[get("/images/songs/{image}")]
 pub async fn get_song_cover(
+    req: HttpRequest,
     path: web::Path<PathSafeString>,
-    opt: web::Data<Arc<Opt>>,
-) -> actix_web::Result<NamedFile> {
-    let path = path.into_inner().0;
-    let path = opt.covers_dir.join(path);
-    Ok(NamedFile::open(path)?)
+    query: Query<CoverQuery>,
+    storage: web::Data<dyn Storage>,
+) -> error::Result<HttpResponse> {
+    let song_hash = path.into_inner().0;
+
+    let accepts_webp =
+        Accept::parse(&req).is_ok_and(|accept| accept.iter().any(|mime| mime.item == "image/webp"));
+    let format = match accepts_webp {
+        true => ThumbnailFormat::WebP,
+        false => ThumbnailFormat::Jpeg,
+    };
+
+    let variant = CoverVariant::for_size(query.size, format);
+    let Some(cover) = storage.cover_file(&song_hash, variant).await? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    // the cover may have been replaced since its file was found
+    let file = match NamedFile::open_async(&cover.path).await {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(HttpResponse::NotFound().finish()),
+        Err(e) => return Err(eyre::Report::new(e).wrap_err("Failed to open cover").into()),
+    };
+
+    // NamedFile handles caching and range requests. It would make originals attachments, since
+    // they have no file extension.
+    let mut response = file
+        .set_content_type(cover.mime_type)
+        .disable_content_disposition()
+        .into_response(&req);
+    response
+        .headers_mut()
+        .insert(VARY, HeaderValue::from_static("Accept"));
+    Ok(response)
 }
 
-/// Delete all song covers.
+/// List the song covers that are stored, and the hashes of their originals.
 ///
-/// This route requires ADMIN_TOKEN.
+/// Lets uploaders tell which covers are missing or have changed.
+#[get("/images/songs")]
+pub async fn song_cover_manifest(
+    storage: web::Data<dyn Storage>,
+) -> error::Result<Json<CoverManifest>> {
+    Ok(Json(storage.cover_manifest().await?))
+}
+
+/// Delete the cover of a song, and clear it from the song list.
 ///
-/// Returns how many files were deleted.
-#[delete("/images/songs")]
-pub async fn delete_song_covers(
-    _token: Admin,
-    opt: web::Data<Arc<Opt>>,
-) -> actix_web::Result<Json<usize>> {
-    // TODO: make this async
-    let mut count = 0;
-    for f in opt
-        .covers_dir
-        .read_dir()
-        .map_err(ErrorInternalServerError)?
-    {
-        let f = f.map_err(ErrorInternalServerError)?;
-        remove_file(f.path()).map_err(ErrorInternalServerError)?;
-        count += 1;
+/// This route requires an admin, or an API token with the `covers` scope.
+#[delete("/images/songs/{cover}")]
+pub async fn delete_song_cover(
+    _admin: Admin<scope::Covers>,
+    path: web::Path<PathSafeString>,
+    storage: web::Data<dyn Storage>,
+) -> error::Result<HttpResponse> {
+    let song_hash = path.into_inner().0;
+
+    if !storage.delete_cover(&song_hash).await? {
+        return Ok(HttpResponse::NotFound().finish());
     }
 
-    // TODO: consider updating database to clear song covers
+    Ok(HttpResponse::Ok().finish())
+}
 
-    Ok(Json(count))
+/// Delete all song covers, and clear them from the song list.
+///
+/// This route requires an admin, or an API token with the `covers` scope.
+///
+/// Returns how many covers were deleted.
+#[delete("/images/songs")]
+pub async fn delete_song_covers(
+    _admin: Admin<scope::Covers>,
+    storage: web::Data<dyn Storage>,
+) -> error::Result<Json<usize>> {
+    Ok(Json(storage.delete_covers().await?))
 }
 
 /// Upload a song cover.
 ///
-/// This route requires ADMIN_TOKEN.
+/// The cover must be an image. Thumbnails of it are made for `GET /images/songs/{image}`.
+///
+/// This route requires an admin, or an API token with the `covers` scope.
 #[put("/images/songs/{cover}")]
 pub async fn put_song_cover(
-    _token: Admin,
+    _admin: Admin<scope::Covers>,
     path: web::Path<PathSafeString>,
-    opt: web::Data<Arc<Opt>>,
+    storage: web::Data<dyn Storage>,
     cover: web::Bytes,
-) -> actix_web::Result<&'static str> {
-    let path = path.into_inner().0;
-    let path = opt.covers_dir.join(path);
-    fs::write(path, cover).map_err(ErrorInternalServerError)?;
-    Ok("✧*｡٩(ˊᗜˋ*)و✧*｡")
+) -> error::Result<HttpResponse> {
+    let song_hash = path.into_inner().0;
+
+    let processed = spawn_blocking(move || cover::process(cover.to_vec()))
+        .await
+        .wrap_err("Cover processing thread panicked")?;
+
+    let processed = match processed {
+        Ok(processed) => processed,
+        Err(e) => {
+            log::warn!("Invalid cover uploaded for {song_hash}: {e}");
+            return Ok(HttpResponse::BadRequest().body(format!("Invalid image: {e}")));
+        }
+    };
+
+    storage.write_cover(&song_hash, processed).await?;
+    Ok(HttpResponse::Ok().body("✧*｡٩(ˊᗜˋ*)و✧*｡"))
 }
 
 #[derive(Parser)]
+#[clap(subcommand_negates_reqs = true)]
 pub struct Opt {
     /// Address to bind to.
     #[clap(short, long, env = "BIND_ADDRESS", default_value = "0.0.0.0")]
@@ -248,14 +549,37 @@ pub struct Opt {
     #[clap(short, long, env = "BIND_PORT", default_value = "8080")]
     pub port: u16,
 
-    /// Postgresql URL.
+    /// Postgresql URL, or `sqlite://<path>` to use an embedded SQLite database instead. Syncing the
+    /// song list, revisions, lyrics and queues need Postgres, and answer 501 with SQLite.
     #[clap(short, long, env = "DATABASE_URL")]
     pub database_url: String,
 
-    /// Directory where song covers are stored.
+    /// Directory where song covers are stored. Not needed by the `migrate` subcommand.
     #[clap(short, long, env = "COVERS_DIR")]
-    pub covers_dir: PathBuf,
+    pub covers_dir: Option<PathBuf>,
+
+    /// Gamma groups whose members are admins, comma separated.
+    ///
+    /// Members are only admins for an hour after logging in, since their groups are only fetched
+    /// then. More admins can be added with `PUT /admin/users/{cid}`.
+    #[clap(long, env = "ADMIN_GROUPS", value_delimiter = ',')]
+    pub admin_groups: Vec<String>,
+
+    /// Apply pending database migrations on startup.
+    #[clap(long, env = "RUN_MIGRATIONS")]
+    pub run_migrations: bool,
 
+    /// Always set when running the server, the subcommands don't need it.
+    #[clap(flatten)]
+    pub server: Option<ServerOpt>,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Options that are only needed to run the server.
+#[derive(Args)]
+pub struct ServerOpt {
     /// Client ID to auth against gamma.
     #[clap(long, env = "GAMMA_CLIENT_ID")]
     pub gamma_client_id: String,
@@ -279,57 +603,13 @@ pub struct Opt {
     /// The secret key to use when encrypting cookies
     #[clap(long, env = "COOKIE_SECRET_KEY")]
     pub cookie_secret_key: String,
+}
 
-    /// The token required to send destructive requests, e.g. `PUT /songs`.
-    ///
-    /// If this is omitted, those routes will be unavailable.
-    #[clap(long, env = "ADMIN_TOKEN")]
-    pub admin_token: Option<String>,
-}
-
-pub struct Admin;
-
-impl FromRequest for Admin {
-    type Error = actix_web::error::Error;
-    type Future = Ready<Result<Self, Self::Error>>;
-
-    fn from_request(req: &HttpRequest, payload: &mut actix_web::dev::Payload) -> Self::Future {
-        let mut f = || {
-            #[derive(Debug, Deserialize)]
-            struct AdminTokenQuery {
-                token: String,
-            }
-
-            let opt = web::Data::<Arc<Opt>>::from_request(req, payload)
-                .into_inner()
-                .map_err(ErrorInternalServerError)?
-                .into_inner();
-
-            let not_configured = Err(ErrorUnauthorized(
-                "Admin token not configured. Check server config.",
-            ));
-
-            let Some(token) = &opt.admin_token else {
-                return not_configured;
-            };
-
-            if token.is_empty() {
-                return not_configured;
-            }
-
-            let query = Query::<AdminTokenQuery>::from_request(req, payload)
-                .into_inner()
-                // TODO: is this right?
-                .map_err(ErrorUnauthorized)?
-                .into_inner();
-
-            if token == &query.token {
-                Ok(Admin)
-            } else {
-                Err(ErrorUnauthorized("Invalid token"))
-            }
-        };
+#[derive(Subcommand)]
+pub enum Command {
+    /// Create the database if it's missing, apply pending migrations, and exit.
+    Migrate(db::MigrateOpt),
 
-        ready(f())
-    }
+    /// Create an API token, e.g. for ultrascraper, print it, and exit.
+    CreateToken(admin::CreateTokenOpt),
 }
@@ -12,8 +12,12 @@ use dotenv::dotenv;
 use gamma_rust_client::config::GammaConfig;
 
 use singit_srv::{
-    db, delete_song_covers, get_song_cover, index, put_song_cover, put_songs, root, route, songs,
-    Opt,
+    admin, db, delete_song_cover, delete_song_covers,
+    events::Events,
+    get_song_cover, index, put_song_cover, put_songs, removed_songs, root, route,
+    song_cover_manifest, songs,
+    storage::{self, Storage},
+    Command, Opt,
 };
 
 #[actix_web::main]
@@ -22,43 +26,98 @@ async fn main() -> eyre::Result<()> {
     let opt = Arc::new(Opt::parse());
     env_logger::init();
 
+    match &opt.command {
+        Some(Command::Migrate(migrate)) => return db::migrate(&opt, migrate).await,
+        Some(Command::CreateToken(create)) => return admin::create_token(&opt, create).await,
+        None => {}
+    }
+
+    // clap requires the server options when there is no subcommand
+    let server = opt.server.as_ref().expect("server options are required");
+
     let gamma_config = Arc::new(GammaConfig {
-        gamma_client_secret: opt.gamma_client_secret.clone(),
-        gamma_redirect_uri: opt.gamma_redirect_uri.clone(),
-        gamma_client_id: opt.gamma_client_id.clone(),
-        gamma_api_key: opt.gamma_api_key.clone(),
-        gamma_url: opt.gamma_uri.clone(),
+        gamma_client_secret: server.gamma_client_secret.clone(),
+        gamma_redirect_uri: server.gamma_redirect_uri.clone(),
+        gamma_client_id: server.gamma_client_id.clone(),
+        gamma_api_key: server.gamma_api_key.clone(),
+        gamma_url: server.gamma_uri.clone(),
         scopes: "openid profile".into(),
     });
 
-    let db_pool = db::setup(&opt).await?;
+    let (storage, db_pool) = storage::setup(&opt).await?;
+    let storage: web::Data<dyn Storage> = web::Data::from(storage);
+    let events = web::Data::new(Events::new());
+    let secret_key = Key::from(server.cookie_secret_key.as_bytes());
     let app = {
         let opt = Arc::clone(&opt);
         move || {
             let logger = Logger::default();
-            let secret_key = Key::from(opt.cookie_secret_key.as_bytes());
 
             App::new()
                 .wrap(logger)
                 .wrap(SessionMiddleware::new(
                     CookieSessionStore::default(),
-                    secret_key,
+                    secret_key.clone(),
                 ))
                 .app_data(PayloadConfig::new(100_000_000)) // 100 MB
-                .app_data(web::Data::new(db_pool.clone()))
+                .app_data(storage.clone())
                 .app_data(web::Data::new(Arc::clone(&opt)))
                 .app_data(web::Data::new(Arc::clone(&gamma_config)))
+                .app_data(events.clone())
                 .service(root)
                 .service(songs)
+                .service(removed_songs)
+                .service(route::search::search_songs)
                 .service(put_songs)
                 .service(get_song_cover)
+                .service(song_cover_manifest)
                 .service(put_song_cover)
+                .service(delete_song_cover)
                 .service(delete_song_covers)
+                .service(route::admin::list_admins)
+                .service(route::admin::add_admin)
+                .service(route::admin::remove_admin)
+                .service(route::admin::list_tokens)
+                .service(route::admin::create_token)
+                .service(route::admin::revoke_token)
                 .service(route::custom_list::list_all)
+                .service(route::custom_list::create_list)
+                .service(route::custom_list::update_list)
+                .service(route::custom_list::delete_list)
+                .service(route::custom_list::set_editors)
                 .service(route::custom_list::get_list)
                 .service(route::custom_list::insert_entry)
+                .service(route::custom_list::move_entry)
                 .service(route::custom_list::remove_entry)
+                .configure(|cfg| {
+                    // these routes only work with postgres
+                    let Some(db_pool) = &db_pool else {
+                        for (method, path) in route::POSTGRES_ROUTES {
+                            cfg.route(path, web::method(method.clone()).to(route::needs_postgres));
+                        }
+                        return;
+                    };
+                    cfg.app_data(web::Data::new(db_pool.clone()))
+                        .service(route::sync::sync_plan)
+                        .service(route::sync::patch_songs)
+                        .service(route::revision::list_revisions)
+                        .service(route::revision::diff_revisions)
+                        .service(route::revision::rollback)
+                        .service(route::lyrics::search_lyrics)
+                        .service(route::lyrics::song_lyrics)
+                        .service(route::queue::list_sessions)
+                        .service(route::queue::create_session)
+                        .service(route::queue::get_queue)
+                        .service(route::queue::enqueue)
+                        .service(route::queue::move_entry)
+                        .service(route::queue::mark_done)
+                        .service(route::queue::skip);
+                })
+                .service(route::events::subscribe)
                 .service(route::auth::user_info)
+                .service(route::token::list_tokens)
+                .service(route::token::create_token)
+                .service(route::token::revoke_token)
                 .service(route::auth::login_with_gamma)
                 .service(route::auth::gamma_redirect)
                 .service(route::auth::logout)
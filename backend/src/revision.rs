@@ -0,0 +1,334 @@
+//! Revisions of the song list.
+//!
+//! Every change to the song list is stored as a numbered revision, with the state of each changed
+//! song before and after. The song list at any revision can then be restored by undoing the
+//! revisions after it. Revision `0` is the song list as it was before the first revision.
+
+use std::collections::{HashMap, HashSet};
+
+use diesel::{
+    dsl::now, ExpressionMethods, Insertable, QueryDsl, Queryable, Selectable, SelectableHelper,
+};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use eyre::Context;
+use singit_lib::{ChangedSong, DiffSong, FieldChange, SongsDiff};
+
+use crate::{schema, upsert_songs, NewSong, Song};
+
+/// The state of a song before and after a change. `None` if the song wasn't in the list.
+#[derive(Debug, Clone)]
+pub struct SongChange {
+    pub song_hash: String,
+    pub old: Option<NewSong>,
+    pub new: Option<NewSong>,
+}
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = schema::song_revision_change)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+struct ChangeRow {
+    revision_id: i32,
+    song_hash: String,
+    old: Option<serde_json::Value>,
+    new: Option<serde_json::Value>,
+}
+
+/// Figure out what upserting and removing some songs would change.
+///
+/// `old_songs` must contain the current state of every affected song that is in the list.
+///
+/// Songs where only the content or cover hash differs are not counted as changed, see
+/// [changed_fields].
+pub fn song_changes(
+    old_songs: Vec<NewSong>,
+    upserted: &[NewSong],
+    removed: &[String],
+) -> Vec<SongChange> {
+    let mut old_songs: HashMap<String, NewSong> = old_songs
+        .into_iter()
+        .map(|old| (old.song.song_hash.clone(), old))
+        .collect();
+
+    let mut changes = vec![];
+
+    for new in upserted {
+        let old = old_songs.remove(&new.song.song_hash);
+        let changed = match &old {
+            Some(old) => !changed_fields(old, new).is_empty(),
+            None => true,
+        };
+        if changed {
+            changes.push(SongChange {
+                song_hash: new.song.song_hash.clone(),
+                old,
+                new: Some(new.clone()),
+            });
+        }
+    }
+
+    for song_hash in removed {
+        if let Some(old) = old_songs.remove(song_hash) {
+            changes.push(SongChange {
+                song_hash: song_hash.clone(),
+                old: Some(old),
+                new: None,
+            });
+        }
+    }
+
+    changes
+}
+
+/// Store some changes as a new revision, and return its number.
+pub async fn record(
+    db: &mut AsyncPgConnection,
+    created_by: &str,
+    rollback_of: Option<i32>,
+    changes: &[SongChange],
+) -> eyre::Result<i32> {
+    use schema::song_revision::dsl as revision;
+    use schema::song_revision_change::dsl as change;
+
+    let revision_id: i32 = diesel::insert_into(revision::song_revision)
+        .values((
+            revision::created_by.eq(created_by),
+            revision::rollback_of.eq(rollback_of),
+        ))
+        .returning(revision::id)
+        .get_result(db)
+        .await
+        .wrap_err("Failed to insert song revision")?;
+
+    let to_json = |song: &Option<NewSong>| song.as_ref().map(serde_json::to_value).transpose();
+    let rows = changes
+        .iter()
+        .map(|c| {
+            Ok(ChangeRow {
+                revision_id,
+                song_hash: c.song_hash.clone(),
+                old: to_json(&c.old)?,
+                new: to_json(&c.new)?,
+            })
+        })
+        .collect::<serde_json::Result<Vec<_>>>()
+        .wrap_err("Failed to serialize song changes")?;
+
+    // postgres allows at most 65535 parameters per query, so split up large lists
+    for chunk in rows.chunks(1000) {
+        diesel::insert_into(change::song_revision_change)
+            .values(chunk)
+            .execute(db)
+            .await
+            .wrap_err("Failed to insert song revision changes")?;
+    }
+
+    Ok(revision_id)
+}
+
+/// Get the changes that would take the song list from revision `from` to revision `to`.
+///
+/// Works in both directions, i.e. `to` may be older than `from`.
+pub async fn changes_between(
+    db: &mut AsyncPgConnection,
+    from: i32,
+    to: i32,
+) -> eyre::Result<Vec<SongChange>> {
+    use schema::song_revision_change::dsl as change;
+
+    let (first, last) = (from.min(to), from.max(to));
+
+    let rows: Vec<ChangeRow> = change::song_revision_change
+        .filter(change::revision_id.gt(first))
+        .filter(change::revision_id.le(last))
+        .order_by(change::revision_id)
+        .select(ChangeRow::as_select())
+        .load(db)
+        .await
+        .wrap_err("Failed to select song revision changes")?;
+
+    // the state of each song before the first change, and after the last
+    let mut states: HashMap<String, (Option<serde_json::Value>, Option<serde_json::Value>)> =
+        HashMap::new();
+    for row in rows {
+        states
+            .entry(row.song_hash)
+            .and_modify(|(_, new)| *new = row.new.clone())
+            .or_insert((row.old, row.new));
+    }
+
+    let from_json = |song: Option<serde_json::Value>| song.map(serde_json::from_value).transpose();
+    let mut changes = vec![];
+    for (song_hash, (old, new)) in states {
+        if old == new {
+            continue;
+        }
+
+        let (old, new) = if from <= to { (old, new) } else { (new, old) };
+        changes.push(SongChange {
+            song_hash,
+            old: from_json(old).wrap_err("Failed to deserialize song revision")?,
+            new: from_json(new).wrap_err("Failed to deserialize song revision")?,
+        });
+    }
+
+    changes.sort_by(|a, b| a.song_hash.cmp(&b.song_hash));
+
+    Ok(changes)
+}
+
+/// Make some changes to the song list.
+pub async fn apply(db: &mut AsyncPgConnection, changes: &[SongChange]) -> eyre::Result<()> {
+    use schema::song::dsl as song;
+
+    let removed: Vec<&String> = changes
+        .iter()
+        .filter(|c| c.new.is_none())
+        .map(|c| &c.song_hash)
+        .collect();
+
+    diesel::update(song::song)
+        .filter(song::song_hash.eq_any(removed))
+        .filter(song::removed_at.is_null())
+        .set(song::removed_at.eq(now))
+        .execute(db)
+        .await
+        .wrap_err("Failed to mark songs as removed")?;
+
+    let upserted: Vec<NewSong> = changes.iter().filter_map(|c| c.new.clone()).collect();
+    upsert_songs(db, &upserted).await
+}
+
+/// Summarize some changes, for showing to an admin.
+pub fn diff(changes: &[SongChange]) -> SongsDiff {
+    let mut diff = SongsDiff::default();
+
+    for change in changes {
+        match (&change.old, &change.new) {
+            (None, Some(new)) => diff.added.push(DiffSong::from(&new.song)),
+            (Some(old), None) => diff.removed.push(DiffSong::from(&old.song)),
+            (Some(old), Some(new)) => {
+                let changes = changed_fields(old, new);
+                if !changes.is_empty() {
+                    diff.changed.push(ChangedSong {
+                        song: DiffSong::from(&new.song),
+                        changes,
+                    });
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    diff
+}
+
+/// Get the hashes of the songs in `old_songs` that are not in `new_songs`.
+pub fn missing_songs(old_songs: &[NewSong], new_songs: &[NewSong]) -> Vec<String> {
+    let new_hashes: HashSet<&str> = new_songs
+        .iter()
+        .map(|new| new.song.song_hash.as_str())
+        .collect();
+
+    old_songs
+        .iter()
+        .map(|old| &old.song.song_hash)
+        .filter(|old| !new_hashes.contains(old.as_str()))
+        .cloned()
+        .collect()
+}
+
+/// List the fields of a song that differ between two versions of it.
+///
+/// The content and cover hashes are left out, since they only tell uploaders what to sync.
+fn changed_fields(old: &NewSong, new: &NewSong) -> Vec<FieldChange> {
+    fn fields(new_song: &NewSong) -> [(&'static str, Option<&str>); 11] {
+        let NewSong { song, lyrics, .. } = new_song;
+        let Song {
+            song_hash: _,
+            title,
+            artist,
+            cover,
+            language,
+            video,
+            year,
+            genre,
+            bpm,
+            duet_singer_1,
+            duet_singer_2,
+        } = song;
+
+        [
+            ("title", Some(title.as_str())),
+            ("artist", Some(artist.as_str())),
+            ("cover", cover.as_deref()),
+            ("language", language.as_deref()),
+            ("video", video.as_deref()),
+            ("year", year.as_deref()),
+            ("genre", genre.as_deref()),
+            ("bpm", Some(bpm.as_str())),
+            ("duet_singer_1", duet_singer_1.as_deref()),
+            ("duet_singer_2", duet_singer_2.as_deref()),
+            ("lyrics", lyrics.as_deref()),
+        ]
+    }
+
+    fields(old)
+        .into_iter()
+        .zip(fields(new))
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((field, old), (_, new))| FieldChange {
+            field: field.to_string(),
+            old: old.map(str::to_string),
+            new: new.map(str::to_string),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_song(song_hash: &str, title: &str) -> NewSong {
+        NewSong {
+            song: Song {
+                song_hash: song_hash.to_string(),
+                title: title.to_string(),
+                artist: "Artist".to_string(),
+                cover: None,
+                language: None,
+                video: None,
+                year: None,
+                genre: None,
+                bpm: "120".to_string(),
+                duet_singer_1: None,
+                duet_singer_2: None,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn song_changes_ignore_hashes() {
+        let old = vec![new_song("a", "A"), new_song("b", "B"), new_song("c", "C")];
+
+        let mut rehashed = new_song("a", "A");
+        rehashed.content_hash = Some("new".to_string());
+        rehashed.cover_hash = Some("new".to_string());
+        let upserted = [rehashed, new_song("b", "B2"), new_song("d", "D")];
+
+        let changes = song_changes(old, &upserted, &["c".to_string()]);
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|c| (c.song_hash.as_str(), c.old.is_some(), c.new.is_some()))
+            .collect();
+        assert_eq!(
+            summary,
+            [("b", true, true), ("d", false, true), ("c", true, false)]
+        );
+
+        let diff = diff(&changes);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].changes.len(), 1);
+        assert_eq!(diff.changed[0].changes[0].field, "title");
+    }
+}
@@ -1,2 +1,36 @@
+use actix_web::{http::Method, HttpResponse};
+
+pub mod admin;
 pub mod auth;
 pub mod custom_list;
+pub mod events;
+pub mod lyrics;
+pub mod queue;
+pub mod revision;
+pub mod search;
+pub mod sync;
+pub mod token;
+
+/// The routes that need a Postgres database. With SQLite they're answered by [needs_postgres],
+/// rather than by the index page or with 404.
+pub const POSTGRES_ROUTES: &[(Method, &str)] = &[
+    (Method::POST, "/songs/sync"),
+    (Method::PATCH, "/songs"),
+    (Method::GET, "/songs/revisions"),
+    (Method::GET, "/songs/revisions/{from}/diff/{to}"),
+    (Method::POST, "/songs/revisions/{revision}/rollback"),
+    (Method::GET, "/songs/lyrics"),
+    (Method::GET, "/songs/{song_hash}/lyrics"),
+    (Method::GET, "/queues"),
+    (Method::POST, "/queue"),
+    (Method::GET, "/queue/{session}"),
+    (Method::POST, "/queue/{session}/entry"),
+    (Method::PUT, "/queue/{session}/entry/{entry}/position"),
+    (Method::PUT, "/queue/{session}/entry/{entry}/done"),
+    (Method::PUT, "/queue/{session}/entry/{entry}/skip"),
+];
+
+/// Answer one of [POSTGRES_ROUTES] when running with SQLite.
+pub async fn needs_postgres() -> HttpResponse {
+    HttpResponse::NotImplemented().body("Not available with SQLite, this needs a Postgres database")
+}
@@ -0,0 +1,111 @@
+//! Managing admins and API tokens. These routes can't be used with API tokens.
+
+use actix_web::{delete, get, post, put, web, web::Json, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    admin::{self, Admin, ApiToken, Scope, Scopes},
+    error::Result,
+    storage::Storage,
+    token,
+};
+
+#[derive(Deserialize)]
+pub struct CreateToken {
+    /// What the token is for, e.g. `"ultrascraper"`.
+    pub name: String,
+    pub scopes: Vec<Scope>,
+}
+
+#[derive(Serialize)]
+pub struct CreatedToken {
+    #[serde(flatten)]
+    pub info: ApiToken,
+
+    /// The token to send as `Authorization: Bearer <token>`. It isn't stored, so this is the only
+    /// time that it's shown.
+    pub token: String,
+}
+
+/// Get the users that have been made admin, in addition to the members of ADMIN_GROUPS
+#[get("/admin/users")]
+pub async fn list_admins(_admin: Admin, storage: web::Data<dyn Storage>) -> Result<impl Responder> {
+    Ok(Json(storage.admins().await?))
+}
+
+/// Make a user admin
+#[put("/admin/users/{cid}")]
+pub async fn add_admin(
+    admin: Admin,
+    storage: web::Data<dyn Storage>,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    let cid = path.into_inner();
+
+    log::info!("{} made {cid:?} admin", admin.name);
+    storage.add_admin(cid, admin.name).await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Stop a user from being admin. Members of ADMIN_GROUPS are still admins.
+#[delete("/admin/users/{cid}")]
+pub async fn remove_admin(
+    admin: Admin,
+    storage: web::Data<dyn Storage>,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    let cid = path.into_inner();
+
+    if !storage.remove_admin(cid.clone()).await? {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    log::info!("{} removed {cid:?} as admin", admin.name);
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Get all API tokens, including revoked ones
+#[get("/admin/tokens")]
+pub async fn list_tokens(_admin: Admin, storage: web::Data<dyn Storage>) -> Result<impl Responder> {
+    Ok(Json(storage.api_tokens().await?))
+}
+
+/// Create an API token, e.g. for ultrascraper
+#[post("/admin/tokens")]
+pub async fn create_token(
+    admin: Admin,
+    storage: web::Data<dyn Storage>,
+    new_token: Json<CreateToken>,
+) -> Result<impl Responder> {
+    let CreateToken { name, scopes } = new_token.into_inner();
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Ok(HttpResponse::BadRequest().body("Token name may not be empty"));
+    }
+
+    let token = admin::generate_token();
+    let info = storage
+        .create_api_token(name, Scopes(scopes), token::hash(&token), admin.name)
+        .await?;
+
+    log::info!("{} created API token {:?}", info.created_by, info.name);
+    Ok(HttpResponse::Created().json(CreatedToken { info, token }))
+}
+
+/// Revoke an API token, so that it can't be used anymore
+#[delete("/admin/tokens/{id}")]
+pub async fn revoke_token(
+    admin: Admin,
+    storage: web::Data<dyn Storage>,
+    path: web::Path<i32>,
+) -> Result<impl Responder> {
+    let id = path.into_inner();
+
+    if !storage.revoke_api_token(id).await? {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    log::info!("{} revoked API token {id}", admin.name);
+    Ok(HttpResponse::Ok().finish())
+}
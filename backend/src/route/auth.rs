@@ -1,42 +1,79 @@
-use std::sync::Arc;
+use std::{fmt::Debug, sync::Arc};
 
 use actix_session::Session;
-use actix_utils::future::{ready, Ready};
 use actix_web::{
+    dev::Payload,
     error, get,
-    web::{self, Json, Redirect},
     http::StatusCode,
+    web::{self, Json, Redirect},
     FromRequest, HttpRequest, Responder,
 };
+use chrono::{DateTime, Duration, Utc};
 use eyre::eyre;
+use futures::future::LocalBoxFuture;
 use gamma_rust_client::{
     config::GammaConfig,
-    oauth::{gamma_init_auth, GammaAccessToken, GammaOpenIDUser, GammaState},
+    oauth::{gamma_init_auth, GammaAccessToken, GammaState},
 };
 use serde::{Deserialize, Serialize};
 use singit_lib::UserInfo;
 
+use crate::{
+    error::{Error, LoginError},
+    gamma,
+    storage::Storage,
+    token::{self, PersonalToken, PERSONAL_TOKEN_PREFIX},
+};
+
 const ACCESS_TOKEN_SESSION_KEY: &str = "access_token";
 const GAMMA_AUTH_STATE_KEY: &str = "GAMMA_AUTH_STATE";
 
+/// How long after logging in the Gamma groups of a user are trusted, since they're only fetched
+/// when logging in.
+pub const GROUP_MAX_AGE_MINUTES: i64 = 60;
+
+/// A logged in user, or a script that sends one of their personal access tokens as
+/// `Authorization: Bearer <token>`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
-    pub access_token: GammaAccessToken,
-    pub info: GammaOpenIDUser,
+    /// `None` if the user authenticated with a personal access token.
+    #[serde(default)]
+    pub access_token: Option<GammaAccessToken>,
+
+    pub info: UserInfo,
+
+    /// Names of the Gamma groups that the user was a member of when they logged in. Empty for
+    /// personal access tokens.
+    #[serde(default)]
+    pub groups: Vec<String>,
+
+    /// When the user logged in with Gamma, i.e. when `groups` were fetched. `None` for personal
+    /// access tokens, and for sessions from before this was recorded.
+    #[serde(default)]
+    pub logged_in_at: Option<DateTime<Utc>>,
 }
 
+impl User {
+    /// Whether `groups` were fetched within [GROUP_MAX_AGE_MINUTES], so that they may grant
+    /// rights.
+    pub fn groups_are_recent(&self) -> bool {
+        self.logged_in_at.is_some_and(|logged_in_at| {
+            Utc::now() - logged_in_at < Duration::minutes(GROUP_MAX_AGE_MINUTES)
+        })
+    }
+}
+
+/// Query of the redirect back from Gamma. Either `state` and `code`, or `error`, are set.
 #[derive(Deserialize)]
 struct RedirectParams {
-    state: String,
-    code: String,
+    state: Option<String>,
+    code: Option<String>,
+    error: Option<String>,
 }
 
 #[get("/me")]
 pub async fn user_info(user: Option<User>) -> (Json<Option<UserInfo>>, StatusCode) {
-    let user = user.map(|user| UserInfo {
-        cid: user.info.cid,
-        nick: user.info.nick,
-    });
+    let user = user.map(|user| user.info);
 
     let status = if user.is_some() {
         StatusCode::OK
@@ -49,13 +86,18 @@ pub async fn user_info(user: Option<User>) -> (Json<Option<UserInfo>>, StatusCod
 
 impl FromRequest for User {
     type Error = actix_web::error::Error;
-    type Future = Ready<Result<Self, Self::Error>>;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+
+        Box::pin(async move {
+            if let Some(token) = token::bearer(&req) {
+                return authorize_personal_token(&req, token).await;
+            }
 
-    fn from_request(req: &HttpRequest, payload: &mut actix_web::dev::Payload) -> Self::Future {
-        let mut f = || {
-            let session = Session::from_request(req, payload)
-                .into_inner()
-                // TODO: is this right?
+            let session = Session::extract(&req)
+                .await
                 .map_err(error::ErrorInternalServerError)?;
 
             let user: User = session
@@ -66,9 +108,41 @@ impl FromRequest for User {
                 .map_err(error::ErrorUnauthorized)?;
 
             Ok(user)
-        };
+        })
+    }
+}
+
+/// Get the owner of a personal access token.
+async fn authorize_personal_token(req: &HttpRequest, token: &str) -> actix_web::Result<User> {
+    if !token.starts_with(PERSONAL_TOKEN_PREFIX) {
+        return Err(error::ErrorUnauthorized(
+            "Only personal access tokens can be used as a user",
+        ));
+    }
 
-        ready(f())
+    let storage = web::Data::<dyn Storage>::extract(req).await?;
+    let token = storage
+        .use_personal_token(&token::hash(token))
+        .await
+        .map_err(Error::from)?
+        .ok_or_else(|| error::ErrorUnauthorized("Invalid personal access token"))?;
+
+    Ok(User::from(token))
+}
+
+impl From<PersonalToken> for User {
+    fn from(token: PersonalToken) -> Self {
+        User {
+            access_token: None,
+            info: UserInfo {
+                cid: token.owner,
+                nick: token.owner_nick,
+            },
+            // The groups aren't known when a token is used, and rights from groups that were
+            // copied when the token was created would outlive the membership.
+            groups: vec![],
+            logged_in_at: None,
+        }
     }
 }
 
@@ -83,50 +157,101 @@ pub async fn logout(session: Session) -> impl Responder {
 pub async fn login_with_gamma(
     gamma_config: web::Data<Arc<GammaConfig>>,
     session: Session,
-) -> impl Responder {
-    let gamma_auth = gamma_init_auth(&gamma_config).expect("Failed to do gamma auth");
+) -> Result<impl Responder, Error> {
+    let gamma_auth = gamma_init_auth(&gamma_config)
+        .or_login_error(LoginError::GammaUnavailable, "Failed to start gamma auth")?;
 
     session
         .insert(
             GAMMA_AUTH_STATE_KEY.to_string(),
             gamma_auth.state.get_state(),
         )
-        .expect("Failed to set state cookie");
+        .or_login_error(LoginError::Session, "Failed to set state cookie")?;
 
-    Redirect::to(gamma_auth.redirect_to).temporary()
+    Ok(Redirect::to(gamma_auth.redirect_to).temporary())
 }
 
 #[get("/login/gamma/redirect")]
 pub async fn gamma_redirect(
     params: web::Query<RedirectParams>,
     gamma_config: web::Data<Arc<GammaConfig>>,
-    //opt: web::Data<Arc<Opt>>,
     session: Session,
-) -> impl Responder {
+) -> Result<impl Responder, Error> {
+    let RedirectParams {
+        state: gamma_state,
+        code,
+        error,
+    } = params.into_inner();
+
+    if let Some(error) = error {
+        let error = eyre!("Gamma redirected with error {error:?}");
+        return Err(Error::login(LoginError::Denied, error));
+    }
+
+    let (Some(gamma_state), Some(code)) = (gamma_state, code) else {
+        let error = eyre!("Gamma redirected without state and code");
+        return Err(Error::login(LoginError::Rejected, error));
+    };
+
     let state: String = session
         .get(GAMMA_AUTH_STATE_KEY)
-        .expect("Failed to read session store")
-        .expect("Failed to deserialize gamma auth state key");
+        .or_login_error(LoginError::Session, "Failed to read gamma auth state")?
+        .ok_or_else(|| {
+            let error = eyre!("No gamma login in progress");
+            Error::login(LoginError::Expired, error)
+        })?;
     let state = GammaState::get_state_str(state);
 
     let access_token = state
-        .gamma_callback_params(&gamma_config, &params.state, params.code.clone())
+        .gamma_callback_params(&gamma_config, &gamma_state, code)
         .await
-        .expect("Failed to verify gamma redirect");
+        .or_login_error(LoginError::Rejected, "Failed to verify gamma redirect")?;
 
     let user = access_token
         .get_current_user(&gamma_config)
         .await
-        .expect("Failed to get gamma user info");
+        .or_login_error(
+            LoginError::GammaUnavailable,
+            "Failed to get gamma user info",
+        )?;
+
+    let groups = gamma::user_groups(&gamma_config, &user.sub)
+        .await
+        .unwrap_or_else(|e| {
+            log::error!("Failed to get groups of {:?}: {e:?}", user.cid);
+            vec![]
+        });
 
     let user = User {
-        access_token,
-        info: user,
+        access_token: Some(access_token),
+        info: UserInfo {
+            cid: user.cid,
+            nick: user.nick,
+        },
+        groups,
+        logged_in_at: Some(Utc::now()),
     };
 
+    // the state is only valid once, so an old tab can't redo the login
+    session.remove(GAMMA_AUTH_STATE_KEY);
     session
         .insert(ACCESS_TOKEN_SESSION_KEY, user)
-        .expect("Failed to insert auth token in session");
+        .or_login_error(
+            LoginError::Session,
+            "Failed to insert auth token in session",
+        )?;
 
-    Redirect::to("/").temporary()
+    Ok(Redirect::to("/").temporary())
+}
+
+/// Turns errors while logging in into [Error]s that send the user back with a [LoginError].
+trait OrLoginError<T> {
+    fn or_login_error(self, kind: LoginError, context: &str) -> Result<T, Error>;
+}
+
+impl<T, E: Debug> OrLoginError<T> for Result<T, E> {
+    fn or_login_error(self, kind: LoginError, context: &str) -> Result<T, Error> {
+        // the errors of the gamma client aren't std errors, so they can only be formatted
+        self.map_err(|e| Error::login(kind, eyre!("{context}: {e:?}")))
+    }
 }
@@ -1,155 +1,381 @@
 use actix_web::web::Json;
-use actix_web::{delete, dev::Response, get, put, web, Responder, http::StatusCode};
-use diesel::ExpressionMethods;
-use diesel::{QueryDsl, SelectableHelper};
-use diesel_async::{AsyncConnection, RunQueryDsl};
-use eyre::{eyre, Context};
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use diesel::AsChangeset;
+use serde::{Deserialize, Serialize};
 
-use crate::{db::DbPool, error::Result, route::auth::User, schema, CustomList};
+use crate::{
+    error::Result,
+    events::{Event, Events},
+    route::auth::User,
+    schema,
+    storage::Storage,
+    CustomList, CustomListEditor, EditorKind, Visibility,
+};
 
-/// Get a list of all custom lists
+#[derive(Deserialize)]
+pub struct ListName {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct ShareToken {
+    /// The share token of the list, which lets anyone see it while it's unlisted.
+    pub token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct MoveEntry {
+    /// The new index of the entry in the list.
+    pub index: usize,
+}
+
+#[derive(Deserialize, AsChangeset)]
+#[diesel(table_name = schema::custom_list)]
+pub struct UpdateList {
+    pub name: Option<String>,
+    pub visibility: Option<Visibility>,
+}
+
+/// The result of a change that only the editors of a custom list may make.
+pub enum ListEdit<T> {
+    Done(T),
+
+    /// The list doesn't exist.
+    NoSuchList,
+
+    /// The user may not edit the list.
+    NotAllowed,
+}
+
+#[derive(Serialize)]
+pub struct ListInfo {
+    #[serde(flatten)]
+    pub list: CustomList,
+
+    /// Users and groups that the owner has invited to edit the list. Only shown to the users that
+    /// may edit it.
+    pub editors: Vec<CustomListEditor>,
+
+    /// Whether the logged in user may edit the list.
+    pub editable: bool,
+
+    /// The token to put in links to the list while it's unlisted. Only shown to the users that may
+    /// edit the list.
+    pub share_token: Option<String>,
+}
+
+/// Get a list of all public custom lists, and of the lists that the user may edit
 #[get("/custom/lists")]
-pub async fn list_all(pool: web::Data<DbPool>) -> Result<impl Responder> {
-    use schema::custom_list::dsl::*;
+pub async fn list_all(
+    user: Option<User>,
+    storage: web::Data<dyn Storage>,
+) -> Result<impl Responder> {
+    let lists: Vec<ListInfo> = storage
+        .custom_lists()
+        .await?
+        .into_iter()
+        .map(|(list, editors)| {
+            let editable = user
+                .as_ref()
+                .is_some_and(|user| may_edit(&list, &editors, user));
 
-    let mut db = pool.get().await?;
-    let lists: Vec<String> = custom_list
-        .select(name)
-        .load(&mut db)
-        .await
-        .wrap_err("Failed to query custom lists")?;
+            ListInfo {
+                share_token: editable.then(|| list.share_token.clone()),
+                list,
+                editors: if editable { editors } else { vec![] },
+                editable,
+            }
+        })
+        .filter(|info| info.editable || info.list.visibility == Visibility::Public)
+        .collect();
 
     Ok(Json(lists))
 }
 
+/// Create a new custom list, owned by the logged in user
+#[post("/custom/list")]
+pub async fn create_list(
+    user: User,
+    storage: web::Data<dyn Storage>,
+    events: web::Data<Events>,
+    new_list: Json<ListName>,
+) -> Result<impl Responder> {
+    let new_name = new_list.into_inner().name;
+    if new_name.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().body("List name may not be empty"));
+    }
+
+    let list = storage
+        .create_custom_list(new_name.trim().to_string(), user.info.cid.clone())
+        .await?;
+
+    log::info!("{} created custom list {:?}", user.info.cid, list.name);
+    events.send(Event::CustomListsChanged);
+
+    Ok(HttpResponse::Created().json(ListInfo {
+        share_token: Some(list.share_token.clone()),
+        list,
+        editors: vec![],
+        editable: true,
+    }))
+}
+
+/// Rename a custom list, and/or change its visibility
+#[put("/custom/list/{list}")]
+pub async fn update_list(
+    user: User,
+    storage: web::Data<dyn Storage>,
+    events: web::Data<Events>,
+    path: web::Path<i32>,
+    update: Json<UpdateList>,
+) -> Result<impl Responder> {
+    let id_of_list = path.into_inner();
+    let mut update = update.into_inner();
+    if let Some(new_name) = &mut update.name {
+        *new_name = new_name.trim().to_string();
+        if new_name.is_empty() {
+            return Ok(HttpResponse::BadRequest().body("List name may not be empty"));
+        }
+    }
+
+    if update.name.is_none() && update.visibility.is_none() {
+        return Ok(HttpResponse::BadRequest().body("Nothing to update"));
+    }
+
+    if let Some(response) = check_owner(&**storage, &user, id_of_list).await? {
+        return Ok(response);
+    }
+
+    let list = storage.update_custom_list(id_of_list, update).await?;
+
+    events.send(Event::CustomListsChanged);
+
+    Ok(HttpResponse::Ok().json(list))
+}
+
+/// Delete a custom list and all of its entries
+#[delete("/custom/list/{list}")]
+pub async fn delete_list(
+    user: User,
+    storage: web::Data<dyn Storage>,
+    events: web::Data<Events>,
+    path: web::Path<i32>,
+) -> Result<impl Responder> {
+    let id_of_list = path.into_inner();
+
+    if let Some(response) = check_owner(&**storage, &user, id_of_list).await? {
+        return Ok(response);
+    }
+
+    log::info!("{} deleted custom list {id_of_list}", user.info.cid);
+
+    storage.delete_custom_list(id_of_list).await?;
+
+    events.send(Event::CustomListsChanged);
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Replace the set of users and groups that may edit a custom list
+///
+/// Only the owner of the list may do this.
+#[put("/custom/list/{list}/editors")]
+pub async fn set_editors(
+    user: User,
+    storage: web::Data<dyn Storage>,
+    events: web::Data<Events>,
+    path: web::Path<i32>,
+    new_editors: Json<Vec<CustomListEditor>>,
+) -> Result<impl Responder> {
+    let id_of_list = path.into_inner();
+    let mut new_editors = new_editors.into_inner();
+    for editor in &mut new_editors {
+        editor.list_id = id_of_list;
+        editor.name = editor.name.trim().to_string();
+    }
+    new_editors.retain(|editor| !editor.name.is_empty());
+
+    if let Some(response) = check_owner(&**storage, &user, id_of_list).await? {
+        return Ok(response);
+    }
+
+    storage
+        .set_custom_list_editors(id_of_list, new_editors)
+        .await?;
+
+    events.send(Event::CustomListsChanged);
+
+    Ok(HttpResponse::Ok().finish())
+}
+
 /// Get a custom list
+///
+/// Private lists are only shown to the users that may edit them, and unlisted lists also to
+/// anyone with their share token, as `?token=<token>`.
 #[get("/custom/list/{list}")]
-pub async fn get_list(pool: web::Data<DbPool>, path: web::Path<String>) -> Result<impl Responder> {
-    use schema::custom_list::dsl::*;
-    use schema::custom_list_entry::dsl::*;
-
-    let list_name = path.into_inner();
-
-    let mut db = pool.get().await?;
-
-    let list_entries: Vec<String> = db
-        .transaction(|db| {
-            Box::pin(async move {
-                let list: CustomList = custom_list
-                    .select(CustomList::as_select())
-                    .filter(name.eq(list_name))
-                    .get_result(db)
-                    .await?;
-
-                custom_list_entry
-                    .select(song_hash)
-                    .filter(list_id.eq(list.id))
-                    .load(db)
-                    .await
-            })
-        })
-        .await
-        .wrap_err("Failed to query db for custom list")?;
+pub async fn get_list(
+    user: Option<User>,
+    storage: web::Data<dyn Storage>,
+    path: web::Path<i32>,
+    query: web::Query<ShareToken>,
+) -> Result<impl Responder> {
+    let id_of_list = path.into_inner();
+    let token = query.token.as_deref();
 
-    Ok(Json(list_entries))
+    match visible_entries(&**storage, user.as_ref(), id_of_list, token).await? {
+        Some(list_entries) => Ok(HttpResponse::Ok().json(list_entries)),
+        None => Ok(HttpResponse::NotFound().finish()),
+    }
 }
 
 /// Insert a custom list entry
 #[put("/custom/list/{list}/{song_hash}")]
 pub async fn insert_entry(
     user: User,
-    pool: web::Data<DbPool>,
-    path: web::Path<(String, String)>,
+    storage: web::Data<dyn Storage>,
+    events: web::Data<Events>,
+    path: web::Path<(i32, String)>,
 ) -> Result<impl Responder> {
-    use schema::custom_list::dsl::{custom_list, id, name};
-    use schema::custom_list_entry::dsl::{custom_list_entry, list_id, song_hash};
+    let (id_of_list, new_song_hash) = path.into_inner();
 
-    let (list_name, new_song_hash) = path.into_inner();
+    let edit = storage
+        .insert_custom_list_entry(id_of_list, new_song_hash, &user)
+        .await?;
+    match edit {
+        ListEdit::Done(()) => {}
+        ListEdit::NoSuchList => return Ok(HttpResponse::NotFound().finish()),
+        ListEdit::NotAllowed => return Ok(not_editor(&user, id_of_list)),
+    }
 
-    let cid = &user.info.cid;
-    if cid != &list_name {
-        log::warn!("User {cid:?} tried to edit custom list {list_name:?}",);
-        return Ok(Response::new(StatusCode::UNAUTHORIZED));
+    events.send(Event::CustomListChanged { list: id_of_list });
+
+    Ok(HttpResponse::Created().finish())
+}
+
+/// Move a custom list entry to another index in the list
+#[put("/custom/list/{list}/{song_hash}/position")]
+pub async fn move_entry(
+    user: User,
+    storage: web::Data<dyn Storage>,
+    events: web::Data<Events>,
+    path: web::Path<(i32, String)>,
+    new_position: Json<MoveEntry>,
+) -> Result<impl Responder> {
+    let (id_of_list, moved_song_hash) = path.into_inner();
+    let new_index = new_position.into_inner().index;
+
+    let edit = storage
+        .move_custom_list_entry(id_of_list, moved_song_hash, new_index, &user)
+        .await?;
+    let found = match edit {
+        ListEdit::Done(found) => found,
+        ListEdit::NoSuchList => return Ok(HttpResponse::NotFound().finish()),
+        ListEdit::NotAllowed => return Ok(not_editor(&user, id_of_list)),
+    };
+
+    if !found {
+        return Ok(HttpResponse::NotFound().finish());
     }
 
-    let mut db = pool.get().await?;
-
-    db.transaction(|db| {
-        Box::pin(async move {
-            diesel::insert_into(custom_list)
-                .values(name.eq(&list_name))
-                .on_conflict_do_nothing()
-                .execute(db)
-                .await?;
-
-            let id_of_list: i32 = custom_list
-                .select(id)
-                .filter(name.eq(list_name))
-                .get_result(db)
-                .await?;
-
-            diesel::insert_into(custom_list_entry)
-                .values((list_id.eq(id_of_list), song_hash.eq(new_song_hash)))
-                .on_conflict_do_nothing()
-                .execute(db)
-                .await
-        })
-    })
-    .await
-    .wrap_err("Error inserting custom list: {e:?}")?;
+    events.send(Event::CustomListChanged { list: id_of_list });
 
-    Ok(Response::new(StatusCode::CREATED))
+    Ok(HttpResponse::Ok().finish())
 }
 
 /// Delete a custom list entry
 #[delete("/custom/list/{list}/{song_hash}")]
 pub async fn remove_entry(
     user: User,
-    pool: web::Data<DbPool>,
-    path: web::Path<(String, String)>,
+    storage: web::Data<dyn Storage>,
+    events: web::Data<Events>,
+    path: web::Path<(i32, String)>,
 ) -> Result<impl Responder> {
-    use schema::custom_list::dsl::{custom_list, id, name};
-    use schema::custom_list_entry::dsl::{custom_list_entry, list_id, song_hash};
+    let (id_of_list, remove_song_hash) = path.into_inner();
 
-    let (list_name, remove_song_hash) = path.into_inner();
+    log::info!("removing {remove_song_hash} from {id_of_list}");
 
-    let cid = &user.info.cid;
-    if cid != &list_name {
-        log::warn!("User {cid:?} tried to edit custom list {list_name:?}",);
-        return Ok(Response::new(StatusCode::UNAUTHORIZED));
-    }
+    let edit = storage
+        .remove_custom_list_entry(id_of_list, remove_song_hash, &user)
+        .await?;
+    let found = match edit {
+        ListEdit::Done(found) => found,
+        ListEdit::NoSuchList => return Ok(HttpResponse::NotFound().finish()),
+        ListEdit::NotAllowed => return Ok(not_editor(&user, id_of_list)),
+    };
 
-    let mut db = pool.get().await?;
-
-    log::info!("removing {remove_song_hash} from {list_name}");
-
-    let found = db
-        .transaction(|db| {
-            Box::pin(async move {
-                let id_of_list: i32 = custom_list
-                    .select(id)
-                    .filter(name.eq(list_name))
-                    .get_result(db)
-                    .await?;
-
-                let number_of_deleted_rows = diesel::delete(custom_list_entry)
-                    .filter(list_id.eq(id_of_list))
-                    .filter(song_hash.eq(remove_song_hash))
-                    .execute(db)
-                    .await?;
-
-                match number_of_deleted_rows {
-                    0 => Ok(false),
-                    1 => Ok(true),
-                    2.. => Err(eyre!("Custom list delete query had multiple results")),
-                }
-            })
-        })
-        .await
-        .wrap_err("Failed to delete custom list")?;
+    if found {
+        events.send(Event::CustomListChanged { list: id_of_list });
+    }
 
     Ok(match found {
-        true => Response::new(StatusCode::OK),
-        false => Response::new(StatusCode::NOT_FOUND),
+        true => HttpResponse::Ok().finish(),
+        false => HttpResponse::NotFound().finish(),
     })
 }
+
+/// Get the song hashes of a custom list, in order, if the user may see it.
+///
+/// Returns `None` if the list doesn't exist, or if the user may not edit it and it's either private,
+/// or unlisted and `share_token` isn't its share token. List ids are sequential, so unlisted lists
+/// can't be shown by id alone.
+pub async fn visible_entries(
+    storage: &dyn Storage,
+    user: Option<&User>,
+    id_of_list: i32,
+    share_token: Option<&str>,
+) -> eyre::Result<Option<Vec<String>>> {
+    let Some((list, editors)) = storage.custom_list(id_of_list).await? else {
+        return Ok(None);
+    };
+
+    let allowed = match list.visibility {
+        Visibility::Public => true,
+        Visibility::Unlisted if share_token == Some(list.share_token.as_str()) => true,
+        Visibility::Unlisted | Visibility::Private => {
+            user.is_some_and(|user| may_edit(&list, &editors, user))
+        }
+    };
+    if !allowed {
+        return Ok(None);
+    }
+
+    Ok(Some(storage.custom_list_entries(id_of_list).await?))
+}
+
+/// Check that the user owns the custom list.
+///
+/// Returns the response to send if the list doesn't exist, or if the user may not edit it.
+async fn check_owner(
+    storage: &dyn Storage,
+    user: &User,
+    id_of_list: i32,
+) -> eyre::Result<Option<HttpResponse>> {
+    let list = storage.custom_list(id_of_list).await?;
+
+    let cid = &user.info.cid;
+    Ok(match list {
+        None => Some(HttpResponse::NotFound().finish()),
+        Some((list, _)) if &list.owner != cid => {
+            log::warn!("User {cid:?} tried to edit custom list {id_of_list}");
+            Some(HttpResponse::Unauthorized().finish())
+        }
+        Some(_) => None,
+    })
+}
+
+/// The response to send when a user tried to edit the songs of a custom list they may not edit.
+fn not_editor(user: &User, id_of_list: i32) -> HttpResponse {
+    let cid = &user.info.cid;
+    log::warn!("User {cid:?} tried to edit custom list {id_of_list}");
+    HttpResponse::Unauthorized().finish()
+}
+
+/// Whether the user owns the list, or has been invited to edit it. Invitations of groups only
+/// count while the groups of the user are recent, see [User::groups_are_recent].
+pub fn may_edit(list: &CustomList, editors: &[CustomListEditor], user: &User) -> bool {
+    list.owner == user.info.cid
+        || editors.iter().any(|editor| match editor.kind {
+            EditorKind::User => editor.name == user.info.cid,
+            EditorKind::Group => user.groups_are_recent() && user.groups.contains(&editor.name),
+        })
+}
@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use actix_web::{get, rt::time::interval, web, web::Bytes, HttpResponse, Responder};
+use futures::{stream, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::events::{Event, Events};
+
+/// How often to send a comment to keep idle connections from being closed by proxies.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Subscribe to changes of songs, custom lists and queues as server-sent events.
+///
+/// Every event is sent as a `data:` line containing a JSON-serialized [Event].
+#[get("/events")]
+pub async fn subscribe(events: web::Data<Events>) -> impl Responder {
+    let receiver = events.subscribe();
+    let events = stream::once(async { Event::Connected }).chain(stream::unfold(
+        receiver,
+        |mut receiver| async move {
+            match receiver.recv().await {
+                Ok(event) => Some((event, receiver)),
+                Err(RecvError::Lagged(n)) => {
+                    log::warn!("event subscriber lagged behind by {n} events");
+                    Some((Event::Resync, receiver))
+                }
+                Err(RecvError::Closed) => None,
+            }
+        },
+    ));
+
+    let messages =
+        events.map(|event| serde_json::to_string(&event).map(|json| format!("data: {json}\n\n")));
+
+    let heartbeats = stream::unfold(interval(HEARTBEAT_INTERVAL), |mut interval| async move {
+        interval.tick().await;
+        Some((Ok(":\n\n".to_string()), interval))
+    });
+
+    let body = stream::select(messages, heartbeats).map(|message| message.map(Bytes::from));
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(body)
+}
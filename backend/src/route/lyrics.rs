@@ -0,0 +1,121 @@
+// the QueryableByName derive expands to `field: field`, which clippy lints in the deriving module
+#![allow(clippy::redundant_field_names)]
+
+use actix_web::{get, web, web::Json, HttpResponse, Responder};
+use diesel::{
+    sql_types::{BigInt, Text},
+    OptionalExtension, QueryDsl, QueryableByName,
+};
+use diesel_async::RunQueryDsl;
+use eyre::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::{db::DbPool, error::Result, schema, util::PathSafeString};
+
+/// Used to mark the start and end of each match in a snippet. A private use character, so that it
+/// won't appear in any lyrics.
+const HIGHLIGHT: char = '\u{E000}';
+
+/// The maximum number of songs returned by a lyrics search.
+const MAX_RESULTS: i64 = 100;
+
+#[derive(Deserialize)]
+struct LyricsQuery {
+    q: String,
+}
+
+#[derive(QueryableByName)]
+struct LyricsRow {
+    #[diesel(sql_type = Text)]
+    song_hash: String,
+    #[diesel(sql_type = Text)]
+    snippet: String,
+}
+
+#[derive(Serialize)]
+pub struct LyricsMatch {
+    pub song_hash: String,
+
+    /// The best matching line of lyrics, split into parts. Every other part is a match, starting
+    /// with a part that isn't.
+    pub snippet: Vec<String>,
+}
+
+/// Search the lyrics of all songs, using postgres full-text search.
+///
+/// Returns the matching songs, best match first.
+#[get("/songs/lyrics")]
+pub async fn search_lyrics(
+    pool: web::Data<DbPool>,
+    query: web::Query<LyricsQuery>,
+) -> Result<impl Responder> {
+    // Look for a single line containing the whole query to use as a snippet, but fall back to
+    // the full lyrics if the query is spread over several lines.
+    const SEARCH_LYRICS: &str = r#"
+        SELECT song_hash, ts_headline('simple', coalesce(line, lyrics), query, $2) AS snippet
+        FROM song
+        CROSS JOIN websearch_to_tsquery('simple', $1) query
+        LEFT JOIN LATERAL (
+            SELECT line FROM unnest(string_to_array(lyrics, E'\n')) line
+            WHERE to_tsvector('simple', line) @@ query
+            LIMIT 1
+        ) best_line ON true
+        WHERE removed_at IS NULL AND to_tsvector('simple', coalesce(lyrics, '')) @@ query
+        ORDER BY ts_rank(to_tsvector('simple', coalesce(lyrics, '')), query) DESC
+        LIMIT $3
+    "#;
+
+    let options = format!(
+        "StartSel={HIGHLIGHT}, StopSel={HIGHLIGHT}, MinWords=3, MaxWords=12, MaxFragments=1"
+    );
+
+    let mut db = pool.get().await?;
+
+    let rows: Vec<LyricsRow> = diesel::sql_query(SEARCH_LYRICS)
+        .bind::<Text, _>(&query.q)
+        .bind::<Text, _>(options)
+        .bind::<BigInt, _>(MAX_RESULTS)
+        .load(&mut db)
+        .await
+        .wrap_err("Failed to search song lyrics")?;
+
+    let matches: Vec<LyricsMatch> = rows
+        .into_iter()
+        .map(|row| LyricsMatch {
+            song_hash: row.song_hash,
+            snippet: row
+                .snippet
+                .replace('\n', " / ")
+                .split(HIGHLIGHT)
+                .map(str::to_string)
+                .collect(),
+        })
+        .collect();
+
+    Ok(Json(matches))
+}
+
+/// Get the lyrics of a song, or `null` if it has none.
+#[get("/songs/{song_hash}/lyrics")]
+pub async fn song_lyrics(
+    pool: web::Data<DbPool>,
+    path: web::Path<PathSafeString>,
+) -> Result<HttpResponse> {
+    use schema::song::dsl as song;
+
+    let song_hash = path.into_inner().0;
+    let mut db = pool.get().await?;
+
+    let lyrics: Option<Option<String>> = song::song
+        .find(&song_hash)
+        .select(song::lyrics)
+        .first(&mut db)
+        .await
+        .optional()
+        .wrap_err("Failed to query song lyrics")?;
+
+    Ok(match lyrics {
+        Some(lyrics) => HttpResponse::Ok().json(lyrics),
+        None => HttpResponse::NotFound().finish(),
+    })
+}
@@ -0,0 +1,406 @@
+use actix_web::{
+    get, post, put,
+    web::{self, Json},
+    HttpResponse, Responder,
+};
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, SelectableHelper};
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use eyre::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db::DbPool,
+    error::Result,
+    events::{Event, Events},
+    route::auth::User,
+    schema, QueueEntry, QueueEntryState, QueueSession,
+};
+
+#[derive(Serialize)]
+pub struct Queue {
+    #[serde(flatten)]
+    pub session: QueueSession,
+
+    /// All entries of the session, including the ones that have been sung or skipped, ordered by
+    /// position.
+    pub entries: Vec<QueueEntry>,
+}
+
+#[derive(Deserialize)]
+pub struct NewSession {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct NewEntry {
+    pub song_hash: String,
+
+    /// Name of the singer. Defaults to the nick of the logged in user.
+    pub singer: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct MoveEntry {
+    /// The new index of the entry among the entries that are still queued.
+    pub index: usize,
+}
+
+/// The result of moving an entry in a queue.
+enum Reorder {
+    Done,
+
+    /// The session or the entry doesn't exist, or the entry isn't queued.
+    NotFound,
+
+    /// The user doesn't own the session.
+    NotAllowed,
+}
+
+/// Get a list of all queue sessions, newest first.
+#[get("/queues")]
+pub async fn list_sessions(pool: web::Data<DbPool>) -> Result<impl Responder> {
+    use schema::queue_session::dsl::*;
+
+    let mut db = pool.get().await?;
+    let sessions: Vec<QueueSession> = queue_session
+        .select(QueueSession::as_select())
+        .order_by(id.desc())
+        .load(&mut db)
+        .await
+        .wrap_err("Failed to query queue sessions")?;
+
+    Ok(Json(sessions))
+}
+
+/// Create a new queue session, owned by the logged in user.
+#[post("/queue")]
+pub async fn create_session(
+    user: User,
+    pool: web::Data<DbPool>,
+    events: web::Data<Events>,
+    new_session: Json<NewSession>,
+) -> Result<impl Responder> {
+    use schema::queue_session::dsl::*;
+
+    let new_name = new_session.into_inner().name;
+    if new_name.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().body("Queue name may not be empty"));
+    }
+
+    let mut db = pool.get().await?;
+    let session: QueueSession = diesel::insert_into(queue_session)
+        .values((name.eq(new_name.trim()), owner.eq(&user.info.cid)))
+        .returning(QueueSession::as_returning())
+        .get_result(&mut db)
+        .await
+        .wrap_err("Failed to create queue session")?;
+
+    log::info!("{} created queue session {:?}", user.info.cid, session.name);
+    events.send(Event::QueueChanged {
+        session: session.id,
+    });
+
+    Ok(HttpResponse::Created().json(session))
+}
+
+/// Get a queue session and all of its entries.
+#[get("/queue/{session}")]
+pub async fn get_queue(pool: web::Data<DbPool>, path: web::Path<i32>) -> Result<impl Responder> {
+    let session_id = path.into_inner();
+
+    let mut db = pool.get().await?;
+    let queue = load_queue(&mut db, session_id).await?;
+
+    Ok(match queue {
+        Some(queue) => HttpResponse::Ok().json(queue),
+        None => HttpResponse::NotFound().finish(),
+    })
+}
+
+/// Add a song to the end of a queue.
+///
+/// Anyone may queue songs, but anonymous users need to provide a singer name.
+#[post("/queue/{session}/entry")]
+pub async fn enqueue(
+    user: Option<User>,
+    pool: web::Data<DbPool>,
+    events: web::Data<Events>,
+    path: web::Path<i32>,
+    new_entry: Json<NewEntry>,
+) -> Result<impl Responder> {
+    use schema::queue_entry::dsl as entry;
+    use schema::queue_session::dsl as session;
+    use schema::song::dsl as song;
+
+    let id_of_session = path.into_inner();
+    let NewEntry {
+        song_hash: new_song_hash,
+        singer,
+    } = new_entry.into_inner();
+
+    let singer = match (singer, &user) {
+        (Some(singer), _) if !singer.trim().is_empty() => singer.trim().to_string(),
+        (_, Some(user)) => user.info.nick.clone(),
+        _ => return Ok(HttpResponse::BadRequest().body("A singer name is required")),
+    };
+    let singer_cid = user.map(|user| user.info.cid);
+
+    let mut db = pool.get().await?;
+
+    let queued: Option<QueueEntry> = db
+        .transaction(|db| {
+            Box::pin(async move {
+                // lock the session, so that entries queued at the same time get different positions
+                let session_exists = session::queue_session
+                    .find(id_of_session)
+                    .select(session::id)
+                    .for_update()
+                    .first::<i32>(db)
+                    .await
+                    .optional()?
+                    .is_some();
+
+                let song_exists = song::song
+                    .find(&new_song_hash)
+                    .filter(song::removed_at.is_null())
+                    .select(song::song_hash)
+                    .first::<String>(db)
+                    .await
+                    .optional()?
+                    .is_some();
+
+                if !session_exists || !song_exists {
+                    return Ok(None);
+                }
+
+                let last_position: Option<i32> = entry::queue_entry
+                    .filter(entry::session_id.eq(id_of_session))
+                    .select(entry::position)
+                    .order_by(entry::position.desc())
+                    .first(db)
+                    .await
+                    .optional()?;
+
+                diesel::insert_into(entry::queue_entry)
+                    .values((
+                        entry::session_id.eq(id_of_session),
+                        entry::song_hash.eq(new_song_hash),
+                        entry::singer.eq(singer),
+                        entry::singer_cid.eq(singer_cid),
+                        entry::position.eq(last_position.map_or(0, |p| p + 1)),
+                    ))
+                    .returning(QueueEntry::as_returning())
+                    .get_result(db)
+                    .await
+                    .map(Some)
+            })
+        })
+        .await
+        .wrap_err("Failed to insert queue entry")?;
+
+    Ok(match queued {
+        Some(queued) => {
+            events.send(Event::QueueChanged {
+                session: id_of_session,
+            });
+            HttpResponse::Created().json(queued)
+        }
+        None => HttpResponse::NotFound().finish(),
+    })
+}
+
+/// Move a queued entry to another place in the queue.
+///
+/// Only the owner of the session may reorder its queue.
+#[put("/queue/{session}/entry/{entry}/position")]
+pub async fn move_entry(
+    user: User,
+    pool: web::Data<DbPool>,
+    events: web::Data<Events>,
+    path: web::Path<(i32, i32)>,
+    new_position: Json<MoveEntry>,
+) -> Result<impl Responder> {
+    use schema::queue_entry::dsl as entry;
+    use schema::queue_session::dsl as session;
+
+    let (id_of_session, id_of_entry) = path.into_inner();
+    let new_index = new_position.into_inner().index;
+    let cid = user.info.cid;
+
+    let mut db = pool.get().await?;
+
+    let reordered = db
+        .transaction(|db| {
+            let cid = cid.clone();
+            Box::pin(async move {
+                // lock the session, so that concurrent changes to its queue wait for this one
+                let session_owner: Option<String> = session::queue_session
+                    .find(id_of_session)
+                    .select(session::owner)
+                    .for_update()
+                    .first(db)
+                    .await
+                    .optional()?;
+
+                match session_owner {
+                    None => return Ok(Reorder::NotFound),
+                    Some(session_owner) if session_owner != cid => return Ok(Reorder::NotAllowed),
+                    Some(_) => {}
+                }
+
+                let mut queued: Vec<QueueEntry> = entry::queue_entry
+                    .filter(entry::session_id.eq(id_of_session))
+                    .filter(entry::state.eq(QueueEntryState::Queued))
+                    .select(QueueEntry::as_select())
+                    .order_by(entry::position)
+                    .load(db)
+                    .await?;
+
+                let Some(old_index) = queued.iter().position(|e| e.id == id_of_entry) else {
+                    return Ok(Reorder::NotFound);
+                };
+
+                // Shuffle the entries around, but keep the set of positions the same so that
+                // entries that have already been sung stay where they are.
+                let positions: Vec<i32> = queued.iter().map(|e| e.position).collect();
+                let moved = queued.remove(old_index);
+                queued.insert(new_index.min(queued.len()), moved);
+
+                for (e, new_position) in queued.iter().zip(positions) {
+                    if e.position != new_position {
+                        diesel::update(entry::queue_entry.find(e.id))
+                            .set(entry::position.eq(new_position))
+                            .execute(db)
+                            .await?;
+                    }
+                }
+
+                diesel::QueryResult::Ok(Reorder::Done)
+            })
+        })
+        .await
+        .wrap_err("Failed to reorder queue")?;
+
+    match reordered {
+        Reorder::Done => {}
+        Reorder::NotFound => return Ok(HttpResponse::NotFound().finish()),
+        Reorder::NotAllowed => {
+            log::warn!("User {cid:?} tried to reorder queue {id_of_session}");
+            return Ok(HttpResponse::Unauthorized().finish());
+        }
+    }
+
+    events.send(Event::QueueChanged {
+        session: id_of_session,
+    });
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Mark a queue entry as sung.
+///
+/// Only the owner of the session may do this.
+#[put("/queue/{session}/entry/{entry}/done")]
+pub async fn mark_done(
+    user: User,
+    pool: web::Data<DbPool>,
+    events: web::Data<Events>,
+    path: web::Path<(i32, i32)>,
+) -> Result<impl Responder> {
+    set_entry_state(user, pool, events, path.into_inner(), QueueEntryState::Done).await
+}
+
+/// Skip a queue entry.
+///
+/// The owner of the session may skip any entry, while singers may skip their own entries.
+#[put("/queue/{session}/entry/{entry}/skip")]
+pub async fn skip(
+    user: User,
+    pool: web::Data<DbPool>,
+    events: web::Data<Events>,
+    path: web::Path<(i32, i32)>,
+) -> Result<impl Responder> {
+    set_entry_state(
+        user,
+        pool,
+        events,
+        path.into_inner(),
+        QueueEntryState::Skipped,
+    )
+    .await
+}
+
+async fn set_entry_state(
+    user: User,
+    pool: web::Data<DbPool>,
+    events: web::Data<Events>,
+    (id_of_session, id_of_entry): (i32, i32),
+    new_state: QueueEntryState,
+) -> Result<HttpResponse> {
+    use schema::queue_entry::dsl::*;
+
+    let mut db = pool.get().await?;
+
+    let Some(queue) = load_queue(&mut db, id_of_session).await? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    let Some(entry) = queue.entries.iter().find(|e| e.id == id_of_entry) else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    let cid = &user.info.cid;
+    let is_owner = cid == &queue.session.owner;
+    let is_singer = entry.singer_cid.as_ref() == Some(cid);
+    let allowed = match new_state {
+        QueueEntryState::Skipped => is_owner || is_singer,
+        QueueEntryState::Done | QueueEntryState::Queued => is_owner,
+    };
+
+    if !allowed {
+        log::warn!("User {cid:?} tried to change entry {id_of_entry} in queue {id_of_session}");
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+
+    diesel::update(queue_entry.find(id_of_entry))
+        .set(state.eq(new_state))
+        .execute(&mut db)
+        .await
+        .wrap_err("Failed to update queue entry")?;
+
+    events.send(Event::QueueChanged {
+        session: id_of_session,
+    });
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Load a queue session and all of its entries. Returns `None` if the session doesn't exist.
+async fn load_queue(db: &mut AsyncPgConnection, id_of_session: i32) -> eyre::Result<Option<Queue>> {
+    use schema::queue_entry::dsl as entry;
+    use schema::queue_session::dsl as session;
+
+    let Some(queue_session) = session::queue_session
+        .find(id_of_session)
+        .select(QueueSession::as_select())
+        .first(db)
+        .await
+        .optional()
+        .wrap_err("Failed to query queue session")?
+    else {
+        return Ok(None);
+    };
+
+    let entries = entry::queue_entry
+        .filter(entry::session_id.eq(id_of_session))
+        .select(QueueEntry::as_select())
+        .order_by(entry::position)
+        .load(db)
+        .await
+        .wrap_err("Failed to query queue entries")?;
+
+    Ok(Some(Queue {
+        session: queue_session,
+        entries,
+    }))
+}
@@ -0,0 +1,179 @@
+// the QueryableByName derive expands to `field: field`, which clippy lints in the deriving module
+#![allow(clippy::redundant_field_names)]
+
+use actix_web::{get, post, web, web::Json, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use diesel::{
+    sql_types::{BigInt, Integer, Nullable, Text, Timestamptz},
+    ExpressionMethods, OptionalExtension, QueryDsl, QueryableByName,
+};
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use eyre::Context;
+use serde::{Deserialize, Serialize};
+use singit_lib::PutSongs;
+
+use crate::{
+    admin::{scope, Admin},
+    db::DbPool,
+    error::Result,
+    events::{Event, Events},
+    revision, schema,
+};
+
+#[derive(Serialize, QueryableByName)]
+pub struct RevisionInfo {
+    #[diesel(sql_type = Integer)]
+    pub id: i32,
+    #[diesel(sql_type = Timestamptz)]
+    pub created_at: DateTime<Utc>,
+
+    /// CID of the admin that made the change, or `token:<name>` if an API token was used.
+    #[diesel(sql_type = Text)]
+    pub created_by: String,
+
+    /// The revision that the song list was rolled back to, if this was a rollback.
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub rollback_of: Option<i32>,
+
+    #[diesel(sql_type = BigInt)]
+    pub songs_added: i64,
+    #[diesel(sql_type = BigInt)]
+    pub songs_removed: i64,
+    #[diesel(sql_type = BigInt)]
+    pub songs_changed: i64,
+}
+
+#[derive(Deserialize)]
+pub struct RevisionPair {
+    from: i32,
+    to: i32,
+}
+
+/// List all revisions of the song list, newest first.
+///
+/// This route requires an admin, or an API token with the `revisions` scope.
+#[get("/songs/revisions")]
+pub async fn list_revisions(
+    _admin: Admin<scope::Revisions>,
+    pool: web::Data<DbPool>,
+) -> Result<impl Responder> {
+    const LIST_REVISIONS: &str = r#"
+        SELECT
+            r.id, r.created_at, r.created_by, r.rollback_of,
+            count(c.song_hash) FILTER (WHERE c.old IS NULL) AS songs_added,
+            count(c.song_hash) FILTER (WHERE c.new IS NULL) AS songs_removed,
+            count(c.song_hash) FILTER (WHERE c.old IS NOT NULL AND c.new IS NOT NULL) AS songs_changed
+        FROM song_revision r
+        LEFT JOIN song_revision_change c ON c.revision_id = r.id
+        GROUP BY r.id
+        ORDER BY r.id DESC
+    "#;
+
+    let mut db = pool.get().await?;
+
+    let revisions: Vec<RevisionInfo> = diesel::sql_query(LIST_REVISIONS)
+        .load(&mut db)
+        .await
+        .wrap_err("Failed to list song revisions")?;
+
+    Ok(Json(revisions))
+}
+
+/// Get the differences between the song list at two revisions.
+///
+/// Revision `0` is the song list before the first revision.
+///
+/// This route requires an admin, or an API token with the `revisions` scope.
+#[get("/songs/revisions/{from}/diff/{to}")]
+pub async fn diff_revisions(
+    _admin: Admin<scope::Revisions>,
+    pool: web::Data<DbPool>,
+    path: web::Path<RevisionPair>,
+) -> Result<impl Responder> {
+    let RevisionPair { from, to } = path.into_inner();
+    let mut db = pool.get().await?;
+
+    if !revision_exists(&mut db, from).await? || !revision_exists(&mut db, to).await? {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    let changes = revision::changes_between(&mut db, from, to).await?;
+
+    Ok(HttpResponse::Ok().json(revision::diff(&changes)))
+}
+
+/// Restore the song list to how it was at an earlier revision.
+///
+/// The rollback is itself stored as a new revision, so it can be undone.
+///
+/// This route requires an admin, or an API token with the `revisions` scope.
+#[post("/songs/revisions/{revision}/rollback")]
+pub async fn rollback(
+    admin: Admin<scope::Revisions>,
+    pool: web::Data<DbPool>,
+    events: web::Data<Events>,
+    path: web::Path<i32>,
+) -> Result<impl Responder> {
+    use schema::song_revision::dsl as song_revision;
+
+    let target = path.into_inner();
+    let created_by = admin.name;
+    let mut db = pool.get().await?;
+
+    let response = db
+        .transaction(|db| {
+            Box::pin(async move {
+                if !revision_exists(db, target).await? {
+                    return eyre::Ok(None);
+                }
+
+                let latest: Option<i32> = song_revision::song_revision
+                    .select(song_revision::id)
+                    .order_by(song_revision::id.desc())
+                    .first(db)
+                    .await
+                    .optional()?;
+
+                let changes = revision::changes_between(db, latest.unwrap_or(0), target).await?;
+                revision::apply(db, &changes).await?;
+                revision::record(db, &created_by, Some(target), &changes).await?;
+
+                let songs_added = changes.iter().filter(|c| c.old.is_none()).count();
+                let songs_deleted = changes.iter().filter(|c| c.new.is_none()).count();
+                eyre::Ok(Some(PutSongs {
+                    songs_added,
+                    songs_deleted,
+                    songs_updated: changes.len() - songs_added - songs_deleted,
+                }))
+            })
+        })
+        .await?;
+
+    let Some(response) = response else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    events.send(Event::SongsReplaced);
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Check if a revision exists. Revision `0` always does.
+async fn revision_exists(db: &mut AsyncPgConnection, id: i32) -> eyre::Result<bool> {
+    use schema::song_revision::dsl as song_revision;
+
+    if id == 0 {
+        return Ok(true);
+    }
+
+    let exists = song_revision::song_revision
+        .find(id)
+        .select(song_revision::id)
+        .first::<i32>(db)
+        .await
+        .optional()
+        .wrap_err("Failed to query song revision")?
+        .is_some();
+
+    Ok(exists)
+}
@@ -0,0 +1,146 @@
+//! Searching the song list, with the query language and ranking of the search field in the web UI.
+
+use std::collections::HashMap;
+
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::Deserialize;
+use singit_lib::{
+    query::ParsedQuery,
+    search::{self, Searchable},
+    SearchHit, SearchResults,
+};
+
+use crate::{error::Result, route::auth::User, route::custom_list, storage::Storage, Song};
+
+/// The number of songs returned by a search, unless the `limit` parameter is set.
+const DEFAULT_LIMIT: usize = 50;
+
+/// The maximum number of songs returned by a search.
+const MAX_LIMIT: usize = 200;
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    /// The query, e.g. `abba lang:english`. Matches every song if empty.
+    #[serde(default)]
+    q: String,
+
+    /// The number of matching songs to skip.
+    #[serde(default)]
+    offset: usize,
+
+    /// The number of matching songs to return. At most [MAX_LIMIT].
+    limit: Option<usize>,
+
+    /// The share token of the unlisted list that `list:` filters by, if any.
+    token: Option<String>,
+}
+
+/// Search the song list
+///
+/// Takes the same queries as the search field of the web UI, and ranks the songs the same way,
+/// except that songs that match equally well are sorted by title rather than shuffled. `list:`
+/// only matches lists that the user may see, and `lyrics:` isn't supported, use `/songs/lyrics`.
+#[get("/songs/search")]
+pub async fn search_songs(
+    user: Option<User>,
+    storage: web::Data<dyn Storage>,
+    query: web::Query<SearchQuery>,
+) -> Result<impl Responder> {
+    let SearchQuery {
+        q,
+        offset,
+        limit,
+        token,
+    } = query.into_inner();
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+
+    let query = ParsedQuery::parse(&q);
+    if query.lyrics.is_some() {
+        return Ok(HttpResponse::BadRequest().body("Search lyrics with GET /songs/lyrics"));
+    }
+
+    let mut songs = storage.songs().await?;
+
+    // the position of every song in the list that the query filters by
+    let mut list_positions = None;
+    if let Some(list) = &query.list {
+        let entries = match list.parse() {
+            Ok(list) => {
+                let token = token.as_deref();
+                custom_list::visible_entries(&**storage, user.as_ref(), list, token).await?
+            }
+            Err(_) => None,
+        };
+        let Some(entries) = entries else {
+            return Ok(HttpResponse::NotFound().body("No such custom list"));
+        };
+
+        // removed songs are only shown in the lists that contain them
+        songs.extend(storage.removed_songs().await?);
+
+        let positions: HashMap<String, usize> = entries.into_iter().zip(0..).collect();
+        songs.retain(|song| positions.contains_key(&song.song_hash));
+        list_positions = Some(positions);
+    }
+
+    let mut hits: Vec<SearchHit<Song>> = songs
+        .into_iter()
+        .filter_map(|song| {
+            let score = search::score(&song, &query)?;
+            Some(SearchHit { score, song })
+        })
+        .collect();
+
+    match list_positions {
+        // like the web UI, show the songs of a list in the order that its editors arranged them
+        Some(positions) if !query.has_fuzzy_parameters() => {
+            hits.sort_by_key(|hit| positions[&hit.song.song_hash]);
+        }
+        // songs with the same score are ranked by title, then artist, then song hash
+        _ => hits.sort_by(|a, b| {
+            (b.score.cmp(&a.score))
+                .then_with(|| a.song.title.cmp(&b.song.title))
+                .then_with(|| a.song.artist.cmp(&b.song.artist))
+                .then_with(|| a.song.song_hash.cmp(&b.song.song_hash))
+        }),
+    }
+
+    let total = hits.len();
+    let hits = hits.into_iter().skip(offset).take(limit).collect();
+
+    Ok(HttpResponse::Ok().json(SearchResults { total, hits }))
+}
+
+impl Searchable for Song {
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn artist(&self) -> &str {
+        &self.artist
+    }
+
+    fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    fn genre(&self) -> Option<&str> {
+        self.genre.as_deref()
+    }
+
+    fn year(&self) -> Option<&str> {
+        self.year.as_deref()
+    }
+
+    fn bpm(&self) -> &str {
+        &self.bpm
+    }
+
+    fn has_video(&self) -> bool {
+        self.video.is_some()
+    }
+
+    fn is_duet(&self) -> bool {
+        self.duet_singer_1.is_some() && self.duet_singer_2.is_some()
+    }
+}
@@ -0,0 +1,146 @@
+//! Incremental syncing of the song list.
+//!
+//! Instead of uploading every song with `PUT /songs`, the uploader first sends a hash of each song
+//! to `POST /songs/sync`, and then only uploads the songs and covers that the server asks for.
+
+use std::collections::{HashMap, HashSet};
+
+use actix_web::{patch, post, web, web::Json};
+use diesel::{dsl::now, ExpressionMethods, QueryDsl, SelectableHelper};
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use eyre::Context;
+use serde::Deserialize;
+use singit_lib::{PutSongs, SongDigest, SyncPlan};
+
+use crate::{
+    admin::{scope, Admin},
+    db::DbPool,
+    error::Result,
+    events::{Event, Events},
+    revision, schema, upsert_songs, NewSong,
+};
+
+/// Changes to make to the song list.
+#[derive(Deserialize)]
+pub struct SongsPatch {
+    /// Songs to add, or to update if they already exist.
+    #[serde(default)]
+    upsert: Vec<NewSong>,
+
+    /// Hashes of songs to remove.
+    #[serde(default)]
+    remove: Vec<String>,
+}
+
+/// Compare the song list of the uploader with the one on the server.
+///
+/// This route requires an admin, or an API token with the `songs` scope.
+#[post("/songs/sync")]
+pub async fn sync_plan(
+    _admin: Admin<scope::Songs>,
+    pool: web::Data<DbPool>,
+    digests: Json<Vec<SongDigest>>,
+) -> Result<Json<SyncPlan>> {
+    use schema::song::dsl as song;
+
+    let mut db = pool.get().await?;
+
+    let existing: Vec<(String, Option<String>, Option<String>)> = song::song
+        .filter(song::removed_at.is_null())
+        .select((song::song_hash, song::content_hash, song::cover_hash))
+        .load(&mut db)
+        .await
+        .wrap_err("Failed to select song hashes")?;
+
+    let existing: HashMap<String, (Option<String>, Option<String>)> = existing
+        .into_iter()
+        .map(|(hash, content, cover)| (hash, (content, cover)))
+        .collect();
+
+    let mut plan = SyncPlan::default();
+
+    for digest in digests.iter() {
+        let (old_content, old_cover) = match existing.get(&digest.song_hash) {
+            Some((content, cover)) => (content.as_ref(), cover.as_ref()),
+            None => (None, None),
+        };
+
+        if old_content != Some(&digest.content_hash) {
+            plan.songs.push(digest.song_hash.clone());
+        }
+
+        if digest.cover_hash.is_some() && old_cover != digest.cover_hash.as_ref() {
+            plan.covers.push(digest.song_hash.clone());
+        }
+    }
+
+    let uploaded: HashSet<&str> = digests.iter().map(|d| d.song_hash.as_str()).collect();
+    plan.removed = existing
+        .into_keys()
+        .filter(|hash| !uploaded.contains(hash.as_str()))
+        .collect();
+
+    Ok(Json(plan))
+}
+
+/// Add, update and remove some songs, leaving the rest of the song list as it is.
+///
+/// This route requires an admin, or an API token with the `songs` scope.
+#[patch("/songs")]
+pub async fn patch_songs(
+    admin: Admin<scope::Songs>,
+    pool: web::Data<DbPool>,
+    events: web::Data<Events>,
+    patch: Json<SongsPatch>,
+) -> Result<Json<PutSongs>> {
+    use schema::song::dsl as song;
+
+    let SongsPatch { upsert, remove } = patch.into_inner();
+    let mut db = pool.get().await?;
+
+    let response = db
+        .transaction(|db| {
+            Box::pin(async move {
+                let affected = upsert.iter().map(|new| &new.song.song_hash).chain(&remove);
+                let old_songs: Vec<NewSong> = song::song
+                    .filter(song::song_hash.eq_any(affected))
+                    .filter(song::removed_at.is_null())
+                    .select(NewSong::as_select())
+                    .load(db)
+                    .await
+                    .wrap_err("Failed to select existing songs")?;
+
+                let old_hashes: HashSet<&str> = old_songs
+                    .iter()
+                    .map(|old| old.song.song_hash.as_str())
+                    .collect();
+                let songs_updated = (upsert.iter())
+                    .filter(|new| old_hashes.contains(new.song.song_hash.as_str()))
+                    .count();
+
+                let changes = revision::song_changes(old_songs, &upsert, &remove);
+
+                let songs_deleted = diesel::update(song::song)
+                    .filter(song::song_hash.eq_any(remove))
+                    .filter(song::removed_at.is_null())
+                    .set(song::removed_at.eq(now))
+                    .execute(db)
+                    .await
+                    .wrap_err("Failed to mark songs as removed")?;
+
+                upsert_songs(db, &upsert).await?;
+                revision::record(db, &admin.name, None, &changes).await?;
+
+                eyre::Ok(PutSongs {
+                    songs_added: upsert.len() - songs_updated,
+                    songs_deleted,
+                    songs_updated,
+                })
+            })
+        })
+        .await?;
+
+    events.send(Event::SongsReplaced);
+
+    Ok(Json(response))
+}
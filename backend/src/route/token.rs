@@ -0,0 +1,121 @@
+//! Managing the personal access tokens of the logged in user. These routes can't be used with
+//! personal access tokens, so that a leaked token can't be used to make more of them.
+
+use actix_web::{delete, get, post, web, web::Json, HttpResponse, Responder};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Result,
+    route::auth::User,
+    storage::Storage,
+    token::{self, PersonalToken, PERSONAL_TOKEN_PREFIX},
+};
+
+/// How many days personal access tokens work for, unless asked for fewer.
+pub const MAX_TOKEN_DAYS: u32 = 90;
+
+#[derive(Deserialize)]
+pub struct CreateToken {
+    /// What the token is for, e.g. `"list bot"`.
+    pub name: String,
+
+    /// How many days the token works for, at most [MAX_TOKEN_DAYS], which is also the default.
+    #[serde(default)]
+    pub expires_in_days: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct CreatedToken {
+    #[serde(flatten)]
+    pub info: PersonalToken,
+
+    /// The token to send as `Authorization: Bearer <token>`. It isn't stored, so this is the only
+    /// time that it's shown.
+    pub token: String,
+}
+
+/// Get the personal access tokens of the logged in user that haven't been revoked or expired
+#[get("/me/tokens")]
+pub async fn list_tokens(user: User, storage: web::Data<dyn Storage>) -> Result<impl Responder> {
+    if let Some(response) = check_logged_in(&user) {
+        return Ok(response);
+    }
+
+    let tokens = storage.personal_tokens(user.info.cid).await?;
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+/// Create a personal access token, that can be used instead of logging in as the user until it
+/// expires
+#[post("/me/tokens")]
+pub async fn create_token(
+    user: User,
+    storage: web::Data<dyn Storage>,
+    new_token: Json<CreateToken>,
+) -> Result<impl Responder> {
+    if let Some(response) = check_logged_in(&user) {
+        return Ok(response);
+    }
+
+    let new_token = new_token.into_inner();
+    let name = new_token.name.trim().to_string();
+    if name.is_empty() {
+        return Ok(HttpResponse::BadRequest().body("Token name may not be empty"));
+    }
+
+    let days = new_token.expires_in_days.unwrap_or(MAX_TOKEN_DAYS);
+    if !(1..=MAX_TOKEN_DAYS).contains(&days) {
+        return Ok(HttpResponse::BadRequest()
+            .body(format!("Tokens must expire in 1 to {MAX_TOKEN_DAYS} days")));
+    }
+    let expires_at = Utc::now().naive_utc() + Duration::days(days.into());
+
+    let token = token::generate(PERSONAL_TOKEN_PREFIX);
+    let info = storage
+        .create_personal_token(user.info, name, token::hash(&token), expires_at)
+        .await?;
+
+    log::info!(
+        "{} created personal access token {:?}",
+        info.owner,
+        info.name
+    );
+    Ok(HttpResponse::Created().json(CreatedToken { info, token }))
+}
+
+/// Revoke a personal access token of the logged in user, so that it can't be used anymore
+#[delete("/me/tokens/{id}")]
+pub async fn revoke_token(
+    user: User,
+    storage: web::Data<dyn Storage>,
+    path: web::Path<i32>,
+) -> Result<impl Responder> {
+    if let Some(response) = check_logged_in(&user) {
+        return Ok(response);
+    }
+
+    let id = path.into_inner();
+    let cid = user.info.cid;
+
+    if !storage.revoke_personal_token(cid.clone(), id).await? {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    log::info!("{cid} revoked personal access token {id}");
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Check that the user logged in, rather than using a personal access token.
+///
+/// Returns the response to send if they didn't.
+fn check_logged_in(user: &User) -> Option<HttpResponse> {
+    if user.access_token.is_some() {
+        return None;
+    }
+
+    Some(
+        HttpResponse::Forbidden()
+            .body("Personal access tokens can't be used to manage personal access tokens"),
+    )
+}
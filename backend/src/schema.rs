@@ -1,9 +1,41 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    admin_user (cid) {
+        cid -> Text,
+        added_by -> Text,
+        added_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    api_token (id) {
+        id -> Int4,
+        name -> Text,
+        token_hash -> Text,
+        scopes -> Text,
+        created_by -> Text,
+        created_at -> Timestamp,
+        last_used_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     custom_list (id) {
         id -> Int4,
         name -> Text,
+        owner -> Text,
+        visibility -> Text,
+        share_token -> Text,
+    }
+}
+
+diesel::table! {
+    custom_list_editor (list_id, kind, name) {
+        list_id -> Int4,
+        kind -> Text,
+        name -> Text,
     }
 }
 
@@ -11,6 +43,41 @@ diesel::table! {
     custom_list_entry (list_id, song_hash) {
         list_id -> Int4,
         song_hash -> Text,
+        position -> Int4,
+    }
+}
+
+diesel::table! {
+    personal_token (id) {
+        id -> Int4,
+        owner -> Text,
+        owner_nick -> Text,
+        name -> Text,
+        token_hash -> Text,
+        created_at -> Timestamp,
+        last_used_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    queue_entry (id) {
+        id -> Int4,
+        session_id -> Int4,
+        song_hash -> Text,
+        singer -> Text,
+        singer_cid -> Nullable<Text>,
+        position -> Int4,
+        state -> Text,
+    }
+}
+
+diesel::table! {
+    queue_session (id) {
+        id -> Int4,
+        name -> Text,
+        owner -> Text,
     }
 }
 
@@ -27,10 +94,48 @@ diesel::table! {
         bpm -> Text,
         duet_singer_1 -> Nullable<Text>,
         duet_singer_2 -> Nullable<Text>,
+        lyrics -> Nullable<Text>,
+        removed_at -> Nullable<Timestamptz>,
+        content_hash -> Nullable<Text>,
+        cover_hash -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    song_revision (id) {
+        id -> Int4,
+        created_at -> Timestamptz,
+        created_by -> Text,
+        rollback_of -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    song_revision_change (revision_id, song_hash) {
+        revision_id -> Int4,
+        song_hash -> Text,
+        old -> Nullable<Jsonb>,
+        new -> Nullable<Jsonb>,
     }
 }
 
+diesel::joinable!(custom_list_editor -> custom_list (list_id));
 diesel::joinable!(custom_list_entry -> custom_list (list_id));
 diesel::joinable!(custom_list_entry -> song (song_hash));
+diesel::joinable!(queue_entry -> queue_session (session_id));
+diesel::joinable!(queue_entry -> song (song_hash));
+diesel::joinable!(song_revision_change -> song_revision (revision_id));
 
-diesel::allow_tables_to_appear_in_same_query!(custom_list, custom_list_entry, song,);
+diesel::allow_tables_to_appear_in_same_query!(
+    admin_user,
+    api_token,
+    custom_list,
+    custom_list_editor,
+    custom_list_entry,
+    personal_token,
+    queue_entry,
+    queue_session,
+    song,
+    song_revision,
+    song_revision_change,
+);
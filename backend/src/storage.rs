@@ -0,0 +1,602 @@
+//! Where songs, custom lists and covers are stored.
+//!
+//! The routes for these use a [Storage], so that singIT can run either against Postgres or against
+//! an embedded SQLite database. Queues, lyrics, revisions and `POST /songs/sync` are only
+//! available with Postgres, and use [DbPool] directly.
+
+pub mod postgres;
+pub mod sqlite;
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use actix_web::rt::task::spawn_blocking;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use diesel::AsChangeset;
+use eyre::{eyre, Context};
+use log::{info, warn};
+use singit_lib::{CoverManifest, UserInfo};
+
+use crate::{
+    admin::{AdminUser, ApiToken, Scopes},
+    cover::{self, CoverFile, CoverVariant, ProcessedCover, ThumbnailFormat},
+    db::{self, DbPool},
+    route::{
+        auth::User,
+        custom_list::{ListEdit, UpdateList},
+    },
+    schema,
+    token::PersonalToken,
+    CustomList, CustomListEditor, NewSong, Opt, PutSongsResponse, Song,
+};
+
+pub use postgres::PgStorage;
+pub use sqlite::SqliteStorage;
+
+/// URL prefix of [Opt::database_url] that selects [SqliteStorage].
+pub const SQLITE_URL_PREFIX: &str = "sqlite://";
+
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Get all songs that haven't been removed.
+    async fn songs(&self) -> eyre::Result<Vec<Song>>;
+
+    /// Get the songs that have been removed from the song list, but are still in a custom list or
+    /// a queue.
+    async fn removed_songs(&self) -> eyre::Result<Vec<Song>>;
+
+    /// Replace the song list. Songs that are not in `new_songs` are marked as removed.
+    ///
+    /// If `dry_run` is set, nothing is changed.
+    async fn replace_songs(
+        &self,
+        new_songs: Vec<NewSong>,
+        created_by: String,
+        dry_run: bool,
+    ) -> eyre::Result<PutSongsResponse>;
+
+    /// Get all custom lists, and their editors.
+    async fn custom_lists(&self) -> eyre::Result<Vec<(CustomList, Vec<CustomListEditor>)>>;
+
+    /// Get a custom list, and its editors.
+    async fn custom_list(
+        &self,
+        list: i32,
+    ) -> eyre::Result<Option<(CustomList, Vec<CustomListEditor>)>>;
+
+    async fn create_custom_list(&self, name: String, owner: String) -> eyre::Result<CustomList>;
+
+    async fn update_custom_list(&self, list: i32, update: UpdateList) -> eyre::Result<CustomList>;
+
+    /// Delete a custom list and all of its entries.
+    async fn delete_custom_list(&self, list: i32) -> eyre::Result<()>;
+
+    /// Replace the editors of a custom list.
+    async fn set_custom_list_editors(
+        &self,
+        list: i32,
+        editors: Vec<CustomListEditor>,
+    ) -> eyre::Result<()>;
+
+    /// Get the hashes of the songs in a custom list, in order.
+    async fn custom_list_entries(&self, list: i32) -> eyre::Result<Vec<String>>;
+
+    /// Add a song to the end of a custom list, unless it's already in it.
+    ///
+    /// Checks that `editor` may edit the list in the same transaction, as do the other entry
+    /// changes.
+    async fn insert_custom_list_entry(
+        &self,
+        list: i32,
+        song_hash: String,
+        editor: &User,
+    ) -> eyre::Result<ListEdit<()>>;
+
+    /// Move a song to another index in a custom list. Gives `false` if it isn't in the list.
+    async fn move_custom_list_entry(
+        &self,
+        list: i32,
+        song_hash: String,
+        index: usize,
+        editor: &User,
+    ) -> eyre::Result<ListEdit<bool>>;
+
+    /// Remove a song from a custom list. Gives `false` if it wasn't in the list.
+    async fn remove_custom_list_entry(
+        &self,
+        list: i32,
+        song_hash: String,
+        editor: &User,
+    ) -> eyre::Result<ListEdit<bool>>;
+
+    /// Find the file of a version of the cover of a song. Returns `None` if the song has no cover.
+    async fn cover_file(
+        &self,
+        song_hash: &str,
+        variant: CoverVariant,
+    ) -> eyre::Result<Option<CoverFile>>;
+
+    /// Store a cover, and make it the cover of a song.
+    async fn write_cover(&self, song_hash: &str, cover: ProcessedCover) -> eyre::Result<()>;
+
+    /// Get the hash of the cover of every song that has one.
+    async fn cover_manifest(&self) -> eyre::Result<CoverManifest>;
+
+    /// Delete the cover of a song. Returns `false` if it had none.
+    ///
+    /// The cover of the song is also cleared in the database, along with its content hash so that
+    /// the next sync uploads it again.
+    async fn delete_cover(&self, song_hash: &str) -> eyre::Result<bool>;
+
+    /// Delete all song covers, and return how many songs had one.
+    ///
+    /// Clears the covers in the database like [Storage::delete_cover].
+    async fn delete_covers(&self) -> eyre::Result<usize>;
+
+    /// Check if a user has been made admin with [Storage::add_admin].
+    async fn is_admin(&self, cid: &str) -> eyre::Result<bool>;
+
+    /// Get the users that have been made admin.
+    async fn admins(&self) -> eyre::Result<Vec<AdminUser>>;
+
+    /// Make a user admin. Does nothing if they already are.
+    async fn add_admin(&self, cid: String, added_by: String) -> eyre::Result<()>;
+
+    /// Stop a user from being admin. Returns `false` if they weren't.
+    async fn remove_admin(&self, cid: String) -> eyre::Result<bool>;
+
+    /// Get all API tokens, including revoked ones.
+    async fn api_tokens(&self) -> eyre::Result<Vec<ApiToken>>;
+
+    async fn create_api_token(
+        &self,
+        name: String,
+        scopes: Scopes,
+        token_hash: String,
+        created_by: String,
+    ) -> eyre::Result<ApiToken>;
+
+    /// Get the API token with a hash, unless it has been revoked, and mark it as used.
+    async fn use_api_token(&self, token_hash: &str) -> eyre::Result<Option<ApiToken>>;
+
+    /// Revoke an API token. Returns `false` if there's no such token, or if it's already revoked.
+    async fn revoke_api_token(&self, id: i32) -> eyre::Result<bool>;
+
+    /// Get the personal access tokens of a user that haven't been revoked or expired.
+    async fn personal_tokens(&self, owner: String) -> eyre::Result<Vec<PersonalToken>>;
+
+    /// Create a personal access token that authenticates as `owner` until `expires_at`.
+    async fn create_personal_token(
+        &self,
+        owner: UserInfo,
+        name: String,
+        token_hash: String,
+        expires_at: NaiveDateTime,
+    ) -> eyre::Result<PersonalToken>;
+
+    /// Get the personal access token with a hash, unless it has been revoked or has expired, and
+    /// mark it as used.
+    async fn use_personal_token(&self, token_hash: &str) -> eyre::Result<Option<PersonalToken>>;
+
+    /// Revoke a personal access token of `owner`. Returns `false` if they have no such token, or if
+    /// it's already revoked.
+    async fn revoke_personal_token(&self, owner: String, id: i32) -> eyre::Result<bool>;
+}
+
+/// Clears the cover of a song in the database, when it has been deleted.
+///
+/// The content hash includes the cover, so it's cleared too to have the next sync upload the song
+/// again, with its cover.
+#[derive(AsChangeset)]
+#[diesel(table_name = schema::song, treat_none_as_null = true)]
+struct ClearCover {
+    cover: Option<String>,
+    cover_hash: Option<String>,
+    content_hash: Option<String>,
+}
+
+const CLEAR_COVER: ClearCover = ClearCover {
+    cover: None,
+    cover_hash: None,
+    content_hash: None,
+};
+
+/// Song covers, stored as files in a directory.
+///
+/// Covers are stored by their hash, so that songs with the same cover share its files:
+///
+/// - `blobs/<cover_hash>/original` is the cover as it was uploaded,
+/// - `blobs/<cover_hash>/<size>.<jpg|webp>` are its thumbnails, and
+/// - `songs/<song_hash>` contains the hash of the cover of a song.
+///
+/// Covers that were uploaded before this layout are named after their song, directly in the
+/// directory, and are served as they are.
+///
+/// Files are written to `tmp/` and then moved into place, so that they're never seen half-written.
+#[derive(Clone)]
+pub struct CoverDir {
+    path: PathBuf,
+
+    /// How many songs use each cover, by cover hash. Counted when the directory is opened.
+    ///
+    /// Held while covers are changed, so that the files of a cover aren't deleted while another
+    /// song is being given the same cover.
+    users: Arc<Mutex<CoverUsers>>,
+}
+
+type CoverUsers = HashMap<String, usize>;
+
+const BLOBS_DIR: &str = "blobs";
+const SONGS_DIR: &str = "songs";
+const TMP_DIR: &str = "tmp";
+
+impl CoverDir {
+    /// Open the directory at `path`, and clean up after any writes that were interrupted.
+    pub async fn open(path: impl Into<PathBuf>) -> eyre::Result<Self> {
+        let covers = CoverDir {
+            path: path.into(),
+            users: Default::default(),
+        };
+
+        covers
+            .blocking(|covers| {
+                let tmp_dir = covers.path.join(TMP_DIR);
+                if tmp_dir.is_dir() {
+                    fs::remove_dir_all(&tmp_dir)
+                        .wrap_err_with(|| format!("Failed to remove {tmp_dir:?}"))?;
+                }
+
+                let mut users = covers.lock();
+                for song_hash in list_files(&covers.path.join(SONGS_DIR))? {
+                    if let Some(cover_hash) = covers.cover_hash(&song_hash)? {
+                        *users.entry(cover_hash).or_default() += 1;
+                    }
+                }
+
+                Ok(())
+            })
+            .await?;
+
+        Ok(covers)
+    }
+
+    /// Find the file of a version of the cover of a song.
+    pub async fn file(
+        &self,
+        song_hash: &str,
+        variant: CoverVariant,
+    ) -> eyre::Result<Option<CoverFile>> {
+        let song_hash = song_hash.to_string();
+        self.blocking(move |covers| covers.file_blocking(&song_hash, variant))
+            .await
+    }
+
+    /// Store a cover, unless an identical one already is, and make it the cover of a song.
+    pub async fn write(&self, song_hash: &str, cover: ProcessedCover) -> eyre::Result<()> {
+        let song_hash = song_hash.to_string();
+        self.blocking(move |covers| {
+            let mut users = covers.lock();
+            covers.write_blocking(&mut users, &song_hash, &cover)
+        })
+        .await
+    }
+
+    /// List the covers of all songs, and their hashes.
+    pub async fn manifest(&self) -> eyre::Result<CoverManifest> {
+        self.blocking(|covers| covers.manifest_blocking()).await
+    }
+
+    /// Delete the cover of a song, and its files if no other song uses them.
+    ///
+    /// Returns `false` if the song had no cover.
+    pub async fn delete(&self, song_hash: &str) -> eyre::Result<bool> {
+        let song_hash = song_hash.to_string();
+        self.blocking(move |covers| {
+            let mut users = covers.lock();
+            covers.delete_blocking(&mut users, &song_hash)
+        })
+        .await
+    }
+
+    /// Delete all covers, and return how many songs had one.
+    pub async fn delete_all(&self) -> eyre::Result<usize> {
+        self.blocking(|covers| {
+            let mut users = covers.lock();
+            covers.delete_all_blocking(&mut users)
+        })
+        .await
+    }
+
+    /// Call `f` on a thread where it's ok to block.
+    async fn blocking<T, F>(&self, f: F) -> eyre::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&CoverDir) -> eyre::Result<T> + Send + 'static,
+    {
+        let covers = self.clone();
+        spawn_blocking(move || f(&covers))
+            .await
+            .wrap_err("Cover thread panicked")?
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CoverUsers> {
+        self.users.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn file_blocking(
+        &self,
+        song_hash: &str,
+        variant: CoverVariant,
+    ) -> eyre::Result<Option<CoverFile>> {
+        let Some(cover_hash) = self.cover_hash(song_hash)? else {
+            return cover_file(self.path.join(song_hash));
+        };
+
+        let blob_dir = self.blob_dir(&cover_hash);
+        if let Some(cover) = cover_file(blob_dir.join(variant.file_name()))? {
+            return Ok(Some(cover));
+        }
+
+        // WebP thumbnails are skipped when they would be larger than JPEG ones
+        let CoverVariant::Thumbnail { size, .. } = variant else {
+            return Ok(None);
+        };
+        let jpeg = CoverVariant::Thumbnail {
+            size,
+            format: ThumbnailFormat::Jpeg,
+        };
+        cover_file(blob_dir.join(jpeg.file_name()))
+    }
+
+    fn write_blocking(
+        &self,
+        users: &mut CoverUsers,
+        song_hash: &str,
+        cover: &ProcessedCover,
+    ) -> eyre::Result<()> {
+        let old_cover_hash = self.cover_hash(song_hash)?;
+
+        let blob_dir = self.blob_dir(&cover.hash);
+        let original = blob_dir.join(CoverVariant::Original.file_name());
+
+        if !original.is_file() {
+            fs::create_dir_all(&blob_dir).wrap_err("Failed to create cover directory")?;
+            for (variant, thumbnail) in &cover.thumbnails {
+                self.write_file(&blob_dir.join(variant.file_name()), thumbnail)?;
+            }
+
+            // written last, so that its existence means that the thumbnails exist too
+            self.write_file(&original, &cover.original)?;
+        }
+
+        let songs_dir = self.path.join(SONGS_DIR);
+        fs::create_dir_all(&songs_dir).wrap_err("Failed to create cover directory")?;
+        self.write_file(&songs_dir.join(song_hash), cover.hash.as_bytes())?;
+
+        // the cover may have been uploaded before covers were stored by hash
+        remove_file(&self.path.join(song_hash))?;
+
+        if old_cover_hash.as_ref() == Some(&cover.hash) {
+            return Ok(());
+        }
+
+        *users.entry(cover.hash.clone()).or_default() += 1;
+        match old_cover_hash {
+            Some(old_cover_hash) => self.release_blob(users, &old_cover_hash),
+            None => Ok(()),
+        }
+    }
+
+    fn manifest_blocking(&self) -> eyre::Result<CoverManifest> {
+        let mut manifest = CoverManifest::default();
+
+        for song_hash in list_files(&self.path)? {
+            manifest.covers.insert(song_hash, None);
+        }
+
+        for song_hash in list_files(&self.path.join(SONGS_DIR))? {
+            let cover_hash = self.cover_hash(&song_hash)?;
+            manifest.covers.insert(song_hash, cover_hash);
+        }
+
+        Ok(manifest)
+    }
+
+    fn delete_blocking(&self, users: &mut CoverUsers, song_hash: &str) -> eyre::Result<bool> {
+        let had_legacy_cover = remove_file(&self.path.join(song_hash))?;
+
+        let Some(cover_hash) = self.cover_hash(song_hash)? else {
+            return Ok(had_legacy_cover);
+        };
+        remove_file(&self.path.join(SONGS_DIR).join(song_hash))?;
+        self.release_blob(users, &cover_hash)?;
+
+        Ok(true)
+    }
+
+    fn delete_all_blocking(&self, users: &mut CoverUsers) -> eyre::Result<usize> {
+        let legacy_covers = list_files(&self.path)?;
+        for song_hash in &legacy_covers {
+            remove_file(&self.path.join(song_hash))?;
+        }
+
+        let count = legacy_covers.len() + list_files(&self.path.join(SONGS_DIR))?.len();
+
+        for dir in [SONGS_DIR, BLOBS_DIR, TMP_DIR] {
+            let dir = self.path.join(dir);
+            if dir.is_dir() {
+                fs::remove_dir_all(&dir).wrap_err_with(|| format!("Failed to remove {dir:?}"))?;
+            }
+        }
+        users.clear();
+
+        Ok(count)
+    }
+
+    /// Get the hash of the cover of a song.
+    fn cover_hash(&self, song_hash: &str) -> eyre::Result<Option<String>> {
+        let cover_hash = read_file(&self.path.join(SONGS_DIR).join(song_hash))?;
+        Ok(cover_hash.map(|hash| String::from_utf8_lossy(&hash).trim().to_string()))
+    }
+
+    /// Count one song less as using a cover, and delete its files if no song uses it anymore.
+    fn release_blob(&self, users: &mut CoverUsers, cover_hash: &str) -> eyre::Result<()> {
+        if let Some(count) = users.get_mut(cover_hash) {
+            *count -= 1;
+            if *count > 0 {
+                return Ok(());
+            }
+            users.remove(cover_hash);
+        }
+
+        let blob_dir = self.blob_dir(cover_hash);
+        if blob_dir.is_dir() {
+            fs::remove_dir_all(&blob_dir)
+                .wrap_err_with(|| format!("Failed to remove {blob_dir:?}"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a file to `tmp/`, and then move it to `path`.
+    ///
+    /// Must be called while holding [CoverDir::lock], since every write uses the same temporary file.
+    fn write_file(&self, path: &Path, contents: &[u8]) -> eyre::Result<()> {
+        let tmp_dir = self.path.join(TMP_DIR);
+        fs::create_dir_all(&tmp_dir).wrap_err("Failed to create cover directory")?;
+
+        let tmp = tmp_dir.join("cover");
+        let mut file = File::create(&tmp).wrap_err_with(|| format!("Failed to create {tmp:?}"))?;
+        file.write_all(contents)
+            .and_then(|()| file.sync_all())
+            .wrap_err_with(|| format!("Failed to write {tmp:?}"))?;
+
+        fs::rename(&tmp, path).wrap_err_with(|| format!("Failed to move {tmp:?} to {path:?}"))
+    }
+
+    fn blob_dir(&self, cover_hash: &str) -> PathBuf {
+        self.path.join(BLOBS_DIR).join(cover_hash)
+    }
+}
+
+/// Read a file, or return `None` if it doesn't exist.
+fn read_file(path: &Path) -> eyre::Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).wrap_err_with(|| format!("Failed to read {path:?}")),
+    }
+}
+
+/// Get the type of a cover file, or return `None` if it doesn't exist.
+fn cover_file(path: PathBuf) -> eyre::Result<Option<CoverFile>> {
+    let mut header = [0; 32];
+    let header_len = match File::open(&path).and_then(|mut file| file.read(&mut header)) {
+        Ok(len) => len,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).wrap_err_with(|| format!("Failed to read {path:?}")),
+    };
+
+    let mime_type = cover::mime_type(&header[..header_len]);
+    Ok(Some(CoverFile { path, mime_type }))
+}
+
+/// Remove a file, if it exists. Returns `false` if it didn't.
+fn remove_file(path: &Path) -> eyre::Result<bool> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e).wrap_err_with(|| format!("Failed to remove {path:?}")),
+    }
+}
+
+/// Get the names of the files in a directory, or nothing if it doesn't exist.
+fn list_files(dir: &Path) -> eyre::Result<Vec<String>> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut names = vec![];
+    for f in dir.read_dir().wrap_err("Failed to list covers")? {
+        let f = f.wrap_err("Failed to list covers")?;
+        if f.path().is_file() {
+            names.push(f.file_name().to_string_lossy().into_owned());
+        }
+    }
+
+    Ok(names)
+}
+
+/// Set up the storage selected by [Opt::database_url].
+///
+/// Also returns the Postgres pool, if that's what is used, for the routes that need it.
+pub async fn setup(opt: &Opt) -> eyre::Result<(Arc<dyn Storage>, Option<DbPool>)> {
+    let Some(covers_dir) = &opt.covers_dir else {
+        return Err(eyre!("COVERS_DIR must be set"));
+    };
+    let covers = CoverDir::open(covers_dir).await?;
+
+    if let Some(path) = opt.database_url.strip_prefix(SQLITE_URL_PREFIX) {
+        info!("using sqlite database at {path:?}");
+        warn!("syncing, revisions, lyrics and queues need postgres, and answer 501 with sqlite");
+        let storage = SqliteStorage::open(path, covers).await?;
+        return Ok((Arc::new(storage), None));
+    }
+
+    let pool = db::setup(opt).await?;
+    let storage = PgStorage::new(pool.clone(), covers);
+    Ok((Arc::new(storage), Some(pool)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{ImageFormat, RgbImage};
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn processed_cover(color: u8) -> ProcessedCover {
+        let mut png = vec![];
+        RgbImage::from_pixel(100, 100, [color, 0, 0].into())
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        cover::process(png).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn shared_covers_are_counted_when_opened() {
+        let dir = TempDir::new().unwrap();
+        let covers = CoverDir::open(dir.path()).await.unwrap();
+
+        let red = processed_cover(255);
+        let red_blob = covers.blob_dir(&red.hash);
+        covers.write("a", red).await.unwrap();
+        covers.write("b", processed_cover(255)).await.unwrap();
+        covers.write("c", processed_cover(255)).await.unwrap();
+
+        // replacing a cover keeps the files of the old one while other songs use them
+        let black = processed_cover(0);
+        let black_blob = covers.blob_dir(&black.hash);
+        covers.write("c", black).await.unwrap();
+        assert!(red_blob.is_dir());
+        assert!(black_blob.is_dir());
+
+        let covers = CoverDir::open(dir.path()).await.unwrap();
+        assert!(covers.delete("a").await.unwrap());
+        assert!(red_blob.is_dir());
+        assert!(covers.delete("b").await.unwrap());
+        assert!(!red_blob.exists());
+
+        covers.write("c", processed_cover(255)).await.unwrap();
+        assert!(!black_blob.exists());
+        assert!(red_blob.is_dir());
+    }
+}
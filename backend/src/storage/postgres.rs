@@ -0,0 +1,667 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use diesel::{
+    dsl::now, BoolExpressionMethods, ExpressionMethods, OptionalExtension, QueryDsl,
+    SelectableHelper,
+};
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use eyre::{eyre, Context};
+use singit_lib::{CoverManifest, PutSongs, UserInfo};
+
+use super::{CoverDir, Storage, CLEAR_COVER};
+use crate::{
+    admin::{AdminUser, ApiToken, Scopes},
+    cover::{CoverFile, CoverVariant, ProcessedCover},
+    db::DbPool,
+    revision,
+    route::{
+        auth::User,
+        custom_list::{may_edit, ListEdit, UpdateList},
+    },
+    schema,
+    token::PersonalToken,
+    upsert_songs, CustomList, CustomListEditor, NewSong, PutSongsResponse, Song,
+};
+
+/// [Storage] backed by a Postgres database.
+pub struct PgStorage {
+    pool: DbPool,
+    covers: CoverDir,
+}
+
+impl PgStorage {
+    pub fn new(pool: DbPool, covers: CoverDir) -> Self {
+        PgStorage { pool, covers }
+    }
+}
+
+#[async_trait]
+impl Storage for PgStorage {
+    async fn songs(&self) -> eyre::Result<Vec<Song>> {
+        use schema::song::dsl::*;
+
+        let mut db = self.pool.get().await?;
+
+        song.filter(removed_at.is_null())
+            .select(Song::as_select())
+            .load(&mut db)
+            .await
+            .wrap_err("Failed to query db for songs")
+    }
+
+    async fn removed_songs(&self) -> eyre::Result<Vec<Song>> {
+        use schema::song::dsl::*;
+        use schema::{custom_list_entry, queue_entry};
+
+        let mut db = self.pool.get().await?;
+
+        let in_lists = custom_list_entry::table.select(custom_list_entry::song_hash);
+        let in_queues = queue_entry::table.select(queue_entry::song_hash);
+
+        song.filter(removed_at.is_not_null())
+            .filter(song_hash.eq_any(in_lists).or(song_hash.eq_any(in_queues)))
+            .select(Song::as_select())
+            .load(&mut db)
+            .await
+            .wrap_err("Failed to query db for removed songs")
+    }
+
+    async fn replace_songs(
+        &self,
+        new_songs: Vec<NewSong>,
+        created_by: String,
+        dry_run: bool,
+    ) -> eyre::Result<PutSongsResponse> {
+        use schema::song::dsl::*;
+
+        let mut db = self.pool.get().await?;
+
+        db.transaction(|mut db| {
+            Box::pin(async move {
+                // Get list of existing songs
+                let old_songs = song
+                    .filter(removed_at.is_null())
+                    .select(NewSong::as_select())
+                    .load(&mut db)
+                    .await
+                    .wrap_err("Failed to select all songs")?;
+
+                // Remove songs which do not appear in songies
+                let to_delete = revision::missing_songs(&old_songs, &new_songs);
+
+                let songs_updated = old_songs.len() - to_delete.len();
+                let songs_added = new_songs.len() - songs_updated;
+
+                let changes = revision::song_changes(old_songs, &new_songs, &to_delete);
+                if dry_run {
+                    return eyre::Ok(PutSongsResponse::DryRun(revision::diff(&changes)));
+                }
+
+                let songs_deleted = diesel::update(song.filter(song_hash.eq_any(to_delete)))
+                    .set(removed_at.eq(now))
+                    .execute(db)
+                    .await
+                    .wrap_err("Failed to mark songs as removed")?;
+
+                upsert_songs(db, &new_songs).await?;
+                revision::record(db, &created_by, None, &changes).await?;
+
+                eyre::Ok(PutSongsResponse::Done(PutSongs {
+                    songs_added,
+                    songs_deleted,
+                    songs_updated,
+                }))
+            })
+        })
+        .await
+    }
+
+    async fn custom_lists(&self) -> eyre::Result<Vec<(CustomList, Vec<CustomListEditor>)>> {
+        use schema::custom_list::dsl::*;
+        use schema::custom_list_editor::dsl::custom_list_editor;
+
+        let mut db = self.pool.get().await?;
+        let lists: Vec<CustomList> = custom_list
+            .select(CustomList::as_select())
+            .order_by(id)
+            .load(&mut db)
+            .await
+            .wrap_err("Failed to query custom lists")?;
+
+        let all_editors: Vec<CustomListEditor> = custom_list_editor
+            .select(CustomListEditor::as_select())
+            .load(&mut db)
+            .await
+            .wrap_err("Failed to query custom list editors")?;
+
+        let mut editors_by_list: HashMap<i32, Vec<CustomListEditor>> = HashMap::new();
+        for editor in all_editors {
+            editors_by_list
+                .entry(editor.list_id)
+                .or_default()
+                .push(editor);
+        }
+
+        Ok(lists
+            .into_iter()
+            .map(|list| {
+                let editors = editors_by_list.remove(&list.id).unwrap_or_default();
+                (list, editors)
+            })
+            .collect())
+    }
+
+    async fn custom_list(
+        &self,
+        list: i32,
+    ) -> eyre::Result<Option<(CustomList, Vec<CustomListEditor>)>> {
+        use schema::custom_list::dsl::custom_list;
+
+        let mut db = self.pool.get().await?;
+
+        let Some(list) = custom_list
+            .find(list)
+            .select(CustomList::as_select())
+            .first(&mut db)
+            .await
+            .optional()
+            .wrap_err("Failed to query custom list")?
+        else {
+            return Ok(None);
+        };
+
+        let editors = load_editors(&mut db, list.id)
+            .await
+            .wrap_err("Failed to query custom list editors")?;
+
+        Ok(Some((list, editors)))
+    }
+
+    async fn create_custom_list(
+        &self,
+        new_name: String,
+        new_owner: String,
+    ) -> eyre::Result<CustomList> {
+        use schema::custom_list::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        diesel::insert_into(custom_list)
+            .values((name.eq(new_name), owner.eq(new_owner)))
+            .returning(CustomList::as_returning())
+            .get_result(&mut db)
+            .await
+            .wrap_err("Failed to create custom list")
+    }
+
+    async fn update_custom_list(&self, list: i32, update: UpdateList) -> eyre::Result<CustomList> {
+        use schema::custom_list::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        diesel::update(custom_list.find(list))
+            .set(update)
+            .returning(CustomList::as_returning())
+            .get_result(&mut db)
+            .await
+            .wrap_err("Failed to update custom list")
+    }
+
+    async fn delete_custom_list(&self, list: i32) -> eyre::Result<()> {
+        use schema::custom_list::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        diesel::delete(custom_list.find(list))
+            .execute(&mut db)
+            .await
+            .wrap_err("Failed to delete custom list")?;
+
+        Ok(())
+    }
+
+    async fn set_custom_list_editors(
+        &self,
+        list: i32,
+        new_editors: Vec<CustomListEditor>,
+    ) -> eyre::Result<()> {
+        use schema::custom_list::dsl::custom_list;
+        use schema::custom_list_editor::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        db.transaction(|db| {
+            Box::pin(async move {
+                // wait for entry changes that have checked the old editors, see check_editor
+                custom_list
+                    .find(list)
+                    .select(schema::custom_list::id)
+                    .for_update()
+                    .execute(db)
+                    .await?;
+
+                diesel::delete(custom_list_editor)
+                    .filter(list_id.eq(list))
+                    .execute(db)
+                    .await?;
+
+                diesel::insert_into(custom_list_editor)
+                    .values(new_editors)
+                    .on_conflict_do_nothing()
+                    .execute(db)
+                    .await
+            })
+        })
+        .await
+        .wrap_err("Failed to update custom list editors")?;
+
+        Ok(())
+    }
+
+    async fn custom_list_entries(&self, list: i32) -> eyre::Result<Vec<String>> {
+        use schema::custom_list_entry::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        custom_list_entry
+            .select(song_hash)
+            .filter(list_id.eq(list))
+            .order_by(position)
+            .load(&mut db)
+            .await
+            .wrap_err("Failed to query db for custom list")
+    }
+
+    async fn insert_custom_list_entry(
+        &self,
+        list: i32,
+        new_song_hash: String,
+        editor: &User,
+    ) -> eyre::Result<ListEdit<()>> {
+        use schema::custom_list_entry::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        db.transaction(|db| {
+            Box::pin(async move {
+                if let Some(denied) = check_editor(db, editor, list).await? {
+                    return Ok(denied);
+                }
+
+                let last_position: Option<i32> = custom_list_entry
+                    .filter(list_id.eq(list))
+                    .select(position)
+                    .order_by(position.desc())
+                    .first(db)
+                    .await
+                    .optional()?;
+
+                diesel::insert_into(custom_list_entry)
+                    .values((
+                        list_id.eq(list),
+                        song_hash.eq(new_song_hash),
+                        position.eq(last_position.map_or(0, |p| p + 1)),
+                    ))
+                    .on_conflict_do_nothing()
+                    .execute(db)
+                    .await?;
+
+                eyre::Ok(ListEdit::Done(()))
+            })
+        })
+        .await
+        .wrap_err("Error inserting custom list entry")
+    }
+
+    async fn move_custom_list_entry(
+        &self,
+        list: i32,
+        moved_song_hash: String,
+        new_index: usize,
+        editor: &User,
+    ) -> eyre::Result<ListEdit<bool>> {
+        use schema::custom_list_entry::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        db.transaction(|db| {
+            Box::pin(async move {
+                if let Some(denied) = check_editor(db, editor, list).await? {
+                    return Ok(denied);
+                }
+
+                let mut entries: Vec<(String, i32)> = custom_list_entry
+                    .filter(list_id.eq(list))
+                    .select((song_hash, position))
+                    .order_by(position)
+                    .load(db)
+                    .await?;
+
+                let Some(old_index) = entries.iter().position(|(h, _)| *h == moved_song_hash)
+                else {
+                    return Ok(ListEdit::Done(false));
+                };
+
+                let moved = entries.remove(old_index);
+                entries.insert(new_index.min(entries.len()), moved);
+
+                for (new_position, (hash, old_position)) in (0..).zip(&entries) {
+                    if *old_position != new_position {
+                        diesel::update(custom_list_entry.find((list, hash)))
+                            .set(position.eq(new_position))
+                            .execute(db)
+                            .await?;
+                    }
+                }
+
+                eyre::Ok(ListEdit::Done(true))
+            })
+        })
+        .await
+        .wrap_err("Failed to reorder custom list")
+    }
+
+    async fn remove_custom_list_entry(
+        &self,
+        list: i32,
+        remove_song_hash: String,
+        editor: &User,
+    ) -> eyre::Result<ListEdit<bool>> {
+        use schema::custom_list_entry::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        db.transaction(|db| {
+            Box::pin(async move {
+                if let Some(denied) = check_editor(db, editor, list).await? {
+                    return Ok(denied);
+                }
+
+                let number_of_deleted_rows = diesel::delete(custom_list_entry)
+                    .filter(list_id.eq(list))
+                    .filter(song_hash.eq(remove_song_hash))
+                    .execute(db)
+                    .await
+                    .wrap_err("Failed to delete custom list entry")?;
+
+                match number_of_deleted_rows {
+                    0 => Ok(ListEdit::Done(false)),
+                    1 => Ok(ListEdit::Done(true)),
+                    2.. => Err(eyre!("Custom list delete query had multiple results")),
+                }
+            })
+        })
+        .await
+    }
+
+    async fn cover_file(
+        &self,
+        song_hash: &str,
+        variant: CoverVariant,
+    ) -> eyre::Result<Option<CoverFile>> {
+        self.covers.file(song_hash, variant).await
+    }
+
+    async fn write_cover(&self, song_hash: &str, cover: ProcessedCover) -> eyre::Result<()> {
+        self.covers.write(song_hash, cover).await
+    }
+
+    async fn cover_manifest(&self) -> eyre::Result<CoverManifest> {
+        self.covers.manifest().await
+    }
+
+    async fn delete_cover(&self, hash: &str) -> eyre::Result<bool> {
+        use schema::song::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        diesel::update(song.filter(song_hash.eq(hash)))
+            .set(CLEAR_COVER)
+            .execute(&mut db)
+            .await
+            .wrap_err("Failed to clear song cover")?;
+
+        self.covers.delete(hash).await
+    }
+
+    async fn delete_covers(&self) -> eyre::Result<usize> {
+        use schema::song::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        diesel::update(song.filter(cover.is_not_null().or(cover_hash.is_not_null())))
+            .set(CLEAR_COVER)
+            .execute(&mut db)
+            .await
+            .wrap_err("Failed to clear song covers")?;
+
+        self.covers.delete_all().await
+    }
+
+    async fn is_admin(&self, admin_cid: &str) -> eyre::Result<bool> {
+        use schema::admin_user::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        let admin: Option<String> = admin_user
+            .find(admin_cid)
+            .select(cid)
+            .first(&mut db)
+            .await
+            .optional()
+            .wrap_err("Failed to query admins")?;
+
+        Ok(admin.is_some())
+    }
+
+    async fn admins(&self) -> eyre::Result<Vec<AdminUser>> {
+        use schema::admin_user::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        admin_user
+            .select(AdminUser::as_select())
+            .order_by(cid)
+            .load(&mut db)
+            .await
+            .wrap_err("Failed to query admins")
+    }
+
+    async fn add_admin(&self, admin_cid: String, admin_added_by: String) -> eyre::Result<()> {
+        use schema::admin_user::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        diesel::insert_into(admin_user)
+            .values((
+                cid.eq(admin_cid),
+                added_by.eq(admin_added_by),
+                added_at.eq(Utc::now().naive_utc()),
+            ))
+            .on_conflict_do_nothing()
+            .execute(&mut db)
+            .await
+            .wrap_err("Failed to add admin")?;
+
+        Ok(())
+    }
+
+    async fn remove_admin(&self, admin_cid: String) -> eyre::Result<bool> {
+        use schema::admin_user::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        let removed = diesel::delete(admin_user.find(admin_cid))
+            .execute(&mut db)
+            .await
+            .wrap_err("Failed to remove admin")?;
+
+        Ok(removed > 0)
+    }
+
+    async fn api_tokens(&self) -> eyre::Result<Vec<ApiToken>> {
+        use schema::api_token::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        api_token
+            .select(ApiToken::as_select())
+            .order_by(id)
+            .load(&mut db)
+            .await
+            .wrap_err("Failed to query API tokens")
+    }
+
+    async fn create_api_token(
+        &self,
+        new_name: String,
+        new_scopes: Scopes,
+        new_token_hash: String,
+        new_created_by: String,
+    ) -> eyre::Result<ApiToken> {
+        use schema::api_token::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        diesel::insert_into(api_token)
+            .values((
+                name.eq(new_name),
+                scopes.eq(new_scopes.to_string()),
+                token_hash.eq(new_token_hash),
+                created_by.eq(new_created_by),
+                created_at.eq(Utc::now().naive_utc()),
+            ))
+            .returning(ApiToken::as_returning())
+            .get_result(&mut db)
+            .await
+            .wrap_err("Failed to create API token")
+    }
+
+    async fn use_api_token(&self, hash: &str) -> eyre::Result<Option<ApiToken>> {
+        use schema::api_token::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        diesel::update(api_token)
+            .filter(token_hash.eq(hash))
+            .filter(revoked_at.is_null())
+            .set(last_used_at.eq(Utc::now().naive_utc()))
+            .returning(ApiToken::as_returning())
+            .get_result(&mut db)
+            .await
+            .optional()
+            .wrap_err("Failed to query API token")
+    }
+
+    async fn revoke_api_token(&self, token: i32) -> eyre::Result<bool> {
+        use schema::api_token::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        let revoked = diesel::update(api_token.find(token))
+            .filter(revoked_at.is_null())
+            .set(revoked_at.eq(Utc::now().naive_utc()))
+            .execute(&mut db)
+            .await
+            .wrap_err("Failed to revoke API token")?;
+
+        Ok(revoked > 0)
+    }
+
+    async fn personal_tokens(&self, token_owner: String) -> eyre::Result<Vec<PersonalToken>> {
+        use schema::personal_token::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        personal_token
+            .filter(owner.eq(token_owner))
+            .filter(revoked_at.is_null())
+            .filter(expires_at.gt(Utc::now().naive_utc()))
+            .select(PersonalToken::as_select())
+            .order_by(id)
+            .load(&mut db)
+            .await
+            .wrap_err("Failed to query personal access tokens")
+    }
+
+    async fn create_personal_token(
+        &self,
+        new_owner: UserInfo,
+        new_name: String,
+        new_token_hash: String,
+        new_expires_at: NaiveDateTime,
+    ) -> eyre::Result<PersonalToken> {
+        use schema::personal_token::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        diesel::insert_into(personal_token)
+            .values((
+                owner.eq(new_owner.cid),
+                owner_nick.eq(new_owner.nick),
+                name.eq(new_name),
+                token_hash.eq(new_token_hash),
+                created_at.eq(Utc::now().naive_utc()),
+                expires_at.eq(new_expires_at),
+            ))
+            .returning(PersonalToken::as_returning())
+            .get_result(&mut db)
+            .await
+            .wrap_err("Failed to create personal access token")
+    }
+
+    async fn use_personal_token(&self, hash: &str) -> eyre::Result<Option<PersonalToken>> {
+        use schema::personal_token::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        diesel::update(personal_token)
+            .filter(token_hash.eq(hash))
+            .filter(revoked_at.is_null())
+            .filter(expires_at.gt(Utc::now().naive_utc()))
+            .set(last_used_at.eq(Utc::now().naive_utc()))
+            .returning(PersonalToken::as_returning())
+            .get_result(&mut db)
+            .await
+            .optional()
+            .wrap_err("Failed to query personal access token")
+    }
+
+    async fn revoke_personal_token(&self, token_owner: String, token: i32) -> eyre::Result<bool> {
+        use schema::personal_token::dsl::*;
+
+        let mut db = self.pool.get().await?;
+        let revoked = diesel::update(personal_token.find(token))
+            .filter(owner.eq(token_owner))
+            .filter(revoked_at.is_null())
+            .set(revoked_at.eq(Utc::now().naive_utc()))
+            .execute(&mut db)
+            .await
+            .wrap_err("Failed to revoke personal access token")?;
+
+        Ok(revoked > 0)
+    }
+}
+
+/// Check that a user may edit a custom list, in the transaction that edits it.
+///
+/// The list is locked until the transaction ends, so that its editors can't change in between.
+/// Returns what to give up with if the list doesn't exist, or if the user may not edit it.
+async fn check_editor<T>(
+    db: &mut AsyncPgConnection,
+    user: &User,
+    id_of_list: i32,
+) -> eyre::Result<Option<ListEdit<T>>> {
+    use schema::custom_list::dsl::custom_list;
+
+    let Some(list) = custom_list
+        .find(id_of_list)
+        .select(CustomList::as_select())
+        .for_update()
+        .first(db)
+        .await
+        .optional()
+        .wrap_err("Failed to query custom list")?
+    else {
+        return Ok(Some(ListEdit::NoSuchList));
+    };
+
+    let editors = load_editors(db, list.id)
+        .await
+        .wrap_err("Failed to query custom list editors")?;
+    Ok((!may_edit(&list, &editors, user)).then_some(ListEdit::NotAllowed))
+}
+
+async fn load_editors(
+    db: &mut AsyncPgConnection,
+    id_of_list: i32,
+) -> diesel::QueryResult<Vec<CustomListEditor>> {
+    use schema::custom_list_editor::dsl::*;
+
+    custom_list_editor
+        .filter(list_id.eq(id_of_list))
+        .select(CustomListEditor::as_select())
+        .load(db)
+        .await
+}
@@ -0,0 +1,1033 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use actix_web::rt::task::spawn_blocking;
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use diesel::{
+    connection::SimpleConnection, dsl::now, upsert::excluded, BoolExpressionMethods, Connection,
+    ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper,
+    SqliteConnection,
+};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use eyre::{eyre, Context};
+use log::info;
+use singit_lib::{CoverManifest, PutSongs, UserInfo};
+
+use super::{CoverDir, Storage, CLEAR_COVER};
+use crate::{
+    admin::{AdminUser, ApiToken, Scopes},
+    cover::{CoverFile, CoverVariant, ProcessedCover},
+    revision,
+    route::{
+        auth::User,
+        custom_list::{may_edit, ListEdit, UpdateList},
+    },
+    schema,
+    token::PersonalToken,
+    CustomList, CustomListEditor, NewSong, PutSongsResponse, Song,
+};
+
+/// The migrations in `backend/migrations_sqlite`, embedded in the binary.
+///
+/// These are always applied when the database is opened.
+pub const SQLITE_MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations_sqlite");
+
+/// [Storage] backed by an embedded SQLite database.
+///
+/// Uses the same [schema] as Postgres. Revisions of the song list aren't recorded.
+pub struct SqliteStorage {
+    db: Arc<Mutex<SqliteConnection>>,
+    covers: CoverDir,
+}
+
+impl SqliteStorage {
+    /// Open the database at `path`, creating it if it doesn't exist.
+    pub async fn open(path: &str, covers: CoverDir) -> eyre::Result<Self> {
+        let path = path.to_string();
+        let db = spawn_blocking(move || {
+            let mut db = SqliteConnection::establish(&path)
+                .wrap_err_with(|| format!("Failed to open sqlite database {path:?}"))?;
+
+            db.batch_execute("PRAGMA foreign_keys = ON;")
+                .wrap_err("Failed to enable foreign keys")?;
+
+            let applied = db
+                .run_pending_migrations(SQLITE_MIGRATIONS)
+                .map_err(|e| eyre!(e))
+                .wrap_err("Failed to apply database migrations")?;
+            for version in applied {
+                info!("applied migration {version}");
+            }
+
+            eyre::Ok(db)
+        })
+        .await
+        .wrap_err("Database thread panicked")??;
+
+        Ok(SqliteStorage {
+            db: Arc::new(Mutex::new(db)),
+            covers,
+        })
+    }
+
+    /// Call `f` with the database connection, on a thread where it's ok to block.
+    async fn with_db<T, F>(&self, f: F) -> eyre::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut SqliteConnection) -> eyre::Result<T> + Send + 'static,
+    {
+        let db = Arc::clone(&self.db);
+        spawn_blocking(move || {
+            let mut db = db.lock().unwrap_or_else(PoisonError::into_inner);
+            f(&mut db)
+        })
+        .await
+        .wrap_err("Database thread panicked")?
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn songs(&self) -> eyre::Result<Vec<Song>> {
+        use schema::song::dsl::*;
+
+        self.with_db(|db| {
+            song.filter(removed_at.is_null())
+                .select(Song::as_select())
+                .load(db)
+                .wrap_err("Failed to query db for songs")
+        })
+        .await
+    }
+
+    async fn removed_songs(&self) -> eyre::Result<Vec<Song>> {
+        use schema::custom_list_entry;
+        use schema::song::dsl::*;
+
+        self.with_db(|db| {
+            // there are no queues in sqlite
+            let in_lists = custom_list_entry::table.select(custom_list_entry::song_hash);
+
+            song.filter(removed_at.is_not_null())
+                .filter(song_hash.eq_any(in_lists))
+                .select(Song::as_select())
+                .load(db)
+                .wrap_err("Failed to query db for removed songs")
+        })
+        .await
+    }
+
+    async fn replace_songs(
+        &self,
+        new_songs: Vec<NewSong>,
+        _created_by: String,
+        dry_run: bool,
+    ) -> eyre::Result<PutSongsResponse> {
+        use schema::song::dsl::*;
+
+        self.with_db(move |db| {
+            db.transaction(|db| {
+                let old_songs = song
+                    .filter(removed_at.is_null())
+                    .select(NewSong::as_select())
+                    .load(db)
+                    .wrap_err("Failed to select all songs")?;
+
+                let to_delete = revision::missing_songs(&old_songs, &new_songs);
+
+                let songs_updated = old_songs.len() - to_delete.len();
+                let songs_added = new_songs.len() - songs_updated;
+
+                if dry_run {
+                    let changes = revision::song_changes(old_songs, &new_songs, &to_delete);
+                    return Ok(PutSongsResponse::DryRun(revision::diff(&changes)));
+                }
+
+                let songs_deleted = diesel::update(song.filter(song_hash.eq_any(&to_delete)))
+                    .set(removed_at.eq(now))
+                    .execute(db)
+                    .wrap_err("Failed to mark songs as removed")?;
+
+                for new_song in &new_songs {
+                    diesel::insert_into(song)
+                        .values(new_song)
+                        .on_conflict(song_hash)
+                        .do_update()
+                        .set((
+                            artist.eq(excluded(artist)),
+                            title.eq(excluded(title)),
+                            language.eq(excluded(language)),
+                            genre.eq(excluded(genre)),
+                            year.eq(excluded(year)),
+                            cover.eq(excluded(cover)),
+                            video.eq(excluded(video)),
+                            bpm.eq(excluded(bpm)),
+                            duet_singer_1.eq(excluded(duet_singer_1)),
+                            duet_singer_2.eq(excluded(duet_singer_2)),
+                            lyrics.eq(excluded(lyrics)),
+                            content_hash.eq(excluded(content_hash)),
+                            cover_hash.eq(excluded(cover_hash)),
+                            // always null, which restores songs that were previously removed
+                            removed_at.eq(excluded(removed_at)),
+                        ))
+                        .execute(db)
+                        .wrap_err("Failed to insert new songs")?;
+                }
+
+                Ok(PutSongsResponse::Done(PutSongs {
+                    songs_added,
+                    songs_deleted,
+                    songs_updated,
+                }))
+            })
+        })
+        .await
+    }
+
+    async fn custom_lists(&self) -> eyre::Result<Vec<(CustomList, Vec<CustomListEditor>)>> {
+        use schema::custom_list::dsl::*;
+
+        self.with_db(|db| {
+            let lists: Vec<CustomList> = custom_list
+                .select(CustomList::as_select())
+                .order_by(id)
+                .load(db)
+                .wrap_err("Failed to query custom lists")?;
+
+            lists
+                .into_iter()
+                .map(|list| {
+                    let editors = load_editors(db, list.id)?;
+                    Ok((list, editors))
+                })
+                .collect()
+        })
+        .await
+    }
+
+    async fn custom_list(
+        &self,
+        list: i32,
+    ) -> eyre::Result<Option<(CustomList, Vec<CustomListEditor>)>> {
+        use schema::custom_list::dsl::custom_list;
+
+        self.with_db(move |db| {
+            let Some(list) = custom_list
+                .find(list)
+                .select(CustomList::as_select())
+                .first(db)
+                .optional()
+                .wrap_err("Failed to query custom list")?
+            else {
+                return Ok(None);
+            };
+
+            let editors = load_editors(db, list.id)?;
+            Ok(Some((list, editors)))
+        })
+        .await
+    }
+
+    async fn create_custom_list(
+        &self,
+        new_name: String,
+        new_owner: String,
+    ) -> eyre::Result<CustomList> {
+        use schema::custom_list::dsl::*;
+
+        self.with_db(move |db| {
+            diesel::insert_into(custom_list)
+                .values((name.eq(new_name), owner.eq(new_owner)))
+                .returning(CustomList::as_returning())
+                .get_result(db)
+                .wrap_err("Failed to create custom list")
+        })
+        .await
+    }
+
+    async fn update_custom_list(&self, list: i32, update: UpdateList) -> eyre::Result<CustomList> {
+        use schema::custom_list::dsl::*;
+
+        self.with_db(move |db| {
+            diesel::update(custom_list.find(list))
+                .set(update)
+                .returning(CustomList::as_returning())
+                .get_result(db)
+                .wrap_err("Failed to update custom list")
+        })
+        .await
+    }
+
+    async fn delete_custom_list(&self, list: i32) -> eyre::Result<()> {
+        use schema::custom_list::dsl::*;
+
+        self.with_db(move |db| {
+            diesel::delete(custom_list.find(list))
+                .execute(db)
+                .wrap_err("Failed to delete custom list")?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn set_custom_list_editors(
+        &self,
+        list: i32,
+        new_editors: Vec<CustomListEditor>,
+    ) -> eyre::Result<()> {
+        use schema::custom_list_editor::dsl::*;
+
+        self.with_db(move |db| {
+            db.transaction(|db| {
+                diesel::delete(custom_list_editor)
+                    .filter(list_id.eq(list))
+                    .execute(db)?;
+
+                for editor in &new_editors {
+                    diesel::insert_into(custom_list_editor)
+                        .values(editor)
+                        .on_conflict_do_nothing()
+                        .execute(db)?;
+                }
+
+                diesel::QueryResult::Ok(())
+            })
+            .wrap_err("Failed to update custom list editors")
+        })
+        .await
+    }
+
+    async fn custom_list_entries(&self, list: i32) -> eyre::Result<Vec<String>> {
+        use schema::custom_list_entry::dsl::*;
+
+        self.with_db(move |db| {
+            custom_list_entry
+                .select(song_hash)
+                .filter(list_id.eq(list))
+                .order_by(position)
+                .load(db)
+                .wrap_err("Failed to query db for custom list")
+        })
+        .await
+    }
+
+    async fn insert_custom_list_entry(
+        &self,
+        list: i32,
+        new_song_hash: String,
+        editor: &User,
+    ) -> eyre::Result<ListEdit<()>> {
+        use schema::custom_list_entry::dsl::*;
+
+        let editor = editor.clone();
+        self.with_db(move |db| {
+            db.transaction(|db| {
+                if let Some(denied) = check_editor(db, &editor, list)? {
+                    return Ok(denied);
+                }
+
+                let last_position: Option<i32> = custom_list_entry
+                    .filter(list_id.eq(list))
+                    .select(position)
+                    .order_by(position.desc())
+                    .first(db)
+                    .optional()?;
+
+                diesel::insert_into(custom_list_entry)
+                    .values((
+                        list_id.eq(list),
+                        song_hash.eq(new_song_hash),
+                        position.eq(last_position.map_or(0, |p| p + 1)),
+                    ))
+                    .on_conflict_do_nothing()
+                    .execute(db)?;
+
+                eyre::Ok(ListEdit::Done(()))
+            })
+            .wrap_err("Error inserting custom list entry")
+        })
+        .await
+    }
+
+    async fn move_custom_list_entry(
+        &self,
+        list: i32,
+        moved_song_hash: String,
+        new_index: usize,
+        editor: &User,
+    ) -> eyre::Result<ListEdit<bool>> {
+        use schema::custom_list_entry::dsl::*;
+
+        let editor = editor.clone();
+        self.with_db(move |db| {
+            db.transaction(|db| {
+                if let Some(denied) = check_editor(db, &editor, list)? {
+                    return Ok(denied);
+                }
+
+                let mut entries: Vec<(String, i32)> = custom_list_entry
+                    .filter(list_id.eq(list))
+                    .select((song_hash, position))
+                    .order_by(position)
+                    .load(db)?;
+
+                let Some(old_index) = entries.iter().position(|(h, _)| *h == moved_song_hash)
+                else {
+                    return Ok(ListEdit::Done(false));
+                };
+
+                let moved = entries.remove(old_index);
+                entries.insert(new_index.min(entries.len()), moved);
+
+                for (new_position, (hash, old_position)) in (0..).zip(&entries) {
+                    if *old_position != new_position {
+                        diesel::update(custom_list_entry.find((list, hash)))
+                            .set(position.eq(new_position))
+                            .execute(db)?;
+                    }
+                }
+
+                eyre::Ok(ListEdit::Done(true))
+            })
+            .wrap_err("Failed to reorder custom list")
+        })
+        .await
+    }
+
+    async fn remove_custom_list_entry(
+        &self,
+        list: i32,
+        remove_song_hash: String,
+        editor: &User,
+    ) -> eyre::Result<ListEdit<bool>> {
+        use schema::custom_list_entry::dsl::*;
+
+        let editor = editor.clone();
+        self.with_db(move |db| {
+            db.transaction(|db| {
+                if let Some(denied) = check_editor(db, &editor, list)? {
+                    return Ok(denied);
+                }
+
+                let number_of_deleted_rows = diesel::delete(custom_list_entry)
+                    .filter(list_id.eq(list))
+                    .filter(song_hash.eq(remove_song_hash))
+                    .execute(db)
+                    .wrap_err("Failed to delete custom list entry")?;
+
+                match number_of_deleted_rows {
+                    0 => Ok(ListEdit::Done(false)),
+                    1 => Ok(ListEdit::Done(true)),
+                    2.. => Err(eyre!("Custom list delete query had multiple results")),
+                }
+            })
+        })
+        .await
+    }
+
+    async fn cover_file(
+        &self,
+        song_hash: &str,
+        variant: CoverVariant,
+    ) -> eyre::Result<Option<CoverFile>> {
+        self.covers.file(song_hash, variant).await
+    }
+
+    async fn write_cover(&self, song_hash: &str, cover: ProcessedCover) -> eyre::Result<()> {
+        self.covers.write(song_hash, cover).await
+    }
+
+    async fn cover_manifest(&self) -> eyre::Result<CoverManifest> {
+        self.covers.manifest().await
+    }
+
+    async fn delete_cover(&self, hash: &str) -> eyre::Result<bool> {
+        use schema::song::dsl::*;
+
+        let hash_of_song = hash.to_string();
+        self.with_db(move |db| {
+            diesel::update(song.filter(song_hash.eq(hash_of_song)))
+                .set(CLEAR_COVER)
+                .execute(db)
+                .wrap_err("Failed to clear song cover")
+        })
+        .await?;
+
+        self.covers.delete(hash).await
+    }
+
+    async fn delete_covers(&self) -> eyre::Result<usize> {
+        use schema::song::dsl::*;
+
+        self.with_db(|db| {
+            diesel::update(song.filter(cover.is_not_null().or(cover_hash.is_not_null())))
+                .set(CLEAR_COVER)
+                .execute(db)
+                .wrap_err("Failed to clear song covers")
+        })
+        .await?;
+
+        self.covers.delete_all().await
+    }
+
+    async fn is_admin(&self, admin_cid: &str) -> eyre::Result<bool> {
+        use schema::admin_user::dsl::*;
+
+        let admin_cid = admin_cid.to_string();
+        self.with_db(move |db| {
+            let admin: Option<String> = admin_user
+                .find(admin_cid)
+                .select(cid)
+                .first(db)
+                .optional()
+                .wrap_err("Failed to query admins")?;
+
+            Ok(admin.is_some())
+        })
+        .await
+    }
+
+    async fn admins(&self) -> eyre::Result<Vec<AdminUser>> {
+        use schema::admin_user::dsl::*;
+
+        self.with_db(|db| {
+            admin_user
+                .select(AdminUser::as_select())
+                .order_by(cid)
+                .load(db)
+                .wrap_err("Failed to query admins")
+        })
+        .await
+    }
+
+    async fn add_admin(&self, admin_cid: String, admin_added_by: String) -> eyre::Result<()> {
+        use schema::admin_user::dsl::*;
+
+        self.with_db(move |db| {
+            diesel::insert_into(admin_user)
+                .values((
+                    cid.eq(admin_cid),
+                    added_by.eq(admin_added_by),
+                    added_at.eq(Utc::now().naive_utc()),
+                ))
+                .on_conflict_do_nothing()
+                .execute(db)
+                .wrap_err("Failed to add admin")?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn remove_admin(&self, admin_cid: String) -> eyre::Result<bool> {
+        use schema::admin_user::dsl::*;
+
+        self.with_db(move |db| {
+            let removed = diesel::delete(admin_user.find(admin_cid))
+                .execute(db)
+                .wrap_err("Failed to remove admin")?;
+
+            Ok(removed > 0)
+        })
+        .await
+    }
+
+    async fn api_tokens(&self) -> eyre::Result<Vec<ApiToken>> {
+        use schema::api_token::dsl::*;
+
+        self.with_db(|db| {
+            api_token
+                .select(ApiToken::as_select())
+                .order_by(id)
+                .load(db)
+                .wrap_err("Failed to query API tokens")
+        })
+        .await
+    }
+
+    async fn create_api_token(
+        &self,
+        new_name: String,
+        new_scopes: Scopes,
+        new_token_hash: String,
+        new_created_by: String,
+    ) -> eyre::Result<ApiToken> {
+        use schema::api_token::dsl::*;
+
+        self.with_db(move |db| {
+            diesel::insert_into(api_token)
+                .values((
+                    name.eq(new_name),
+                    scopes.eq(new_scopes.to_string()),
+                    token_hash.eq(new_token_hash),
+                    created_by.eq(new_created_by),
+                    created_at.eq(Utc::now().naive_utc()),
+                ))
+                .returning(ApiToken::as_returning())
+                .get_result(db)
+                .wrap_err("Failed to create API token")
+        })
+        .await
+    }
+
+    async fn use_api_token(&self, hash: &str) -> eyre::Result<Option<ApiToken>> {
+        use schema::api_token::dsl::*;
+
+        let hash = hash.to_string();
+        self.with_db(move |db| {
+            diesel::update(api_token)
+                .filter(token_hash.eq(hash))
+                .filter(revoked_at.is_null())
+                .set(last_used_at.eq(Utc::now().naive_utc()))
+                .returning(ApiToken::as_returning())
+                .get_result(db)
+                .optional()
+                .wrap_err("Failed to query API token")
+        })
+        .await
+    }
+
+    async fn revoke_api_token(&self, token: i32) -> eyre::Result<bool> {
+        use schema::api_token::dsl::*;
+
+        self.with_db(move |db| {
+            let revoked = diesel::update(api_token.find(token))
+                .filter(revoked_at.is_null())
+                .set(revoked_at.eq(Utc::now().naive_utc()))
+                .execute(db)
+                .wrap_err("Failed to revoke API token")?;
+
+            Ok(revoked > 0)
+        })
+        .await
+    }
+
+    async fn personal_tokens(&self, token_owner: String) -> eyre::Result<Vec<PersonalToken>> {
+        use schema::personal_token::dsl::*;
+
+        self.with_db(move |db| {
+            personal_token
+                .filter(owner.eq(token_owner))
+                .filter(revoked_at.is_null())
+                .filter(expires_at.gt(Utc::now().naive_utc()))
+                .select(PersonalToken::as_select())
+                .order_by(id)
+                .load(db)
+                .wrap_err("Failed to query personal access tokens")
+        })
+        .await
+    }
+
+    async fn create_personal_token(
+        &self,
+        new_owner: UserInfo,
+        new_name: String,
+        new_token_hash: String,
+        new_expires_at: NaiveDateTime,
+    ) -> eyre::Result<PersonalToken> {
+        use schema::personal_token::dsl::*;
+
+        self.with_db(move |db| {
+            diesel::insert_into(personal_token)
+                .values((
+                    owner.eq(new_owner.cid),
+                    owner_nick.eq(new_owner.nick),
+                    name.eq(new_name),
+                    token_hash.eq(new_token_hash),
+                    created_at.eq(Utc::now().naive_utc()),
+                    expires_at.eq(new_expires_at),
+                ))
+                .returning(PersonalToken::as_returning())
+                .get_result(db)
+                .wrap_err("Failed to create personal access token")
+        })
+        .await
+    }
+
+    async fn use_personal_token(&self, hash: &str) -> eyre::Result<Option<PersonalToken>> {
+        use schema::personal_token::dsl::*;
+
+        let hash = hash.to_string();
+        self.with_db(move |db| {
+            diesel::update(personal_token)
+                .filter(token_hash.eq(hash))
+                .filter(revoked_at.is_null())
+                .filter(expires_at.gt(Utc::now().naive_utc()))
+                .set(last_used_at.eq(Utc::now().naive_utc()))
+                .returning(PersonalToken::as_returning())
+                .get_result(db)
+                .optional()
+                .wrap_err("Failed to query personal access token")
+        })
+        .await
+    }
+
+    async fn revoke_personal_token(&self, token_owner: String, token: i32) -> eyre::Result<bool> {
+        use schema::personal_token::dsl::*;
+
+        self.with_db(move |db| {
+            let revoked = diesel::update(personal_token.find(token))
+                .filter(owner.eq(token_owner))
+                .filter(revoked_at.is_null())
+                .set(revoked_at.eq(Utc::now().naive_utc()))
+                .execute(db)
+                .wrap_err("Failed to revoke personal access token")?;
+
+            Ok(revoked > 0)
+        })
+        .await
+    }
+}
+
+/// Check that a user may edit a custom list, in the transaction that edits it.
+///
+/// Returns what to give up with if the list doesn't exist, or if the user may not edit it.
+fn check_editor<T>(
+    db: &mut SqliteConnection,
+    user: &User,
+    id_of_list: i32,
+) -> eyre::Result<Option<ListEdit<T>>> {
+    use schema::custom_list::dsl::custom_list;
+
+    let Some(list) = custom_list
+        .find(id_of_list)
+        .select(CustomList::as_select())
+        .first(db)
+        .optional()
+        .wrap_err("Failed to query custom list")?
+    else {
+        return Ok(Some(ListEdit::NoSuchList));
+    };
+
+    let editors = load_editors(db, list.id)?;
+    Ok((!may_edit(&list, &editors, user)).then_some(ListEdit::NotAllowed))
+}
+
+fn load_editors(db: &mut SqliteConnection, id_of_list: i32) -> eyre::Result<Vec<CustomListEditor>> {
+    use schema::custom_list_editor::dsl::*;
+
+    custom_list_editor
+        .filter(list_id.eq(id_of_list))
+        .select(CustomListEditor::as_select())
+        .load(db)
+        .wrap_err("Failed to query custom list editors")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use chrono::Duration;
+    use image::{ImageFormat, RgbImage};
+    use singit_lib::UserInfo;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::{
+        cover::{self, ThumbnailFormat},
+        route::custom_list::visible_entries,
+        EditorKind, Visibility,
+    };
+
+    async fn open() -> (SqliteStorage, TempDir) {
+        let covers_dir = TempDir::new().unwrap();
+        let covers = CoverDir::open(covers_dir.path()).await.unwrap();
+        let storage = SqliteStorage::open(":memory:", covers).await.unwrap();
+        (storage, covers_dir)
+    }
+
+    fn song(hash: &str) -> NewSong {
+        NewSong {
+            song: Song {
+                song_hash: hash.to_string(),
+                title: format!("Title {hash}"),
+                artist: format!("Artist {hash}"),
+                bpm: "120".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn user(cid: &str, groups: &[&str]) -> User {
+        User {
+            access_token: None,
+            info: UserInfo {
+                cid: cid.to_string(),
+                nick: cid.to_string(),
+            },
+            groups: groups.iter().map(|group| group.to_string()).collect(),
+            logged_in_at: Some(Utc::now()),
+        }
+    }
+
+    fn hashes(songs: Vec<Song>) -> Vec<String> {
+        songs.into_iter().map(|song| song.song_hash).collect()
+    }
+
+    #[actix_web::test]
+    async fn removed_songs_in_lists() {
+        let (storage, _covers) = open().await;
+        let owner = user("owner", &[]);
+
+        let songs = vec![song("a"), song("b"), song("c")];
+        storage
+            .replace_songs(songs, "test".into(), false)
+            .await
+            .unwrap();
+
+        let list = storage
+            .create_custom_list("list".into(), "owner".into())
+            .await
+            .unwrap();
+        let edit = storage
+            .insert_custom_list_entry(list.id, "b".into(), &owner)
+            .await
+            .unwrap();
+        assert!(matches!(edit, ListEdit::Done(())));
+
+        // dry runs don't change anything
+        storage
+            .replace_songs(vec![song("a")], "test".into(), true)
+            .await
+            .unwrap();
+        assert_eq!(hashes(storage.songs().await.unwrap()), ["a", "b", "c"]);
+
+        storage
+            .replace_songs(vec![song("a")], "test".into(), false)
+            .await
+            .unwrap();
+        assert_eq!(hashes(storage.songs().await.unwrap()), ["a"]);
+        assert_eq!(hashes(storage.removed_songs().await.unwrap()), ["b"]);
+        assert_eq!(storage.custom_list_entries(list.id).await.unwrap(), ["b"]);
+
+        // uploading a removed song restores it
+        storage
+            .replace_songs(vec![song("a"), song("b")], "test".into(), false)
+            .await
+            .unwrap();
+        assert_eq!(hashes(storage.songs().await.unwrap()), ["a", "b"]);
+        assert!(storage.removed_songs().await.unwrap().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn only_editors_change_entries() {
+        let (storage, _covers) = open().await;
+        let songs = vec![song("a"), song("b"), song("c")];
+        storage
+            .replace_songs(songs, "test".into(), false)
+            .await
+            .unwrap();
+
+        let list = storage
+            .create_custom_list("list".into(), "owner".into())
+            .await
+            .unwrap();
+        let editors = vec![
+            CustomListEditor {
+                list_id: list.id,
+                kind: EditorKind::User,
+                name: "friend".into(),
+            },
+            CustomListEditor {
+                list_id: list.id,
+                kind: EditorKind::Group,
+                name: "digit".into(),
+            },
+        ];
+        storage
+            .set_custom_list_editors(list.id, editors)
+            .await
+            .unwrap();
+
+        let owner = user("owner", &[]);
+        let friend = user("friend", &[]);
+        let member = user("member", &["digit"]);
+        let stranger = user("stranger", &["prit"]);
+
+        for (editor, hash) in [(&owner, "a"), (&friend, "b"), (&member, "c")] {
+            let edit = storage
+                .insert_custom_list_entry(list.id, hash.into(), editor)
+                .await
+                .unwrap();
+            assert!(matches!(edit, ListEdit::Done(())));
+        }
+
+        let edit = storage
+            .insert_custom_list_entry(list.id, "a".into(), &stranger)
+            .await
+            .unwrap();
+        assert!(matches!(edit, ListEdit::NotAllowed));
+        let edit = storage
+            .move_custom_list_entry(list.id, "a".into(), 2, &stranger)
+            .await
+            .unwrap();
+        assert!(matches!(edit, ListEdit::NotAllowed));
+        let edit = storage
+            .remove_custom_list_entry(list.id, "a".into(), &stranger)
+            .await
+            .unwrap();
+        assert!(matches!(edit, ListEdit::NotAllowed));
+
+        // groups that were fetched too long ago don't count
+        let stale_member = User {
+            logged_in_at: Some(Utc::now() - Duration::hours(2)),
+            ..member.clone()
+        };
+        let edit = storage
+            .remove_custom_list_entry(list.id, "c".into(), &stale_member)
+            .await
+            .unwrap();
+        assert!(matches!(edit, ListEdit::NotAllowed));
+
+        let edit = storage
+            .insert_custom_list_entry(list.id + 1, "a".into(), &owner)
+            .await
+            .unwrap();
+        assert!(matches!(edit, ListEdit::NoSuchList));
+        assert_eq!(
+            storage.custom_list_entries(list.id).await.unwrap(),
+            ["a", "b", "c"]
+        );
+
+        let edit = storage
+            .move_custom_list_entry(list.id, "a".into(), 2, &friend)
+            .await
+            .unwrap();
+        assert!(matches!(edit, ListEdit::Done(true)));
+        let edit = storage
+            .remove_custom_list_entry(list.id, "b".into(), &member)
+            .await
+            .unwrap();
+        assert!(matches!(edit, ListEdit::Done(true)));
+        let edit = storage
+            .remove_custom_list_entry(list.id, "b".into(), &member)
+            .await
+            .unwrap();
+        assert!(matches!(edit, ListEdit::Done(false)));
+        assert_eq!(
+            storage.custom_list_entries(list.id).await.unwrap(),
+            ["c", "a"]
+        );
+    }
+
+    #[actix_web::test]
+    async fn unlisted_lists_need_share_token() {
+        let (storage, _covers) = open().await;
+        storage
+            .replace_songs(vec![song("a")], "test".into(), false)
+            .await
+            .unwrap();
+
+        let list = storage
+            .create_custom_list("list".into(), "owner".into())
+            .await
+            .unwrap();
+        let update = UpdateList {
+            name: None,
+            visibility: Some(Visibility::Unlisted),
+        };
+        let list = storage.update_custom_list(list.id, update).await.unwrap();
+        let other = storage
+            .create_custom_list("other".into(), "owner".into())
+            .await
+            .unwrap();
+        assert_ne!(list.share_token, other.share_token);
+
+        let owner = user("owner", &[]);
+        let stranger = user("stranger", &[]);
+        let token = list.share_token.as_str();
+        for (user, token, visible) in [
+            (Some(&owner), None, true),
+            (Some(&stranger), None, false),
+            (None, Some("wrong"), false),
+            (None, Some(token), true),
+        ] {
+            let entries = visible_entries(&storage, user, list.id, token).await;
+            assert_eq!(entries.unwrap().is_some(), visible);
+        }
+    }
+
+    #[actix_web::test]
+    async fn expired_personal_tokens_are_unusable() {
+        let (storage, _covers) = open().await;
+        let owner = user("owner", &["digit"]).info;
+        let today = Utc::now().naive_utc();
+
+        let token = storage
+            .create_personal_token(
+                owner.clone(),
+                "bot".into(),
+                "new".into(),
+                today + Duration::days(1),
+            )
+            .await
+            .unwrap();
+        storage
+            .create_personal_token(
+                owner,
+                "old bot".into(),
+                "old".into(),
+                today - Duration::days(1),
+            )
+            .await
+            .unwrap();
+
+        let used = storage.use_personal_token("new").await.unwrap().unwrap();
+        assert_eq!(used.id, token.id);
+        assert!(User::from(used).groups.is_empty());
+        assert!(storage.use_personal_token("old").await.unwrap().is_none());
+
+        let tokens = storage.personal_tokens("owner".into()).await.unwrap();
+        assert_eq!(
+            tokens.iter().map(|token| token.id).collect::<Vec<_>>(),
+            [token.id]
+        );
+    }
+
+    #[actix_web::test]
+    async fn cover_files() {
+        let (storage, _covers) = open().await;
+        storage
+            .replace_songs(vec![song("a"), song("b")], "test".into(), false)
+            .await
+            .unwrap();
+
+        let mut png = vec![];
+        RgbImage::from_fn(300, 200, |x, y| [x as u8, y as u8, 0].into())
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        let processed = cover::process(png.clone()).unwrap();
+        let cover_hash = processed.hash.clone();
+        storage.write_cover("a", processed).await.unwrap();
+        let processed = cover::process(png).unwrap();
+        storage.write_cover("b", processed).await.unwrap();
+
+        let original = storage.cover_file("a", CoverVariant::Original).await;
+        assert_eq!(original.unwrap().unwrap().mime_type, "image/png");
+
+        let thumbnail = CoverVariant::Thumbnail {
+            size: 128,
+            format: ThumbnailFormat::Jpeg,
+        };
+        let thumbnail = storage.cover_file("a", thumbnail).await.unwrap().unwrap();
+        assert_eq!(thumbnail.mime_type, "image/jpeg");
+        assert!(thumbnail.path.is_file());
+
+        let manifest = storage.cover_manifest().await.unwrap();
+        assert_eq!(manifest.covers["a"].as_deref(), Some(cover_hash.as_str()));
+
+        // the files are shared, and kept until no song uses them
+        assert!(storage.delete_cover("a").await.unwrap());
+        assert!(!storage.delete_cover("a").await.unwrap());
+        assert!(storage
+            .cover_file("a", CoverVariant::Original)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(thumbnail.path.is_file());
+
+        assert_eq!(storage.delete_covers().await.unwrap(), 1);
+        assert!(!thumbnail.path.exists());
+    }
+}
@@ -0,0 +1,65 @@
+//! Tokens that are sent as `Authorization: Bearer <token>` instead of logging in.
+//!
+//! There are two kinds, told apart by their prefix: API tokens, which let scripts make admin
+//! requests (see [crate::admin]), and personal access tokens, which authenticate as the user that
+//! created them.
+
+use actix_web::{http::header::AUTHORIZATION, HttpRequest};
+use chrono::NaiveDateTime;
+use diesel::{Queryable, Selectable};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Prefix of personal access tokens, to make them easy to recognize.
+pub const PERSONAL_TOKEN_PREFIX: &str = "singit_pat_";
+
+/// A token that lets a user's scripts edit their custom lists and queues.
+///
+/// Tokens don't get the rights that the Gamma groups of the user give, since the groups aren't
+/// known when a token is used.
+#[derive(Serialize, Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = crate::schema::personal_token)]
+pub struct PersonalToken {
+    pub id: i32,
+
+    /// CID of the user that the token authenticates as.
+    pub owner: String,
+
+    #[serde(skip)]
+    pub owner_nick: String,
+
+    /// What the token is for, e.g. `"list bot"`.
+    pub name: String,
+
+    /// In UTC, like the other times.
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+
+    /// The token can't be used after this.
+    pub expires_at: NaiveDateTime,
+}
+
+/// Make a new random token, starting with `prefix`.
+pub fn generate(prefix: &str) -> String {
+    let random: String = rand::thread_rng()
+        .sample_iter(Alphanumeric)
+        .take(40)
+        .map(char::from)
+        .collect();
+
+    format!("{prefix}{random}")
+}
+
+/// Hash a token for storing it. Tokens are random, so there's no need for a slow hash.
+pub fn hash(token: &str) -> String {
+    let hash = Sha256::digest(token.as_bytes());
+    hash.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Get the token from an `Authorization: Bearer <token>` header.
+pub fn bearer(req: &HttpRequest) -> Option<&str> {
+    let header = req.headers().get(AUTHORIZATION)?.to_str().ok()?;
+    header.strip_prefix("Bearer ").map(str::trim)
+}
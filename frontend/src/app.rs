@@ -1,40 +1,77 @@
 use crate::category::Category;
 use crate::css::C;
 use crate::custom_list::{
-    add_song_to_list, fetch_custom_song_list, fetch_custom_song_list_index, remove_song_from_list,
-    CustomLists,
+    add_song_to_list, create_list, delete_list, fetch_custom_song_list,
+    fetch_custom_song_list_index, move_song_in_list, remove_song_from_list, set_list_editors,
+    update_list, CustomList, CustomLists, ListEditor, ListSongs, ListUpdate, Visibility,
 };
+use crate::events::subscribe;
 use crate::fetch::{fetch_list_of, FetchError};
-use crate::fuzzy::FuzzyScore;
-use crate::query::ParsedQuery;
+use crate::lyrics::{fetch_song_lyrics, search_lyrics, LyricsMatch, LyricsMatches, SongLyrics};
+use crate::query::{quote, random_query, ParsedQuery};
+use crate::queue::{
+    create_queue_session, enqueue, fetch_queue, fetch_queue_sessions, move_entry, set_entry_state,
+    Queue, QueueEntry, QueueSession,
+};
 use crate::song::Song;
+use crate::token::{
+    create_personal_token, fetch_personal_tokens, revoke_personal_token, PersonalToken,
+};
 use gloo_console::error;
+use gloo_net::eventsource::futures::EventSource;
 use gloo_net::http::Request;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
 use seed::app::cmds::timeout;
-use seed::browser::util::document;
+use seed::browser::util::{document, history, window};
 use seed::{a, prelude::*};
-use seed::{attrs, button, div, empty, img, input, p, span, C, IF};
+use seed::{attrs, button, div, empty, img, input, option, p, select, span, Attrs, C, IF};
 use serde::Deserialize;
+use singit_lib::fuzzy::FuzzyScore;
+use singit_lib::index::SearchIndex;
+use singit_lib::search::{self, Highlights};
 use std::cmp::Reverse;
 use std::collections::hash_map::DefaultHasher;
-use std::collections::{BTreeSet, HashSet};
+use std::collections::BTreeSet;
 use std::hash::{Hash, Hasher};
 use web_sys::Element;
 
 pub struct Model {
     songs: Vec<(Reverse<FuzzyScore>, Song)>,
 
+    /// Index of the songs, by their `search_id`. Rebuilt when the songs are fetched.
+    search_index: SearchIndex,
+
     /// If logged in, this contains info about the user.
     user_info: Loading<Option<UserInfo>>,
 
+    /// The code of the error that the backend redirected with, if logging in failed.
+    login_error: Option<String>,
+
     /// Custom song lists, lazily loaded.
     custom_lists: CustomLists,
 
+    /// The ID of the custom list that songs are added to, if the user has one.
+    active_list: Option<i32>,
+
+    /// The ID and share token of the custom list that the page was opened with a link to, if any.
+    shared_list: Option<(i32, String)>,
+
+    /// Hash of the song that the user is currently dragging to another place in a custom list.
+    dragged_song: Option<String>,
+
+    /// Results of lyrics searches, lazily loaded.
+    lyrics_matches: LyricsMatches,
+
+    /// Lyrics of the songs whose pages have been shown, lazily loaded.
+    song_lyrics: SongLyrics,
+
     /// The search string.
     query: String,
 
+    /// Waits for the user to stop typing before searching. Cancelled when dropped.
+    search_debounce: Option<CmdHandle>,
+
     /// The number of songs currently in the dom. Goes up when the user scrolls down.
     shown_songs: usize,
 
@@ -50,6 +87,33 @@ pub struct Model {
     /// Which screen is currently being shown
     screen: View,
 
+    /// Karaoke queue sessions, loaded when the queue is first shown.
+    queue_sessions: Loading<Vec<QueueSession>>,
+
+    /// The ID of the queue that songs are added to, if any.
+    selected_queue: Option<i32>,
+
+    /// The selected queue, once loaded.
+    queue: Option<Queue>,
+
+    /// Whether the queue panel is shown.
+    show_queue: bool,
+
+    /// Whether the queue panel is collapsed to only show its head.
+    queue_collapsed: bool,
+
+    /// The name to queue songs under. Defaults to the nick of the user.
+    queue_singer: String,
+
+    /// The name of a new queue that the user is about to create.
+    new_queue_name: String,
+
+    /// The personal access tokens of the user, loaded when the token panel is first shown.
+    personal_tokens: Loading<Vec<PersonalToken>>,
+
+    /// Whether the panel for managing personal access tokens is shown.
+    show_tokens: bool,
+
     query_placeholder: String,
     query_placeholder_len: usize,
 
@@ -57,6 +121,12 @@ pub struct Model {
 
     /// URLs of some defaults for songs with missing cover art.
     default_song_covers: Vec<&'static str>,
+
+    /// Stream of changes pushed by the server. Closed when dropped.
+    _events: Option<EventSource>,
+
+    /// Whether the event stream has been opened before, i.e. if the next connect is a reconnect.
+    events_connected: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -73,6 +143,9 @@ enum View {
 
     /// The list of song categories.
     Categories,
+
+    /// The page of a song, by its hash. Shown on `/song/<hash>`.
+    Song(String),
 }
 
 #[derive(Default)]
@@ -91,34 +164,94 @@ pub enum Loading<T> {
 const SCROLL_THRESHOLD: usize = 50;
 const INITIAL_ELEM_COUNT: usize = 100;
 
+/// How long to wait for the user to stop typing before searching, in milliseconds.
+const SEARCH_DEBOUNCE: u32 = 150;
+
 pub enum Msg {
     /// Fetched songs.
     Songs(Vec<Song>),
 
+    /// The event stream was opened, or reopened after losing the connection.
+    EventsConnected,
+
+    /// The server says that the song list was replaced.
+    SongsReplaced,
+
+    /// The server says that a custom list was created, renamed or deleted.
+    CustomListsChanged,
+
+    /// The server says that the songs in a custom list were changed.
+    CustomListChanged(i32),
+
+    /// The server says that a queue was changed.
+    QueueChanged(i32),
+
+    /// The server says that we've missed some changes.
+    Resync,
+
     /// Fetched user info.
     UserInfo(Option<UserInfo>),
 
+    /// The user closed the banner about logging in failing
+    DismissLoginError,
+
     /// Fetched custom song index.
-    CustomSongLists(Vec<String>),
+    CustomSongLists(Vec<CustomList>),
 
     /// Fetched custom song list.
-    CustomSongList {
-        list: String,
-        song_hashes: HashSet<String>,
+    CustomSongList { list: i32, song_hashes: Vec<String> },
+
+    /// Created a new custom list.
+    CustomListCreated(CustomList),
+
+    /// Fetched the results of a lyrics search.
+    LyricsMatches {
+        query: String,
+        matches: Vec<LyricsMatch>,
     },
 
-    /// The user entered something into the search field
+    /// Search for something right away, e.g. when the user picked a category
     Search(String),
 
-    /// The user wants to show their custom list
-    ShowUserList,
+    /// The user typed into the search field
+    SearchInput(String),
+
+    /// The user stopped typing into the search field
+    SearchInputSettled,
+
+    /// The user picked one of their custom lists
+    ShowList(i32),
+
+    /// The user wants to create a new custom list
+    NewList,
+
+    /// The user wants to rename one of their custom lists
+    RenameList(i32),
+
+    /// The user wants to change who can see one of their custom lists
+    SetListVisibility(i32, Visibility),
+
+    /// The user wants to delete one of their custom lists
+    DeleteList(i32),
+
+    /// The user wants to change who may edit one of their custom lists
+    ShareList(i32),
 
-    /// The user wants to add a song hash to their list
+    /// The user wants a link to one of their custom lists
+    ShowListLink(i32),
+
+    /// The user wants to add a song hash to their active list
     AddToList(String),
 
-    /// The user wants to remove a song hash from their list
+    /// The user wants to remove a song hash from their active list
     RemoveFromList(String),
 
+    /// The user started dragging a song in the shown custom list
+    DragListSong(String),
+
+    /// The user dropped the dragged song onto another song in the shown custom list
+    DropListSong(String),
+
     /// The user pressed the Toggle Video button
     ToggleVideo,
 
@@ -131,6 +264,78 @@ pub enum Msg {
     /// The user pressed the Shuffle button
     Shuffle,
 
+    /// Fetched the list of queue sessions.
+    QueueSessions(Vec<QueueSession>),
+
+    /// Fetched a queue.
+    Queue(Queue),
+
+    /// The user pressed the Queue button
+    ToggleQueue,
+
+    /// The user pressed the head of the queue panel
+    CollapseQueue,
+
+    /// The user picked a queue, or went back to the list of queues
+    SelectQueue(Option<i32>),
+
+    /// The user entered something into the singer name field
+    QueueSinger(String),
+
+    /// The user entered something into the new queue name field
+    NewQueueName(String),
+
+    /// The user wants to create a new queue
+    CreateQueue,
+
+    /// Created a new queue.
+    QueueCreated(QueueSession),
+
+    /// The user wants to add a song hash to the selected queue
+    Enqueue(String),
+
+    /// The queue owner wants to move an entry to another index among the queued entries
+    MoveQueueEntry { entry: i32, index: usize },
+
+    /// The queue owner wants to mark an entry as sung
+    QueueEntryDone(i32),
+
+    /// The queue owner, or the singer, wants to skip an entry
+    SkipQueueEntry(i32),
+
+    /// The user pressed the Tokens button
+    ToggleTokens,
+
+    /// Fetched the personal access tokens of the user.
+    PersonalTokens(Vec<PersonalToken>),
+
+    /// The user wants to create a new personal access token
+    NewToken,
+
+    /// Created a personal access token. It's only sent this once, so it must be shown now.
+    PersonalTokenCreated(String),
+
+    /// The user wants to revoke one of their personal access tokens
+    RevokeToken(i32),
+
+    /// The user opened the page of a song
+    ShowSong(String),
+
+    /// The user went back to the song list from the page of a song
+    ShowSongs,
+
+    /// The user went back or forward in the browser history
+    UrlChanged,
+
+    /// Fetched the lyrics of a song, or failed to.
+    SongLyrics {
+        song_hash: String,
+        lyrics: Result<Option<String>, String>,
+    },
+
+    /// The user wants to add a song hash to one of their lists, or remove it if it's already there
+    ToggleListSong { list: i32, song_hash: String },
+
     /// The user scrolled the song list
     Scroll,
 
@@ -138,21 +343,58 @@ pub enum Msg {
     Autotyper,
 }
 
-pub fn init(_url: Url, orders: &mut impl Orders<Msg>) -> Model {
+pub fn init(url: Url, orders: &mut impl Orders<Msg>) -> Model {
     orders.perform_cmd(fetch_songs());
     orders.perform_cmd(fetch_user_info());
     orders.perform_cmd(fetch_custom_song_list_index());
+    orders.stream(streams::window_event(Ev::PopState, |_| Msg::UrlChanged));
 
     // get list of default song covers. see build.rs
     const DEFAULT_SONG_COVERS: &str = env!("DEFAULT_SONG_COVERS");
     let default_song_covers = DEFAULT_SONG_COVERS.split(',').collect();
 
-    Model {
-        screen: Default::default(),
+    let login_error = url
+        .search()
+        .get("login_error")
+        .and_then(|codes| codes.first());
+    if login_error.is_some() {
+        // so that the error isn't shown again on reload
+        let _ = history().replace_state_with_url(&JsValue::NULL, "", Some("/"));
+    }
+
+    // links to custom lists, see CustomList::link
+    let search = url.search();
+    let shown_list = search
+        .get("list")
+        .and_then(|lists| lists.first()?.parse().ok());
+    let share_token = search.get("token").and_then(|tokens| tokens.first());
+    let shared_list = shown_list.zip(share_token.cloned());
+
+    let mut model = Model {
+        screen: View::from_url(&url),
+        queue_sessions: Loading::NotLoaded,
+        selected_queue: None,
+        queue: None,
+        show_queue: false,
+        queue_collapsed: false,
+        queue_singer: String::new(),
+        new_queue_name: String::new(),
+        personal_tokens: Loading::NotLoaded,
+        show_tokens: false,
         songs: vec![],
+        search_index: Default::default(),
         user_info: Loading::InProgress,
+        login_error: login_error.cloned(),
         custom_lists: Default::default(),
-        query: String::new(),
+        active_list: None,
+        shared_list,
+        dragged_song: None,
+        lyrics_matches: Default::default(),
+        song_lyrics: Default::default(),
+        query: shown_list
+            .map(|list: i32| format!("list:{list}"))
+            .unwrap_or_default(),
+        search_debounce: None,
         hidden_songs: 0,
         shown_songs: INITIAL_ELEM_COUNT,
         filter_video: false,
@@ -161,7 +403,13 @@ pub fn init(_url: Url, orders: &mut impl Orders<Msg>) -> Model {
         query_placeholder_len: 0,
         autotyper: Some(orders.perform_cmd_with_handle(timeout(500, || Msg::Autotyper))),
         default_song_covers,
-    }
+        _events: subscribe(orders),
+        events_connected: false,
+    };
+
+    load_song_page(&mut model, orders);
+
+    model
 }
 
 fn update_song_list(model: &mut Model, orders: &mut impl Orders<Msg>) {
@@ -174,16 +422,27 @@ fn update_song_list(model: &mut Model, orders: &mut impl Orders<Msg>) {
     model.filter_duets = query.duet == Some(true);
     model.filter_video = query.video == Some(true);
 
-    if let Some(name) = query.list {
-        if let Some(l @ Loading::NotLoaded) = model.custom_lists.get_mut(name) {
-            orders.perform_cmd(fetch_custom_song_list(name.to_string()));
+    if let Some(list) = query.list.as_deref().and_then(|list| list.parse().ok()) {
+        load_custom_list(&mut model.custom_lists, orders, list);
+    }
+
+    if let Some(lyrics) = &query.lyrics {
+        if let l @ Loading::NotLoaded = model.lyrics_matches.entry(lyrics.to_string()).or_default()
+        {
+            orders.perform_cmd(search_lyrics(lyrics.to_string()));
             *l = Loading::InProgress;
         }
     }
 
     // calculate search scores & sort list
+    let scores = model.search_index.scores(&query);
     for (score, song) in model.songs.iter_mut() {
-        let new_score = song.fuzzy_compare(&query, &model.custom_lists);
+        let new_score = song.fuzzy_compare(
+            scores[song.search_id],
+            &query,
+            &model.custom_lists,
+            &model.lyrics_matches,
+        );
         if new_score < Default::default() {
             model.hidden_songs += 1;
         }
@@ -193,6 +452,11 @@ fn update_song_list(model: &mut Model, orders: &mut impl Orders<Msg>) {
 
     if query.has_fuzzy_parameters() {
         model.songs.sort_unstable();
+    } else if let Some(list) = shown_list(&model.custom_lists, &model.query) {
+        // show the songs of a list in the order that its editors arranged them
+        if list.songs.get().is_some() {
+            sort_by_list_order(model);
+        }
     } else {
         // if the user didn't input any fuzzy parameters, shuffle the results. this is stylistic
         // choice. i don't want the same results to show up at the top over and over when the user
@@ -204,62 +468,397 @@ fn update_song_list(model: &mut Model, orders: &mut impl Orders<Msg>) {
     }
 }
 
+/// Get the custom list that the query filters by, if it's known.
+fn shown_list<'a>(custom_lists: &'a CustomLists, query: &str) -> Option<&'a CustomList> {
+    let list: i32 = ParsedQuery::parse(query).list?.parse().ok()?;
+    custom_lists.get(&list)
+}
+
+/// Sort the songs of the shown custom list in the order that its editors arranged them.
+fn sort_by_list_order(model: &mut Model) {
+    let Some(list) = shown_list(&model.custom_lists, &model.query) else {
+        return;
+    };
+    let Some(list) = list.songs.get() else {
+        return;
+    };
+
+    (model.songs).sort_by_cached_key(|(score, song)| (*score, list.position(&song.song_hash)));
+}
+
+/// Start loading the songs of a custom list, unless they're already loaded.
+fn load_custom_list(custom_lists: &mut CustomLists, orders: &mut impl Orders<Msg>, list: i32) {
+    if let Some(list) = custom_lists.get_mut(&list) {
+        if let l @ Loading::NotLoaded = &mut list.songs {
+            orders.perform_cmd(fetch_custom_song_list(list.id, list.share_token.clone()));
+            *l = Loading::InProgress;
+        }
+    }
+}
+
+/// Make sure that the active list is one that the user may edit, if there are any.
+fn select_active_list(model: &mut Model, orders: &mut impl Orders<Msg>) {
+    let Some(user) = model.user_info.get_option() else {
+        model.active_list = None;
+        return;
+    };
+
+    let is_editable = |list: &i32| {
+        model
+            .custom_lists
+            .get(list)
+            .is_some_and(|list| list.editable)
+    };
+
+    if !model.active_list.as_ref().is_some_and(is_editable) {
+        // prefer the user's own lists over the ones that are shared with them
+        model.active_list = model
+            .custom_lists
+            .values()
+            .filter(|list| list.editable)
+            .min_by_key(|list| (list.owner != user.cid, list.id))
+            .map(|list| list.id);
+    }
+
+    if let Some(list) = model.active_list {
+        load_custom_list(&mut model.custom_lists, orders, list);
+    }
+}
+
+impl View {
+    /// The screen that a url points to.
+    fn from_url(url: &Url) -> Self {
+        match url.path() {
+            [page, song_hash] if page == "song" => View::Song(song_hash.clone()),
+            _ => View::Songs,
+        }
+    }
+
+    /// The path that points to the screen.
+    fn path(&self) -> String {
+        match self {
+            View::Songs | View::Categories => String::from("/"),
+            View::Song(song_hash) => song_path(song_hash),
+        }
+    }
+}
+
+fn song_path(song_hash: &str) -> String {
+    format!("/song/{song_hash}")
+}
+
+/// Show a screen, and point the address bar to it so that it can be linked to.
+fn navigate(model: &mut Model, screen: View) {
+    let path = screen.path();
+    if path != model.screen.path() {
+        let _ = history().push_state_with_url(&JsValue::NULL, "", Some(&path));
+    }
+    model.screen = screen;
+}
+
+/// Go back to the song list if the page of a song is shown, e.g. when the user searches.
+fn leave_song_page(model: &mut Model) {
+    if let View::Song(_) = model.screen {
+        navigate(model, View::Songs);
+    }
+}
+
+/// Start loading what the page of a song shows, if it's the shown screen.
+fn load_song_page(model: &mut Model, orders: &mut impl Orders<Msg>) {
+    let View::Song(song_hash) = &model.screen else {
+        return;
+    };
+
+    let lyrics = model.song_lyrics.entry(song_hash.clone()).or_default();
+    if matches!(lyrics, Loading::NotLoaded | Loading::Loaded(Err(_))) {
+        orders.perform_cmd(fetch_song_lyrics(song_hash.clone()));
+        *lyrics = Loading::InProgress;
+    }
+
+    // the page shows which of the user's lists contain the song
+    let lists: Vec<i32> = (model.custom_lists.values())
+        .filter(|list| list.editable)
+        .map(|list| list.id)
+        .collect();
+    for list in lists {
+        load_custom_list(&mut model.custom_lists, orders, list);
+    }
+}
+
 pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
     match msg {
-        Msg::Songs(songs) => {
+        Msg::Songs(mut songs) => {
+            for (search_id, song) in songs.iter_mut().enumerate() {
+                song.search_id = search_id;
+            }
+            model.search_index = SearchIndex::new(&songs);
+
+            // removed songs are last in the list, hide them until they're searched for
+            model.hidden_songs = songs.iter().filter(|song| song.removed).count();
             model.songs = songs
                 .into_iter()
                 .map(|song| (Default::default(), song))
                 .collect();
+
+            // the list was replaced while the user was searching, search the new one instead
+            if !model.query.is_empty() {
+                update_song_list(model, orders);
+            }
+        }
+        Msg::EventsConnected => {
+            // we might have missed some changes while we were disconnected
+            if model.events_connected {
+                update(Msg::Resync, model, orders);
+            }
+            model.events_connected = true;
+        }
+        Msg::SongsReplaced => {
+            orders.perform_cmd(fetch_songs());
+        }
+        Msg::CustomListsChanged => {
+            orders.perform_cmd(fetch_custom_song_list_index());
+        }
+        Msg::CustomListChanged(list) => match model.custom_lists.get(&list) {
+            Some(CustomList {
+                songs: Loading::NotLoaded,
+                ..
+            }) => {}
+            Some(list) => {
+                orders.perform_cmd(fetch_custom_song_list(list.id, list.share_token.clone()));
+            }
+            None => {
+                orders.perform_cmd(fetch_custom_song_list_index());
+            }
+        },
+        Msg::QueueChanged(session) => {
+            if model.selected_queue == Some(session) {
+                orders.perform_cmd(fetch_queue(session));
+            }
+
+            if let Loading::Loaded(sessions) = &model.queue_sessions {
+                if !sessions.iter().any(|s| s.id == session) {
+                    orders.perform_cmd(fetch_queue_sessions());
+                }
+            }
+        }
+        Msg::Resync => {
+            orders.perform_cmd(fetch_songs());
+            orders.perform_cmd(fetch_custom_song_list_index());
+
+            for list in model.custom_lists.values() {
+                if !matches!(list.songs, Loading::NotLoaded) {
+                    let token = list.share_token.clone();
+                    orders.perform_cmd(fetch_custom_song_list(list.id, token));
+                }
+            }
+
+            if !matches!(model.queue_sessions, Loading::NotLoaded) {
+                orders.perform_cmd(fetch_queue_sessions());
+            }
+
+            if let Some(session) = model.selected_queue {
+                orders.perform_cmd(fetch_queue(session));
+            }
         }
         Msg::UserInfo(user_info) => {
             if let Some(user_info) = &user_info {
-                if let l @ Loading::NotLoaded =
-                    model.custom_lists.entry(user_info.cid.clone()).or_default()
-                {
-                    orders.perform_cmd(fetch_custom_song_list(user_info.cid.clone()));
-                    *l = Loading::InProgress;
+                if model.queue_singer.is_empty() {
+                    model.queue_singer = user_info.nick.clone();
                 }
             }
 
             model.user_info = Loading::Loaded(user_info);
+            select_active_list(model, orders);
+        }
+        Msg::DismissLoginError => {
+            model.login_error = None;
         }
         Msg::CustomSongLists(lists) => {
+            // keep the songs of the lists that we've already loaded
+            let mut old_lists = std::mem::take(&mut model.custom_lists);
             model.custom_lists = lists
                 .into_iter()
-                .map(|list| (list, Loading::NotLoaded))
+                .map(|mut list| {
+                    if let Some(old_list) = old_lists.remove(&list.id) {
+                        list.songs = old_list.songs;
+                    }
+                    (list.id, list)
+                })
                 .collect();
+
+            // unlisted lists that the page was opened with a link to aren't in the index
+            if let Some((id, token)) = &model.shared_list {
+                let list = (old_lists.remove(id))
+                    .unwrap_or_else(|| CustomList::shared(*id, token.clone()));
+                model.custom_lists.entry(*id).or_insert(list);
+            }
+
+            select_active_list(model, orders);
+            load_song_page(model, orders);
+
+            // the user might have searched for a list before we knew about it
+            if ParsedQuery::parse(&model.query).list.is_some() {
+                update_song_list(model, orders);
+            }
         }
         Msg::CustomSongList { list, song_hashes } => {
             let query = ParsedQuery::parse(&model.query);
-            let update_list = query.list == Some(&list);
+            let update_list = query.list.as_deref() == Some(list.to_string().as_str());
 
-            *model.custom_lists.entry(list).or_default() = Loading::Loaded(song_hashes);
+            if let Some(list) = model.custom_lists.get_mut(&list) {
+                list.songs = Loading::Loaded(ListSongs::new(song_hashes));
+            }
+
+            if update_list {
+                update_song_list(model, orders);
+            }
+        }
+        Msg::CustomListCreated(list) => {
+            let id = list.id;
+            model.custom_lists.insert(id, list);
+            model.active_list = Some(id);
+            load_custom_list(&mut model.custom_lists, orders, id);
+        }
+        Msg::LyricsMatches { query, matches } => {
+            let parsed_query = ParsedQuery::parse(&model.query);
+            let update_list = parsed_query.lyrics.as_deref() == Some(query.as_str());
+
+            let matches = matches
+                .into_iter()
+                .map(|m| (m.song_hash.clone(), m))
+                .collect();
+            *model.lyrics_matches.entry(query).or_default() = Loading::Loaded(matches);
 
             if update_list {
                 update_song_list(model, orders);
             }
         }
         Msg::Search(query) => {
+            leave_song_page(model);
             model.query = query;
+            model.search_debounce = None;
             update_song_list(model, orders);
         }
-        Msg::ShowUserList => {
-            if let Some(user) = model.user_info.get_option() {
-                model.query = format!("list:{}", user.cid);
-                update_song_list(model, orders);
+        Msg::SearchInput(query) => {
+            leave_song_page(model);
+            model.query = query;
+            model.search_debounce = Some(
+                orders
+                    .perform_cmd_with_handle(timeout(SEARCH_DEBOUNCE, || Msg::SearchInputSettled)),
+            );
+        }
+        Msg::SearchInputSettled => {
+            model.search_debounce = None;
+            update_song_list(model, orders);
+        }
+        Msg::ShowList(list) => {
+            leave_song_page(model);
+            model.active_list = Some(list);
+            model.query = format!("list:{list}");
+            update_song_list(model, orders);
+        }
+        Msg::NewList => {
+            if let Some(name) = prompt("Vad ska listan heta?", "") {
+                orders.perform_cmd(create_list(name, None));
+            }
+        }
+        Msg::RenameList(list) => {
+            let Some(old_name) = model.custom_lists.get(&list).map(|list| &list.name) else {
+                return;
             };
+
+            if let Some(name) = prompt("Vad ska listan heta?", old_name) {
+                let update = ListUpdate {
+                    name: Some(name),
+                    ..Default::default()
+                };
+                orders.perform_cmd(update_list(list, update));
+            }
+        }
+        Msg::SetListVisibility(list, visibility) => {
+            let update = ListUpdate {
+                visibility: Some(visibility),
+                ..Default::default()
+            };
+            orders.perform_cmd(update_list(list, update));
+        }
+        Msg::DeleteList(list) => {
+            let Some(name) = model.custom_lists.get(&list).map(|list| &list.name) else {
+                return;
+            };
+
+            let confirmed = window()
+                .confirm_with_message(&format!("Vill du ta bort {name}?"))
+                .unwrap_or(false);
+
+            if confirmed {
+                orders.perform_cmd(delete_list(list));
+            }
+        }
+        Msg::ShareList(list) => {
+            let Some(editors) = model.custom_lists.get(&list).map(|list| &list.editors) else {
+                return;
+            };
+
+            let editors = window().prompt_with_message_and_default(
+                "Vilka får redigera listan? Skriv CID:n och @grupper, separerade med kommatecken.",
+                &ListEditor::format_list(editors),
+            );
+
+            // an empty answer removes all editors, so only bail if the user cancelled
+            if let Ok(Some(editors)) = editors {
+                let editors = ListEditor::parse_list(&editors);
+                orders.perform_cmd(set_list_editors(list, editors));
+            }
+        }
+        Msg::ShowListLink(list) => {
+            let Some(link) = model.custom_lists.get(&list).and_then(CustomList::link) else {
+                return;
+            };
+
+            // a prompt rather than an alert, so that the link can be copied
+            let _ = window().prompt_with_message_and_default("Länk till listan:", &link);
         }
         Msg::AddToList(song_hash) => {
-            if let Some(user) = model.user_info.get_option() {
-                orders.perform_cmd(add_song_to_list(user.cid.clone(), song_hash));
+            if let Some(list) = model.active_list {
+                orders.perform_cmd(add_song_to_list(list, song_hash));
+            } else if model.user_info.get_option().is_some() {
+                // the user doesn't have a list yet, so make one for them
+                orders.perform_cmd(create_list("Min lista".to_string(), Some(song_hash)));
             }
         }
         Msg::RemoveFromList(song_hash) => {
-            if let Some(user) = model.user_info.get_option() {
-                orders.perform_cmd(remove_song_from_list(user.cid.clone(), song_hash));
+            if let Some(list) = model.active_list {
+                orders.perform_cmd(remove_song_from_list(list, song_hash));
             }
         }
+        Msg::DragListSong(song_hash) => {
+            model.dragged_song = Some(song_hash);
+        }
+        Msg::DropListSong(target) => {
+            let Some(song_hash) = model.dragged_song.take() else {
+                return;
+            };
+            let Some(list) = shown_list(&model.custom_lists, &model.query)
+                .filter(|list| list.editable)
+                .map(|list| list.id)
+            else {
+                return;
+            };
+            let Some(Loading::Loaded(songs)) =
+                model.custom_lists.get_mut(&list).map(|l| &mut l.songs)
+            else {
+                return;
+            };
+            let Some(index) = songs.position(&target) else {
+                return;
+            };
+
+            // move the song locally right away, the server will tell us if anything went wrong
+            songs.move_song(&song_hash, index);
+            sort_by_list_order(model);
+            orders.perform_cmd(move_song_in_list(list, song_hash, index));
+        }
         Msg::ToggleVideo => {
             let mut query = ParsedQuery::parse(&model.query);
             query.video = match query.video {
@@ -278,12 +877,14 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
         }
         Msg::ToggleCategories => {
             if model.screen == View::Categories {
-                model.screen = View::Songs;
+                navigate(model, View::Songs);
             } else {
-                model.screen = View::Categories;
+                navigate(model, View::Categories);
             }
         }
         Msg::Shuffle => {
+            leave_song_page(model);
+
             // clear fuzzy query parameters and call update_song_list, which will shuffle the list.
             let mut query = ParsedQuery::parse(&model.query);
             query.clear_fuzzy_parameters();
@@ -291,6 +892,148 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
 
             update_song_list(model, orders);
         }
+        Msg::QueueSessions(sessions) => {
+            model.queue_sessions = Loading::Loaded(sessions);
+        }
+        Msg::Queue(queue) => {
+            if model.selected_queue == Some(queue.session.id) {
+                model.queue = Some(queue);
+            }
+        }
+        Msg::ToggleQueue => {
+            model.show_queue = !model.show_queue;
+            model.queue_collapsed = false;
+
+            if model.show_queue {
+                if let l @ Loading::NotLoaded = &mut model.queue_sessions {
+                    orders.perform_cmd(fetch_queue_sessions());
+                    *l = Loading::InProgress;
+                }
+            }
+        }
+        Msg::CollapseQueue => {
+            model.queue_collapsed = !model.queue_collapsed;
+        }
+        Msg::SelectQueue(session) => {
+            model.selected_queue = session;
+            model.queue = None;
+
+            match session {
+                Some(session) => orders.perform_cmd(fetch_queue(session)),
+                None => orders.perform_cmd(fetch_queue_sessions()),
+            };
+        }
+        Msg::QueueSinger(singer) => {
+            model.queue_singer = singer;
+        }
+        Msg::NewQueueName(name) => {
+            model.new_queue_name = name;
+        }
+        Msg::CreateQueue => {
+            let name = model.new_queue_name.trim().to_string();
+            if !name.is_empty() {
+                model.new_queue_name.clear();
+                orders.perform_cmd(create_queue_session(name));
+            }
+        }
+        Msg::QueueCreated(session) => {
+            model.selected_queue = Some(session.id);
+            model.queue = None;
+            orders.perform_cmd(fetch_queue_sessions());
+            orders.perform_cmd(fetch_queue(session.id));
+        }
+        Msg::Enqueue(song_hash) => {
+            if let Some(session) = model.selected_queue {
+                let singer = model.queue_singer.trim();
+                let singer = (!singer.is_empty()).then(|| singer.to_string());
+                orders.perform_cmd(enqueue(session, song_hash, singer));
+            }
+        }
+        Msg::MoveQueueEntry { entry, index } => {
+            if let Some(session) = model.selected_queue {
+                orders.perform_cmd(move_entry(session, entry, index));
+            }
+        }
+        Msg::QueueEntryDone(entry) => {
+            if let Some(session) = model.selected_queue {
+                orders.perform_cmd(set_entry_state(session, entry, "done"));
+            }
+        }
+        Msg::SkipQueueEntry(entry) => {
+            if let Some(session) = model.selected_queue {
+                orders.perform_cmd(set_entry_state(session, entry, "skip"));
+            }
+        }
+        Msg::ToggleTokens => {
+            model.show_tokens = !model.show_tokens;
+
+            if model.show_tokens {
+                if let l @ Loading::NotLoaded = &mut model.personal_tokens {
+                    orders.perform_cmd(fetch_personal_tokens());
+                    *l = Loading::InProgress;
+                }
+            }
+        }
+        Msg::PersonalTokens(tokens) => {
+            model.personal_tokens = Loading::Loaded(tokens);
+        }
+        Msg::NewToken => {
+            if let Some(name) = prompt("Vad ska nyckeln användas till?", "") {
+                orders.perform_cmd(create_personal_token(name));
+            }
+        }
+        Msg::PersonalTokenCreated(token) => {
+            // a prompt rather than an alert, so that the token can be copied
+            let _ = window().prompt_with_message_and_default(
+                "Kopiera nyckeln nu, den visas inte igen. Skicka den som \"Authorization: Bearer <nyckel>\".",
+                &token,
+            );
+            orders.perform_cmd(fetch_personal_tokens());
+        }
+        Msg::RevokeToken(id) => {
+            let Some(tokens) = model.personal_tokens.get() else {
+                return;
+            };
+            let Some(token) = tokens.iter().find(|token| token.id == id) else {
+                return;
+            };
+
+            let confirmed = window()
+                .confirm_with_message(&format!(
+                    "Vill du ta bort nyckeln {}? Skript som använder den slutar fungera.",
+                    token.name
+                ))
+                .unwrap_or(false);
+
+            if confirmed {
+                orders.perform_cmd(revoke_personal_token(id));
+            }
+        }
+        Msg::ShowSong(song_hash) => {
+            navigate(model, View::Song(song_hash));
+            load_song_page(model, orders);
+        }
+        Msg::ShowSongs => {
+            navigate(model, View::Songs);
+        }
+        Msg::UrlChanged => {
+            model.screen = View::from_url(&Url::current());
+            load_song_page(model, orders);
+        }
+        Msg::SongLyrics { song_hash, lyrics } => {
+            model.song_lyrics.insert(song_hash, Loading::Loaded(lyrics));
+        }
+        Msg::ToggleListSong { list, song_hash } => {
+            let Some(songs) = model.custom_lists.get(&list).and_then(|l| l.songs.get()) else {
+                return;
+            };
+
+            if songs.contains(&song_hash) {
+                orders.perform_cmd(remove_song_from_list(list, song_hash));
+            } else {
+                orders.perform_cmd(add_song_to_list(list, song_hash));
+            }
+        }
         Msg::Scroll => {
             let Some((scroll, max_scroll)) = get_scroll() else {
                 error!("Failed to get song list element by id:", SONG_LIST_ID);
@@ -326,10 +1069,10 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
 
 pub fn view_categories(model: &Model) -> Node<Msg> {
     let category_card = |category: &Category| -> Node<Msg> {
-        let title = category.title.replace(' ', "");
+        let genre = quote(&category.title, false, true).into_owned();
         div![
             C![C.category_item],
-            ev(Ev::Click, move |_| Msg::Search(format!("genre:{title}"))),
+            ev(Ev::Click, move |_| Msg::Search(format!("genre:{genre}"))),
             ev(Ev::Click, |_| Msg::ToggleCategories),
             div![
                 C![C.category_item_info],
@@ -360,66 +1103,120 @@ pub fn view_categories(model: &Model) -> Node<Msg> {
 
 pub fn view_songs(model: &Model) -> Node<Msg> {
     let user = model.user_info.get_option();
-    let empty_list = HashSet::new();
-    let user_list = user
-        .and_then(|user| model.custom_lists.get(&user.cid)?.get())
-        .unwrap_or(&empty_list);
+    let active_list = model
+        .active_list
+        .and_then(|list| model.custom_lists.get(&list));
+    let user_list = active_list.and_then(|list| list.songs.get());
+    let active_list_name = active_list.map_or("min lista", |list| &list.name);
+
+    let query = ParsedQuery::parse(&model.query);
+    let lyrics_matches = query
+        .lyrics
+        .as_deref()
+        .and_then(|lyrics| model.lyrics_matches.get(lyrics)?.get());
+
+    // highlight the chars of titles and artists that matched the search
+    let highlight = query.plain.is_some() || !query.title.is_empty() || !query.artist.is_empty();
+
+    // songs may be dragged around when showing a list in its stored order
+    let reorderable = !query.has_fuzzy_parameters()
+        && shown_list(&model.custom_lists, &model.query)
+            .is_some_and(|list| list.editable && list.songs.get().is_some());
 
     let song_card = |song: &Song| -> Node<Msg> {
+        let highlights = if highlight {
+            search::highlights(song, &query)
+        } else {
+            Highlights::default()
+        };
+
         div![
             C![C.song_item],
-            img![
-                C![C.song_item_cover],
-                match song.cover {
-                    Some(_) => attrs! {At::Src => format!("/images/songs/{}", song.song_hash)},
-                    None => {
-                        // use a DefaultHasher to turn the song_hash string into a number we can
-                        // use to give the song a psuedo-random default cover.
-                        let mut hasher = DefaultHasher::new();
-                        song.song_hash.hash(&mut hasher);
-                        let hash = hasher.finish() as usize;
-                        let cover_i = hash % model.default_song_covers.len();
-                        let cover = model.default_song_covers[cover_i];
-                        attrs! { At::Src => cover }
-                    }
-                },
-            ],
+            IF![song.removed => C![C.song_item_removed]],
+            IF![reorderable => C![C.song_item_draggable]],
+            IF![reorderable => attrs! {At::Draggable => true}],
+            IF![reorderable => {
+                let song_hash = song.song_hash.clone();
+                ev(Ev::DragStart, |_| Msg::DragListSong(song_hash))
+            }],
+            IF![reorderable => ev(Ev::DragOver, |event| event.prevent_default())],
+            IF![reorderable => {
+                let song_hash = song.song_hash.clone();
+                ev(Ev::Drop, |event| {
+                    event.prevent_default();
+                    Msg::DropListSong(song_hash)
+                })
+            }],
+            img![C![C.song_item_cover], cover_attrs(model, song, 128), {
+                let song_hash = song.song_hash.clone();
+                ev(Ev::Click, |_| Msg::ShowSong(song_hash))
+            },],
             div![
                 C![C.song_item_info],
-                div![C![C.song_item_title], &song.title],
+                div![
+                    C![C.song_item_title],
+                    song_link(
+                        &song.song_hash,
+                        view_highlighted(&song.title, &highlights.title)
+                    ),
+                ],
                 div![
                     C![C.song_item_artist],
-                    span![&song.artist],
+                    span![view_highlighted(&song.artist, &highlights.artist)],
                     if let Some(year) = song.year.as_ref() {
                         span![" (", year, ")"]
                     } else {
                         empty![]
                     }
                 ],
+                match lyrics_matches.and_then(|matches| matches.get(&song.song_hash)) {
+                    Some(lyrics_match) => div![
+                        C![C.song_item_lyrics],
+                        lyrics_match.snippet.iter().enumerate().map(|(i, part)| {
+                            if i % 2 == 1 {
+                                span![C![C.song_item_lyrics_match], part]
+                            } else {
+                                span![part]
+                            }
+                        }),
+                    ],
+                    None => empty![],
+                },
+                IF![song.removed => div![C![C.song_item_removed_notice], "Inte längre tillgänglig"]],
             ],
             div![
                 C![C.song_gizmos],
                 if user.is_none() {
                     empty![]
-                } else if user_list.contains(&song.song_hash) {
+                } else if user_list.is_some_and(|list| list.contains(&song.song_hash)) {
                     div![
                         C![C.gizmo, C.icon_remove, C.tooltip],
-                        span![C![C.tooltiptext], "Ta bort från min lista"],
+                        span![C![C.tooltiptext], "Ta bort från ", active_list_name],
                         {
                             let song_hash = song.song_hash.clone();
                             ev(Ev::Click, |_| Msg::RemoveFromList(song_hash))
                         },
                     ]
+                } else if song.removed {
+                    empty![]
                 } else {
                     div![
                         C![C.gizmo, C.icon_add, C.tooltip],
-                        span![C![C.tooltiptext], "Spara i min lista"],
+                        span![C![C.tooltiptext], "Spara i ", active_list_name],
                         {
                             let song_hash = song.song_hash.clone();
                             ev(Ev::Click, |_| Msg::AddToList(song_hash))
                         },
                     ]
                 },
+                IF![model.selected_queue.is_some() && !song.removed => div![
+                    C![C.gizmo, C.icon_queue, C.tooltip],
+                    span![C![C.tooltiptext], "Lägg till i kön"],
+                    {
+                        let song_hash = song.song_hash.clone();
+                        ev(Ev::Click, |_| Msg::Enqueue(song_hash))
+                    },
+                ]],
                 match &song.genre {
                     Some(genre) => div![
                         C![C.gizmo, C.icon_genre, C.tooltip],
@@ -474,8 +1271,517 @@ pub fn view_songs(model: &Model) -> Node<Msg> {
     ]
 }
 
+/// The attributes of an image of the cover of a song, shown at about `size` pixels wide.
+fn cover_attrs(model: &Model, song: &Song, size: u32) -> Attrs {
+    match song.cover {
+        Some(_) => {
+            // fetch a thumbnail of about the size that it's shown at, instead of the original
+            let url = format!("/images/songs/{}", song.song_hash);
+            let double = size * 2;
+            attrs! {
+                At::Src => format!("{url}?size={size}"),
+                At::SrcSet => format!("{url}?size={size} 1x, {url}?size={double} 2x"),
+            }
+        }
+        None => {
+            // use a DefaultHasher to turn the song_hash string into a number we can use to give the
+            // song a psuedo-random default cover.
+            let mut hasher = DefaultHasher::new();
+            song.song_hash.hash(&mut hasher);
+            let hash = hasher.finish() as usize;
+            let cover_i = hash % model.default_song_covers.len();
+            let cover = model.default_song_covers[cover_i];
+            attrs! { At::Src => cover }
+        }
+    }
+}
+
+/// A link to the page of a song. Opens the page without reloading, unless the user wants it in a
+/// new tab or window.
+fn song_link(song_hash: &str, content: Vec<Node<Msg>>) -> Node<Msg> {
+    let song_hash = song_hash.to_string();
+    a![
+        C![C.song_link],
+        attrs! { At::Href => song_path(&song_hash) },
+        mouse_ev(Ev::Click, |event| {
+            if event.ctrl_key() || event.meta_key() || event.shift_key() {
+                return None;
+            }
+            event.prevent_default();
+            Some(Msg::ShowSong(song_hash))
+        }),
+        content,
+    ]
+}
+
+/// The page of a song, with everything that's known about it.
+fn view_song_page(model: &Model, song_hash: &str) -> Node<Msg> {
+    let back = a![
+        C![C.user_button],
+        attrs! { At::Href => "/" },
+        ev(Ev::Click, |event| {
+            event.prevent_default();
+            Msg::ShowSongs
+        }),
+        "Tillbaka",
+    ];
+
+    let song = (model.songs.iter())
+        .map(|(_, song)| song)
+        .find(|song| song.song_hash == song_hash);
+
+    let Some(song) = song else {
+        return div![
+            C![C.song_page],
+            div![
+                C![C.song_page_card],
+                back,
+                if model.songs.is_empty() {
+                    // the songs haven't been fetched yet
+                    div![C![C.spinner]]
+                } else {
+                    p!["Låten finns inte."]
+                },
+            ],
+        ];
+    };
+
+    let field = |name: &str, value: Option<&str>| -> Node<Msg> {
+        div![
+            C![C.song_page_field],
+            span![C![C.song_page_field_name], name],
+            span![value.unwrap_or("–")],
+        ]
+    };
+
+    let duet = song.duet().map(|(p1, p2)| format!("{p1} & {p2}"));
+
+    // which of the lists that the user may edit contain the song
+    let lists = match model.user_info.get_option() {
+        Some(user) => {
+            let mut lists: Vec<&CustomList> = (model.custom_lists.values())
+                .filter(|list| list.editable)
+                .collect();
+            lists.sort_by_key(|list| (list.owner != user.cid, list.id));
+
+            let list_item = |list: &&CustomList| -> Node<Msg> {
+                let toggle = |label: &str| {
+                    let (list, song_hash) = (list.id, song.song_hash.clone());
+                    a![
+                        C![C.user_button],
+                        attrs! { At::Href => "javascript:;" },
+                        ev(Ev::Click, move |_| Msg::ToggleListSong { list, song_hash }),
+                        label,
+                    ]
+                };
+
+                div![
+                    C![C.song_page_field],
+                    span![
+                        C![C.song_page_field_name],
+                        &list.name,
+                        IF![list.owner != user.cid => format!(" ({})", list.owner)],
+                    ],
+                    match list.songs.get() {
+                        None => span!["…"],
+                        Some(songs) if songs.contains(&song.song_hash) => toggle("Ta bort"),
+                        Some(_) if song.removed => empty![],
+                        Some(_) => toggle("Lägg till"),
+                    },
+                ]
+            };
+
+            div![
+                div![C![C.song_page_heading], "Listor"],
+                lists.iter().map(list_item),
+                IF![lists.is_empty() && !song.removed => {
+                    let song_hash = song.song_hash.clone();
+                    a![
+                        C![C.user_button],
+                        attrs! { At::Href => "javascript:;" },
+                        ev(Ev::Click, |_| Msg::AddToList(song_hash)),
+                        "Spara i en ny lista",
+                    ]
+                }],
+            ]
+        }
+        None => empty![],
+    };
+
+    let lyrics = match model.song_lyrics.get(&song.song_hash) {
+        Some(Loading::Loaded(Ok(Some(lyrics)))) => div![
+            div![C![C.song_page_heading], "Text"],
+            div![C![C.song_page_lyrics], lyrics],
+        ],
+        Some(Loading::Loaded(Ok(None))) => empty![],
+        Some(Loading::Loaded(Err(_))) => div![
+            div![C![C.song_page_heading], "Text"],
+            div![C![C.song_page_lyrics], "Kunde inte hämta texten"],
+        ],
+        Some(Loading::NotLoaded | Loading::InProgress) | None => div![C![C.spinner]],
+    };
+
+    div![
+        C![C.song_page],
+        div![
+            C![C.song_page_card],
+            back,
+            div![
+                C![C.song_page_head],
+                img![C![C.song_page_cover], cover_attrs(model, song, 256)],
+                div![
+                    div![C![C.song_page_title], &song.title],
+                    div![C![C.song_item_artist], &song.artist],
+                    IF![song.removed => div![C![C.song_item_removed_notice], "Inte längre tillgänglig"]],
+                    IF![model.selected_queue.is_some() && !song.removed => {
+                        let song_hash = song.song_hash.clone();
+                        a![
+                            C![C.user_button],
+                            attrs! { At::Href => "javascript:;" },
+                            ev(Ev::Click, |_| Msg::Enqueue(song_hash)),
+                            "Lägg till i kön",
+                        ]
+                    }],
+                ],
+            ],
+            field("År", song.year.as_deref()),
+            field("Språk", song.language.as_deref()),
+            field("Genre", song.genre.as_deref()),
+            field("BPM", Some(&song.bpm)),
+            field("Duett", Some(duet.as_deref().unwrap_or("Nej"))),
+            field(
+                "Musikvideo",
+                Some(if song.video.is_some() { "Ja" } else { "Nej" })
+            ),
+            lists,
+            lyrics,
+        ],
+    ]
+}
+
+/// Show a text with some of its chars highlighted, e.g. the ones that matched a search.
+fn view_highlighted(text: &str, positions: &[usize]) -> Vec<Node<Msg>> {
+    let mut nodes = vec![];
+    let mut part = String::new();
+    let mut highlighted = false;
+
+    let mut end_part = |part: &mut String, highlighted| {
+        if part.is_empty() {
+            return;
+        }
+
+        let part = std::mem::take(part);
+        nodes.push(if highlighted {
+            span![C![C.song_item_match], part]
+        } else {
+            Node::new_text(part)
+        });
+    };
+
+    for (i, c) in text.chars().enumerate() {
+        let matched = positions.binary_search(&i).is_ok();
+        if matched != highlighted {
+            end_part(&mut part, highlighted);
+            highlighted = matched;
+        }
+        part.push(c);
+    }
+    end_part(&mut part, highlighted);
+
+    nodes
+}
+
+pub fn view_queue(model: &Model) -> Node<Msg> {
+    let user = model.user_info.get_option();
+
+    let head = match &model.queue {
+        Some(queue) => match queue.up_next().next() {
+            Some(next) => format!("{}: {} står på tur", queue.session.name, next.singer),
+            None => format!("{}: kön är tom", queue.session.name),
+        },
+        None => String::from("Välj en kö"),
+    };
+
+    let list = match (&model.queue, model.selected_queue) {
+        (Some(queue), _) => {
+            let is_owner = user.is_some_and(|user| user.cid == queue.session.owner);
+            let up_next: Vec<_> = queue.up_next().collect();
+            let last = up_next.len().saturating_sub(1);
+
+            let queue_item = |(i, entry): (usize, &&QueueEntry)| -> Node<Msg> {
+                let song = model
+                    .songs
+                    .iter()
+                    .map(|(_, song)| song)
+                    .find(|song| song.song_hash == entry.song_hash);
+                let is_singer =
+                    user.is_some_and(|user| entry.singer_cid.as_ref() == Some(&user.cid));
+                let id = entry.id;
+
+                div![
+                    C![C.queue_item],
+                    div![C![C.queue_item_position], format!("{}.", i + 1)],
+                    div![
+                        C![C.queue_item_info],
+                        match song {
+                            Some(song) => div![&song.title, " - ", &song.artist],
+                            None => div!["Okänd låt"],
+                        },
+                        div![C![C.queue_item_singer], &entry.singer],
+                    ],
+                    IF![is_owner && i > 0 => button![
+                        C![C.queue_button],
+                        ev(Ev::Click, move |_| Msg::MoveQueueEntry { entry: id, index: i - 1 }),
+                        "↑",
+                    ]],
+                    IF![is_owner && i < last => button![
+                        C![C.queue_button],
+                        ev(Ev::Click, move |_| Msg::MoveQueueEntry { entry: id, index: i + 1 }),
+                        "↓",
+                    ]],
+                    IF![is_owner => button![
+                        C![C.queue_button],
+                        ev(Ev::Click, move |_| Msg::QueueEntryDone(id)),
+                        "✓",
+                    ]],
+                    IF![is_owner || is_singer => button![
+                        C![C.queue_button],
+                        ev(Ev::Click, move |_| Msg::SkipQueueEntry(id)),
+                        "✗",
+                    ]],
+                ]
+            };
+
+            div![
+                a![
+                    C![C.user_button],
+                    attrs! { At::Href => "javascript:;" },
+                    ev(Ev::Click, |_| Msg::SelectQueue(None)),
+                    "Byt kö",
+                ],
+                input![
+                    C![C.queue_input],
+                    input_ev(Ev::Input, Msg::QueueSinger),
+                    attrs! {
+                        At::Placeholder => "Ditt namn",
+                        At::Value => model.queue_singer,
+                    },
+                ],
+                up_next.iter().enumerate().map(queue_item),
+            ]
+        }
+        (None, Some(_)) => div![C![C.spinner]],
+        (None, None) => div![
+            match &model.queue_sessions {
+                Loading::Loaded(sessions) => sessions
+                    .iter()
+                    .map(|session| {
+                        let id = session.id;
+                        div![
+                            C![C.queue_item],
+                            ev(Ev::Click, move |_| Msg::SelectQueue(Some(id))),
+                            &session.name,
+                        ]
+                    })
+                    .collect(),
+                Loading::NotLoaded | Loading::InProgress => vec![div![C![C.spinner]]],
+            },
+            IF![user.is_some() => div![
+                input![
+                    C![C.queue_input],
+                    input_ev(Ev::Input, Msg::NewQueueName),
+                    attrs! {
+                        At::Placeholder => "Namn på ny kö",
+                        At::Value => model.new_queue_name,
+                    },
+                ],
+                a![
+                    C![C.user_button],
+                    attrs! { At::Href => "javascript:;" },
+                    ev(Ev::Click, |_| Msg::CreateQueue),
+                    "Skapa kö",
+                ],
+            ]],
+        ],
+    };
+
+    div![
+        C![C.play_queue],
+        IF![model.queue_collapsed => C![C.play_queue_hidden]],
+        div![
+            C![C.play_queue_head],
+            ev(Ev::Click, |_| Msg::CollapseQueue),
+            head,
+        ],
+        div![C![C.play_queue_list], list],
+    ]
+}
+
+/// Lets the user create and revoke personal access tokens, for scripting against their lists.
+fn view_tokens(model: &Model) -> Node<Msg> {
+    let tokens = match &model.personal_tokens {
+        Loading::Loaded(tokens) => tokens
+            .iter()
+            .map(|token| {
+                let id = token.id;
+                let last_used = match &token.last_used_at {
+                    Some(time) => format!("Senast använd {}", time.replace('T', " ")),
+                    None => String::from("Aldrig använd"),
+                };
+                let expires = format!("Går ut {}", token.expires_at.replace('T', " "));
+
+                div![
+                    C![C.queue_item],
+                    div![
+                        C![C.queue_item_info],
+                        div![&token.name],
+                        div![C![C.queue_item_singer], last_used],
+                        div![C![C.queue_item_singer], expires],
+                    ],
+                    button![
+                        C![C.queue_button],
+                        ev(Ev::Click, move |_| Msg::RevokeToken(id)),
+                        "✗",
+                    ],
+                ]
+            })
+            .collect(),
+        Loading::NotLoaded | Loading::InProgress => vec![div![C![C.spinner]]],
+    };
+
+    div![
+        C![C.token_panel],
+        p!["Med en nyckel kan dina skript ändra dina listor utan att logga in."],
+        tokens,
+        a![
+            C![C.user_button],
+            attrs! { At::Href => "javascript:;" },
+            ev(Ev::Click, |_| Msg::NewToken),
+            "Ny nyckel",
+        ],
+    ]
+}
+
+/// Lets the user pick which one of their custom lists to show and add songs to.
+fn view_list_picker(model: &Model, user: &UserInfo) -> Node<Msg> {
+    let mut lists: Vec<&CustomList> = model
+        .custom_lists
+        .values()
+        .filter(|list| list.editable)
+        .collect();
+    lists.sort_by_key(|list| (list.owner != user.cid, list.id));
+
+    let active_list = model.active_list;
+    let owned_active_list = active_list
+        .and_then(|list| model.custom_lists.get(&list))
+        .filter(|list| list.owner == user.cid);
+    let has_link = owned_active_list.is_some_and(|list| list.visibility != Visibility::Private);
+
+    let visibility_option = |visibility: Visibility, label: &str| {
+        let selected = owned_active_list.is_some_and(|list| list.visibility == visibility);
+        option![
+            attrs! { At::Value => visibility.as_str() },
+            IF![selected => attrs! { At::Selected => AtValue::None }],
+            label,
+        ]
+    };
+
+    span![
+        select![
+            C![C.user_button],
+            input_ev(Ev::Change, |value| match value.parse() {
+                Ok(list) => Msg::ShowList(list),
+                Err(_) => Msg::NewList,
+            }),
+            IF![active_list.is_none() => option![
+                attrs! {
+                    At::Value => "",
+                    At::Selected => AtValue::None,
+                    At::Disabled => AtValue::None,
+                },
+                "Mina listor",
+            ]],
+            lists.iter().map(|list| {
+                option![
+                    attrs! { At::Value => list.id },
+                    IF![active_list == Some(list.id) => attrs! { At::Selected => AtValue::None }],
+                    &list.name,
+                    IF![list.owner != user.cid => format!(" ({})", list.owner)],
+                ]
+            }),
+            option![attrs! { At::Value => "new" }, "Ny lista..."],
+        ],
+        match owned_active_list.map(|list| list.id) {
+            Some(list) => vec![
+                a![
+                    C![C.user_button],
+                    attrs! { At::Href => "javascript:;" },
+                    ev(Ev::Click, move |_| Msg::RenameList(list)),
+                    "Byt namn",
+                ],
+                a![
+                    C![C.user_button],
+                    attrs! { At::Href => "javascript:;" },
+                    ev(Ev::Click, move |_| Msg::DeleteList(list)),
+                    "Ta bort",
+                ],
+                a![
+                    C![C.user_button],
+                    attrs! { At::Href => "javascript:;" },
+                    ev(Ev::Click, move |_| Msg::ShareList(list)),
+                    "Dela",
+                ],
+                if has_link {
+                    a![
+                        C![C.user_button],
+                        attrs! { At::Href => "javascript:;" },
+                        ev(Ev::Click, move |_| Msg::ShowListLink(list)),
+                        "Länk",
+                    ]
+                } else {
+                    empty![]
+                },
+                select![
+                    C![C.user_button],
+                    input_ev(Ev::Change, move |value| {
+                        Visibility::from_str(&value).map(|v| Msg::SetListVisibility(list, v))
+                    }),
+                    visibility_option(Visibility::Private, "Privat"),
+                    visibility_option(Visibility::Unlisted, "Olistad"),
+                    visibility_option(Visibility::Public, "Publik"),
+                ],
+            ],
+            None => vec![],
+        },
+    ]
+}
+
+/// Explain why logging in failed, from the code that the backend redirected with.
+fn login_error_message(code: &str) -> &'static str {
+    match code {
+        "gamma_unavailable" => {
+            "Inloggningen misslyckades, Gamma svarar inte. Försök igen om en stund."
+        }
+        "denied" => "Inloggningen avbröts i Gamma.",
+        "expired" => "Inloggningen tog för lång tid, eller gjordes i en gammal flik. Försök igen.",
+        "rejected" => "Gamma godkände inte inloggningen. Försök igen.",
+        "session" => {
+            "Inloggningen misslyckades, kunde inte spara kakan. Tillåter webbläsaren kakor?"
+        }
+        _ => "Inloggningen misslyckades.",
+    }
+}
+
 pub fn view(model: &Model) -> Vec<Node<Msg>> {
     vec![
+        match &model.login_error {
+            Some(code) => div![
+                C![C.error_banner],
+                ev(Ev::Click, |_| Msg::DismissLoginError),
+                login_error_message(code),
+            ],
+            None => empty![],
+        },
         div![
             C![C.user_bar],
             match &model.user_info {
@@ -492,13 +1798,14 @@ pub fn view(model: &Model) -> Vec<Node<Msg>> {
                         "Hej ",
                         &user.nick,
                         " :* ",
+                        view_list_picker(model, user),
+                        " ",
                         a![
                             C![C.user_button],
-                            attrs! { At::Href => "javascript:;"},
-                            ev(Ev::Click, |_| Msg::ShowUserList),
-                            "Min lista"
+                            attrs! { At::Href => "javascript:;" },
+                            ev(Ev::Click, |_| Msg::ToggleTokens),
+                            "Nycklar",
                         ],
-                        " ",
                         a![
                             C![C.user_button],
                             attrs! { At::Href => "/logout"},
@@ -508,11 +1815,16 @@ pub fn view(model: &Model) -> Vec<Node<Msg>> {
                 }
             },
         ],
+        if model.show_tokens {
+            view_tokens(model)
+        } else {
+            empty![]
+        },
         div![
             C![C.song_search_bar],
             input![
                 C![C.song_search_field],
-                input_ev(Ev::Input, Msg::Search),
+                input_ev(Ev::Input, Msg::SearchInput),
                 attrs! {
                     At::Placeholder => &model.query_placeholder[..model.query_placeholder_len],
                     At::Value => model.query,
@@ -536,6 +1848,12 @@ pub fn view(model: &Model) -> Vec<Node<Msg>> {
                 ev(Ev::Click, |_| Msg::ToggleVideo),
                 span![C![C.tooltiptext], "Endast med Video"],
             ],
+            button![
+                C![C.song_sort_button, C.tooltip, C.icon_queue],
+                IF![model.show_queue => C![C.song_sort_button_selected]],
+                ev(Ev::Click, |_| Msg::ToggleQueue),
+                span![C![C.tooltiptext], "Visa kön"],
+            ],
             button![
                 C![C.song_sort_button, C.song_sort_button_right],
                 C![C.tooltip, C.icon_shuffle],
@@ -543,9 +1861,15 @@ pub fn view(model: &Model) -> Vec<Node<Msg>> {
                 span![C![C.tooltiptext], "Blanda låtar"],
             ],
         ],
-        match model.screen {
+        match &model.screen {
             View::Songs => view_songs(model),
             View::Categories => view_categories(model),
+            View::Song(song_hash) => view_song_page(model, song_hash),
+        },
+        if model.show_queue {
+            view_queue(model)
+        } else {
+            empty![]
         },
     ]
 }
@@ -561,6 +1885,16 @@ async fn fetch_songs() -> Option<Msg> {
 
     songs.shuffle(&mut thread_rng());
 
+    match fetch_list_of::<Song>("/songs/removed").await {
+        Ok(removed) => songs.extend(removed.into_iter().map(|song| Song {
+            removed: true,
+            ..song
+        })),
+        Err(e) => {
+            error!("Error fetching removed songs:", e);
+        }
+    }
+
     Some(Msg::Songs(songs))
 }
 
@@ -592,7 +1926,7 @@ async fn fetch_user_info() -> Option<Msg> {
 
 pub fn autotype_song(model: &mut Model, orders: &mut impl Orders<Msg>) {
     let (_, song) = &model.songs[0];
-    model.query_placeholder = ParsedQuery::random(song, &mut thread_rng()).to_string();
+    model.query_placeholder = random_query(song, &mut thread_rng()).to_string();
     model.query_placeholder_len = 0;
     model.autotyper = Some(orders.perform_cmd_with_handle(timeout(100, || Msg::Autotyper)));
 }
@@ -610,6 +1944,15 @@ fn scroll_to_top() {
     }
 }
 
+/// Ask the user to enter some text. Returns `None` if they cancelled or didn't enter anything.
+fn prompt(message: &str, default: &str) -> Option<String> {
+    let text = window()
+        .prompt_with_message_and_default(message, default)
+        .ok()??;
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
 fn get_scroll() -> Option<(i32, i32)> {
     let list = get_song_list_element()?;
     let scroll = list.scroll_top();
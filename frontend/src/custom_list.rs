@@ -1,17 +1,209 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 use gloo_console::error;
 use gloo_net::http::Request;
+use seed::browser::util::window;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     app::{Loading, Msg},
-    fetch::{fetch_list_of, FetchError},
+    fetch::{fetch_list_of, send, FetchError},
 };
 
-pub type CustomLists = HashMap<String, Loading<HashSet<String>>>;
+pub type CustomLists = HashMap<i32, CustomList>;
+
+#[derive(Deserialize)]
+pub struct CustomList {
+    pub id: i32,
+    pub name: String,
+
+    /// CID of the user that owns the list.
+    pub owner: String,
+
+    /// Users and groups that the owner has invited to edit the list.
+    pub editors: Vec<ListEditor>,
+
+    /// Whether the logged in user may edit the list.
+    pub editable: bool,
+
+    pub visibility: Visibility,
+
+    /// The token that lets anyone see the list while it's unlisted. Only known to the users that
+    /// may edit the list, and to those that opened a link to it.
+    pub share_token: Option<String>,
+
+    /// Hashes of the songs in the list, lazily loaded.
+    #[serde(skip)]
+    pub songs: Loading<ListSongs>,
+}
+
+impl CustomList {
+    /// A list that the page was opened with a link to, but that isn't in the index because it's
+    /// unlisted.
+    pub fn shared(id: i32, share_token: String) -> Self {
+        CustomList {
+            id,
+            name: String::new(),
+            owner: String::new(),
+            editors: vec![],
+            editable: false,
+            visibility: Visibility::Unlisted,
+            share_token: Some(share_token),
+            songs: Loading::NotLoaded,
+        }
+    }
+
+    /// A link that shows the list to anyone that may see it, or `None` if it's private.
+    pub fn link(&self) -> Option<String> {
+        let origin = window().location().origin().ok()?;
+        match (self.visibility, &self.share_token) {
+            (Visibility::Public, _) => Some(format!("{origin}/?list={}", self.id)),
+            (Visibility::Unlisted, Some(token)) => {
+                Some(format!("{origin}/?list={}&token={token}", self.id))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The songs of a custom list, in the order that its editors arranged them.
+#[derive(Default)]
+pub struct ListSongs {
+    order: Vec<String>,
+    positions: HashMap<String, usize>,
+}
+
+impl ListSongs {
+    pub fn new(order: Vec<String>) -> Self {
+        let mut songs = ListSongs {
+            order,
+            positions: HashMap::new(),
+        };
+        songs.update_positions();
+        songs
+    }
+
+    pub fn contains(&self, song_hash: &str) -> bool {
+        self.positions.contains_key(song_hash)
+    }
+
+    /// Get the index of a song in the list.
+    pub fn position(&self, song_hash: &str) -> Option<usize> {
+        self.positions.get(song_hash).copied()
+    }
+
+    /// Move a song to another index in the list.
+    pub fn move_song(&mut self, song_hash: &str, index: usize) {
+        let Some(old_index) = self.position(song_hash) else {
+            return;
+        };
+
+        let song = self.order.remove(old_index);
+        self.order.insert(index.min(self.order.len()), song);
+        self.update_positions();
+    }
+
+    fn update_positions(&mut self) {
+        self.positions = (self.order.iter().cloned()).zip(0..).collect();
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ListEditor {
+    pub kind: EditorKind,
+
+    /// A CID or the name of a Gamma group, depending on `kind`.
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EditorKind {
+    User,
+    Group,
+}
+
+impl ListEditor {
+    /// Parse a comma separated list of CIDs and `@`-prefixed group names, e.g. `"anna, @digit"`.
+    pub fn parse_list(s: &str) -> Vec<ListEditor> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| match name.strip_prefix('@') {
+                Some(group) => ListEditor {
+                    kind: EditorKind::Group,
+                    name: group.trim().to_string(),
+                },
+                None => ListEditor {
+                    kind: EditorKind::User,
+                    name: name.to_string(),
+                },
+            })
+            .collect()
+    }
+
+    /// The inverse of [ListEditor::parse_list].
+    pub fn format_list(editors: &[ListEditor]) -> String {
+        let editors: Vec<String> = editors
+            .iter()
+            .map(|editor| match editor.kind {
+                EditorKind::User => editor.name.clone(),
+                EditorKind::Group => format!("@{}", editor.name),
+            })
+            .collect();
+        editors.join(", ")
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    /// Only the owner and invited editors may see the list.
+    Private,
+
+    /// Anyone with a link may see the list, but it isn't shown in the index.
+    Unlisted,
+
+    /// Anyone may see the list, and it's shown in the index.
+    Public,
+}
+
+impl Visibility {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Visibility::Private => "private",
+            Visibility::Unlisted => "unlisted",
+            Visibility::Public => "public",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "private" => Some(Visibility::Private),
+            "unlisted" => Some(Visibility::Unlisted),
+            "public" => Some(Visibility::Public),
+            _ => None,
+        }
+    }
+}
+
+/// Changes to make to a custom list. Fields that are `None` are left as they are.
+#[derive(Serialize, Default)]
+pub struct ListUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<Visibility>,
+}
+
+#[derive(Serialize)]
+struct ListName {
+    name: String,
+}
 
 pub async fn fetch_custom_song_list_index() -> Option<Msg> {
-    let custom_lists: Vec<String> = match fetch_list_of("custom/lists").await {
+    let custom_lists: Vec<CustomList> = match fetch_list_of("/custom/lists").await {
         Ok(response) => response,
         Err(e) => {
             error!("Failed fetching custom song list index:", e);
@@ -22,9 +214,16 @@ pub async fn fetch_custom_song_list_index() -> Option<Msg> {
     Some(Msg::CustomSongLists(custom_lists))
 }
 
-pub async fn fetch_custom_song_list(list: String) -> Option<Msg> {
-    let song_hashes: HashSet<String> = match fetch_list_of(format!("custom/list/{list}")).await {
-        Ok(response) => response.into_iter().collect(),
+/// Fetch the songs of a custom list. The share token is only needed if the user may not edit the
+/// list, and it's unlisted.
+pub async fn fetch_custom_song_list(list: i32, share_token: Option<String>) -> Option<Msg> {
+    let url = match share_token {
+        Some(token) => format!("/custom/list/{list}?token={token}"),
+        None => format!("/custom/list/{list}"),
+    };
+
+    let song_hashes: Vec<String> = match fetch_list_of(url).await {
+        Ok(response) => response,
         Err(e) => {
             error!("Failed fetching custom song list:", e);
             return None;
@@ -34,9 +233,71 @@ pub async fn fetch_custom_song_list(list: String) -> Option<Msg> {
     Some(Msg::CustomSongList { list, song_hashes })
 }
 
-pub async fn add_song_to_list(cid: String, song_hash: String) -> Option<Msg> {
+/// Create a new custom list, optionally with a song already in it.
+pub async fn create_list(name: String, first_song: Option<String>) -> Option<Msg> {
+    let result = async {
+        let request = Request::post("/custom/list").json(&ListName { name })?;
+        let list: CustomList = send(request).await?.json().await?;
+
+        if let Some(song_hash) = first_song {
+            let url = format!("/custom/list/{}/{song_hash}", list.id);
+            send(Request::put(&url).build()?).await?;
+        }
+
+        Ok::<_, FetchError>(list)
+    };
+
+    match result.await {
+        Ok(list) => Some(Msg::CustomListCreated(list)),
+        Err(e) => {
+            error!("Error creating custom list:", e);
+            None
+        }
+    }
+}
+
+pub async fn update_list(list: i32, update: ListUpdate) -> Option<Msg> {
     let result = async {
-        let response = Request::put(&format!("/custom/list/{cid}/{song_hash}"))
+        let request = Request::put(&format!("/custom/list/{list}")).json(&update)?;
+        send(request).await
+    };
+
+    if let Err(e) = result.await {
+        error!("Error updating custom list:", e);
+    }
+
+    fetch_custom_song_list_index().await
+}
+
+pub async fn delete_list(list: i32) -> Option<Msg> {
+    let result = async {
+        let request = Request::delete(&format!("/custom/list/{list}")).build()?;
+        send(request).await
+    };
+
+    if let Err(e) = result.await {
+        error!("Error deleting custom list:", e);
+    }
+
+    fetch_custom_song_list_index().await
+}
+
+pub async fn set_list_editors(list: i32, editors: Vec<ListEditor>) -> Option<Msg> {
+    let result = async {
+        let request = Request::put(&format!("/custom/list/{list}/editors")).json(&editors)?;
+        send(request).await
+    };
+
+    if let Err(e) = result.await {
+        error!("Error sharing custom list:", e);
+    }
+
+    fetch_custom_song_list_index().await
+}
+
+pub async fn add_song_to_list(list: i32, song_hash: String) -> Option<Msg> {
+    let result = async {
+        let response = Request::put(&format!("/custom/list/{list}/{song_hash}"))
             .send()
             .await?;
 
@@ -54,12 +315,30 @@ pub async fn add_song_to_list(cid: String, song_hash: String) -> Option<Msg> {
         error!("Error adding song to custom list:", e);
     }
 
-    fetch_custom_song_list(cid).await
+    fetch_custom_song_list(list, None).await
+}
+
+pub async fn move_song_in_list(list: i32, song_hash: String, index: usize) -> Option<Msg> {
+    #[derive(Serialize)]
+    struct MoveEntry {
+        index: usize,
+    }
+
+    let result = async {
+        let url = format!("/custom/list/{list}/{song_hash}/position");
+        send(Request::put(&url).json(&MoveEntry { index })?).await
+    };
+
+    if let Err(e) = result.await {
+        error!("Error moving song in custom list:", e);
+    }
+
+    fetch_custom_song_list(list, None).await
 }
 
-pub async fn remove_song_from_list(cid: String, song_hash: String) -> Option<Msg> {
+pub async fn remove_song_from_list(list: i32, song_hash: String) -> Option<Msg> {
     let result = async {
-        let response = Request::delete(&format!("/custom/list/{cid}/{song_hash}"))
+        let response = Request::delete(&format!("/custom/list/{list}/{song_hash}"))
             .send()
             .await?;
 
@@ -77,5 +356,5 @@ pub async fn remove_song_from_list(cid: String, song_hash: String) -> Option<Msg
         error!("Error removing song from custom list:", e);
     }
 
-    fetch_custom_song_list(cid).await
+    fetch_custom_song_list(list, None).await
 }
@@ -0,0 +1,81 @@
+use gloo_console::error;
+use gloo_net::eventsource::futures::EventSource;
+use seed::futures::StreamExt;
+use seed::prelude::Orders;
+use serde::Deserialize;
+
+use crate::app::Msg;
+
+/// A change pushed by the server, see `/events`.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// The event stream was (re)opened.
+    Connected,
+
+    /// The song list was replaced.
+    SongsReplaced,
+
+    /// A custom list was created, renamed or deleted.
+    CustomListsChanged,
+
+    /// A song was added to or removed from a custom list.
+    CustomListChanged { list: i32 },
+
+    /// A queue session or its entries were changed.
+    QueueChanged { session: i32 },
+
+    /// Some events were missed, and everything should be reloaded.
+    Resync,
+}
+
+/// Subscribe to server events, and forward them as messages.
+///
+/// The returned [EventSource] must be kept alive for as long as events should be received.
+pub fn subscribe(orders: &mut impl Orders<Msg>) -> Option<EventSource> {
+    let mut events = match EventSource::new("/events") {
+        Ok(events) => events,
+        Err(e) => {
+            error!("Failed to open event stream:", e.to_string());
+            return None;
+        }
+    };
+
+    let subscription = match events.subscribe("message") {
+        Ok(subscription) => subscription,
+        Err(e) => {
+            error!("Failed to subscribe to event stream:", e.to_string());
+            return None;
+        }
+    };
+
+    orders.stream(subscription.map(|message| {
+        let (_, message) = match message {
+            Ok(message) => message,
+            Err(e) => {
+                error!("Event stream error:", e.to_string());
+                return None;
+            }
+        };
+
+        let data = message.data().as_string()?;
+        let event: Event = match serde_json::from_str(&data) {
+            Ok(event) => event,
+            Err(e) => {
+                error!("Failed to parse event:", e.to_string());
+                return None;
+            }
+        };
+
+        Some(match event {
+            Event::Connected => Msg::EventsConnected,
+            Event::SongsReplaced => Msg::SongsReplaced,
+            Event::CustomListsChanged => Msg::CustomListsChanged,
+            Event::CustomListChanged { list } => Msg::CustomListChanged(list),
+            Event::QueueChanged { session } => Msg::QueueChanged(session),
+            Event::Resync => Msg::Resync,
+        })
+    }));
+
+    Some(events)
+}
@@ -47,6 +47,20 @@ pub async fn fetch(url: impl AsRef<str>) -> Result<Response, FetchError> {
     Ok(response)
 }
 
+/// Send a request, and make sure that it returned a 2XX status code.
+pub async fn send(request: Request) -> Result<Response, FetchError> {
+    let response = request.send().await?;
+
+    if !response.ok() {
+        return Err(FetchError::Status {
+            code: response.status(),
+            text: response.status_text(),
+        });
+    }
+
+    Ok(response)
+}
+
 /// Perform a GET request and try to deserialize the response as a `Vec<T>`.
 pub async fn fetch_list_of<T: DeserializeOwned>(
     url: impl AsRef<str>,
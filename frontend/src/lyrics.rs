@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use gloo_console::error;
+use seed::prelude::js_sys::encode_uri_component;
+use serde::Deserialize;
+use singit_lib::fuzzy::FuzzyScore;
+
+use crate::{
+    app::{Loading, Msg},
+    fetch::{fetch, fetch_list_of, FetchError},
+};
+
+/// Results of lyrics searches, keyed by the search string. Lazily loaded.
+pub type LyricsMatches = HashMap<String, Loading<HashMap<String, LyricsMatch>>>;
+
+/// Lyrics of songs, keyed by song hash. Lazily loaded, `Ok(None)` if the song has no lyrics, and
+/// the error if they couldn't be fetched, in which case they're fetched again the next time that
+/// the page of the song is opened.
+pub type SongLyrics = HashMap<String, Loading<Result<Option<String>, String>>>;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct LyricsMatch {
+    pub song_hash: String,
+
+    /// The best matching line of lyrics, split into parts. Every other part is a match, starting
+    /// with a part that isn't.
+    pub snippet: Vec<String>,
+
+    /// How well the lyrics matched. Derived from the order of the search results.
+    #[serde(skip)]
+    pub score: FuzzyScore,
+}
+
+pub async fn search_lyrics(query: String) -> Option<Msg> {
+    let url = format!("/songs/lyrics?q={}", encode_uri_component(&query));
+    let mut matches: Vec<LyricsMatch> = match fetch_list_of(url).await {
+        Ok(response) => response,
+        Err(e) => {
+            error!("Failed searching lyrics:", e);
+            return None;
+        }
+    };
+
+    // the server returns the best match first
+    let count = matches.len();
+    for (i, m) in matches.iter_mut().enumerate() {
+        m.score = (count - i) as FuzzyScore;
+    }
+
+    Some(Msg::LyricsMatches { query, matches })
+}
+
+pub async fn fetch_song_lyrics(song_hash: String) -> Option<Msg> {
+    let result = async {
+        let response = fetch(format!("/songs/{song_hash}/lyrics")).await?;
+        response.json().await.map_err(FetchError::from)
+    };
+
+    let lyrics = match result.await {
+        Ok(lyrics) => Ok(lyrics),
+
+        // the song is unknown, or the backend runs with sqlite, which doesn't store lyrics
+        Err(FetchError::Status {
+            code: 404 | 501, ..
+        }) => Ok(None),
+
+        Err(e) => {
+            let message = e.to_string();
+            error!("Failed fetching lyrics:", e);
+            Err(message)
+        }
+    };
+
+    Some(Msg::SongLyrics { song_hash, lyrics })
+}
@@ -2,10 +2,13 @@ mod app;
 mod category;
 mod css;
 mod custom_list;
+mod events;
 mod fetch;
-mod fuzzy;
+mod lyrics;
 mod query;
+mod queue;
 mod song;
+mod token;
 
 use seed::App;
 
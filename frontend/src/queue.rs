@@ -0,0 +1,151 @@
+use gloo_console::error;
+use gloo_net::http::Request;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::Msg,
+    fetch::{fetch, fetch_list_of, send, FetchError},
+};
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct QueueSession {
+    pub id: i32,
+    pub name: String,
+
+    /// CID of the user that manages the queue.
+    pub owner: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct QueueEntry {
+    pub id: i32,
+    pub song_hash: String,
+    pub singer: String,
+    pub singer_cid: Option<String>,
+    pub state: QueueEntryState,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueEntryState {
+    Queued,
+    Done,
+    Skipped,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Queue {
+    #[serde(flatten)]
+    pub session: QueueSession,
+
+    /// All entries of the queue, ordered by when they are to be sung.
+    pub entries: Vec<QueueEntry>,
+}
+
+impl Queue {
+    /// Iterate over the entries that haven't been sung or skipped yet.
+    pub fn up_next(&self) -> impl Iterator<Item = &QueueEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.state == QueueEntryState::Queued)
+    }
+}
+
+pub async fn fetch_queue_sessions() -> Option<Msg> {
+    let sessions = match fetch_list_of("/queues").await {
+        Ok(response) => response,
+        Err(e) => {
+            error!("Failed fetching queue sessions:", e);
+            return None;
+        }
+    };
+
+    Some(Msg::QueueSessions(sessions))
+}
+
+pub async fn fetch_queue(session: i32) -> Option<Msg> {
+    let result = async {
+        let response = fetch(format!("/queue/{session}")).await?;
+        Ok::<Queue, FetchError>(response.json().await?)
+    };
+
+    match result.await {
+        Ok(queue) => Some(Msg::Queue(queue)),
+        Err(e) => {
+            error!("Failed fetching queue:", e);
+            None
+        }
+    }
+}
+
+pub async fn create_queue_session(name: String) -> Option<Msg> {
+    #[derive(Serialize)]
+    struct NewSession {
+        name: String,
+    }
+
+    let result = async {
+        let response = send(Request::post("/queue").json(&NewSession { name })?).await?;
+        Ok::<QueueSession, FetchError>(response.json().await?)
+    };
+
+    match result.await {
+        Ok(session) => Some(Msg::QueueCreated(session)),
+        Err(e) => {
+            error!("Error creating queue:", e);
+            fetch_queue_sessions().await
+        }
+    }
+}
+
+pub async fn enqueue(session: i32, song_hash: String, singer: Option<String>) -> Option<Msg> {
+    #[derive(Serialize)]
+    struct NewEntry {
+        song_hash: String,
+        singer: Option<String>,
+    }
+
+    let result = async {
+        let entry = NewEntry { song_hash, singer };
+        let request = Request::post(&format!("/queue/{session}/entry")).json(&entry)?;
+        send(request).await
+    };
+
+    if let Err(e) = result.await {
+        error!("Error adding song to queue:", e);
+    }
+
+    fetch_queue(session).await
+}
+
+pub async fn move_entry(session: i32, entry: i32, index: usize) -> Option<Msg> {
+    #[derive(Serialize)]
+    struct MoveEntry {
+        index: usize,
+    }
+
+    let result = async {
+        let url = format!("/queue/{session}/entry/{entry}/position");
+        send(Request::put(&url).json(&MoveEntry { index })?).await
+    };
+
+    if let Err(e) = result.await {
+        error!("Error moving queue entry:", e);
+    }
+
+    fetch_queue(session).await
+}
+
+/// Mark an entry as done (`"done"`) or skip it (`"skip"`).
+pub async fn set_entry_state(session: i32, entry: i32, action: &'static str) -> Option<Msg> {
+    let result = async {
+        let url = format!("/queue/{session}/entry/{entry}/{action}");
+        send(Request::put(&url).build()?).await
+    };
+
+    if let Err(e) = result.await {
+        error!("Error updating queue entry:", e);
+    }
+
+    fetch_queue(session).await
+}
@@ -1,8 +1,10 @@
 use crate::app::Loading;
 use crate::custom_list::CustomLists;
-use crate::fuzzy::{self, FuzzyScore};
+use crate::lyrics::LyricsMatches;
 use crate::query::ParsedQuery;
 use serde::Deserialize;
+use singit_lib::fuzzy::FuzzyScore;
+use singit_lib::search::Searchable;
 use std::cmp::max;
 
 #[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
@@ -20,6 +22,14 @@ pub struct Song {
     pub duet_singer_1: Option<String>,
     #[serde(rename = "duetsingerp2")]
     pub duet_singer_2: Option<String>,
+
+    /// The song is no longer in the song list, but is kept for custom lists and queues.
+    #[serde(skip)]
+    pub removed: bool,
+
+    /// The position of the song in the search index.
+    #[serde(skip)]
+    pub search_id: usize,
 }
 
 impl Song {
@@ -29,43 +39,32 @@ impl Song {
             .zip(self.duet_singer_2.as_deref())
     }
 
-    pub fn fuzzy_compare(&self, query: &ParsedQuery, custom_lists: &CustomLists) -> FuzzyScore {
+    /// Score the song for a query, given its score from the search index, which doesn't know about
+    /// custom lists and lyrics.
+    pub fn fuzzy_compare(
+        &self,
+        score: Option<FuzzyScore>,
+        query: &ParsedQuery,
+        custom_lists: &CustomLists,
+        lyrics_matches: &LyricsMatches,
+    ) -> FuzzyScore {
         let bad: FuzzyScore = -1;
 
-        let filter_strs = |query: Option<&str>, item: Option<&str>| {
-            if let Some(query) = query {
-                match item {
-                    Some(item) => {
-                        let query_no_whitespace = query.replace(char::is_whitespace, "");
-                        let item_no_whitespace = item.replace(char::is_whitespace, "");
-                        let score =
-                            fuzzy::compare(item_no_whitespace.chars(), query_no_whitespace.chars());
-                        score == fuzzy::max_score(&query_no_whitespace)
-                    }
-                    None => false,
-                }
-            } else {
-                true
-            }
-        };
-
-        let filter_bool =
-            |query: Option<bool>, item| !matches!(query, Some(query) if query != item);
-
-        let filters: &[&dyn Fn() -> bool] = &[
-            &|| filter_bool(query.duet, self.duet().is_some()),
-            &|| filter_bool(query.video, self.video.is_some()),
-            &|| filter_strs(query.language, self.language.as_deref()),
-            &|| filter_strs(query.genre, self.genre.as_deref()),
-            &|| filter_strs(query.year, self.year.as_deref()),
-        ];
-
-        if !filters.iter().all(|f| f()) {
+        // removed songs are only shown in the lists that contain them
+        if self.removed && query.list.is_none() {
             return bad;
         }
 
-        if let Some(list) = query.list {
-            let Some(Loading::Loaded(list)) = custom_lists.get(list) else {
+        let Some(mut score) = score else {
+            return bad;
+        };
+
+        if let Some(list) = &query.list {
+            let list = list
+                .parse()
+                .ok()
+                .and_then(|list: i32| custom_lists.get(&list));
+            let Some(Loading::Loaded(list)) = list.map(|list| &list.songs) else {
                 return bad;
             };
 
@@ -74,23 +73,52 @@ impl Song {
             }
         }
 
-        let mut score = FuzzyScore::default();
-        if let Some(plain) = &query.plain {
-            let title_score = fuzzy::compare(self.title.chars(), plain.chars());
-            let artist_score = fuzzy::compare(self.artist.chars(), plain.chars());
-            score = max(title_score, artist_score);
-        }
+        if let Some(lyrics) = &query.lyrics {
+            let Some(Loading::Loaded(matches)) = lyrics_matches.get(lyrics.as_ref()) else {
+                return bad;
+            };
 
-        if let Some(title) = &query.title {
-            let new_score = fuzzy::compare(self.title.chars(), title.chars());
-            score = max(score, new_score);
-        }
+            let Some(lyrics_match) = matches.get(&self.song_hash) else {
+                return bad;
+            };
 
-        if let Some(artist) = &query.artist {
-            let new_score = fuzzy::compare(self.artist.chars(), artist.chars());
-            score = max(score, new_score);
+            score = max(score, lyrics_match.score);
         }
 
         score
     }
 }
+
+impl Searchable for Song {
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn artist(&self) -> &str {
+        &self.artist
+    }
+
+    fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    fn genre(&self) -> Option<&str> {
+        self.genre.as_deref()
+    }
+
+    fn year(&self) -> Option<&str> {
+        self.year.as_deref()
+    }
+
+    fn bpm(&self) -> &str {
+        &self.bpm
+    }
+
+    fn has_video(&self) -> bool {
+        self.video.is_some()
+    }
+
+    fn is_duet(&self) -> bool {
+        self.duet().is_some()
+    }
+}
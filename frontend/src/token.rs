@@ -0,0 +1,71 @@
+use gloo_console::error;
+use gloo_net::http::Request;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::Msg,
+    fetch::{fetch_list_of, send, FetchError},
+};
+
+/// A personal access token, that the user's scripts can send instead of logging in.
+#[derive(Deserialize, Debug, Clone)]
+pub struct PersonalToken {
+    pub id: i32,
+    pub name: String,
+
+    /// When the token was last used, in UTC.
+    pub last_used_at: Option<String>,
+
+    /// When the token stops working, in UTC.
+    pub expires_at: String,
+}
+
+pub async fn fetch_personal_tokens() -> Option<Msg> {
+    let tokens = match fetch_list_of("/me/tokens").await {
+        Ok(response) => response,
+        Err(e) => {
+            error!("Failed fetching personal access tokens:", e);
+            return None;
+        }
+    };
+
+    Some(Msg::PersonalTokens(tokens))
+}
+
+pub async fn create_personal_token(name: String) -> Option<Msg> {
+    #[derive(Serialize)]
+    struct NewToken {
+        name: String,
+    }
+
+    #[derive(Deserialize)]
+    struct CreatedToken {
+        token: String,
+    }
+
+    let result = async {
+        let response = send(Request::post("/me/tokens").json(&NewToken { name })?).await?;
+        Ok::<CreatedToken, FetchError>(response.json().await?)
+    };
+
+    match result.await {
+        Ok(created) => Some(Msg::PersonalTokenCreated(created.token)),
+        Err(e) => {
+            error!("Error creating personal access token:", e);
+            None
+        }
+    }
+}
+
+pub async fn revoke_personal_token(id: i32) -> Option<Msg> {
+    let result = async {
+        let request = Request::delete(&format!("/me/tokens/{id}")).build()?;
+        send(request).await
+    };
+
+    if let Err(e) = result.await {
+        error!("Error revoking personal access token:", e);
+    }
+
+    fetch_personal_tokens().await
+}
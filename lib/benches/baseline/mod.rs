@@ -0,0 +1,209 @@
+//! How the web UI searched before it had a [SearchIndex](singit_lib::index::SearchIndex):
+//! `update_song_list` parsed the query and scored every song with `Song::fuzzy_compare` on every
+//! keystroke, and then sorted them. Copied from the web UI, without custom lists.
+
+use std::borrow::Cow;
+use std::cmp::{max, Reverse};
+use std::ops::Not;
+
+use singit_lib::search::Searchable;
+
+pub type FuzzyScore = i32;
+
+#[derive(Default)]
+pub struct ParsedQuery<'a> {
+    /// Unspecified query (fuzzy).
+    pub plain: Option<Cow<'a, str>>,
+
+    /// Query a specific title (fuzzy).
+    pub title: Option<Cow<'a, str>>,
+
+    /// Query a specific artist (fuzzy).
+    pub artist: Option<Cow<'a, str>>,
+
+    /// Whether the song is a duet (filter).
+    pub duet: Option<bool>,
+
+    /// Whether the song has a video (filter).
+    pub video: Option<bool>,
+
+    /// Query a specific language (filter).
+    pub language: Option<&'a str>,
+
+    /// Query a specific genre (filter).
+    pub genre: Option<&'a str>,
+
+    /// Query from a specifc year (filter).
+    pub year: Option<&'a str>,
+}
+
+impl<'a> ParsedQuery<'a> {
+    pub fn parse(s: &'a str) -> Self {
+        if s.is_empty() {
+            return Default::default();
+        }
+
+        let mut parsed = ParsedQuery {
+            plain: extract_plain(s),
+            ..Default::default()
+        };
+
+        let kvs = extract_key_values(s);
+
+        for (k, v) in kvs {
+            match k {
+                "title" => parsed.title = Some(Cow::Borrowed(v)),
+                "artist" => parsed.artist = Some(Cow::Borrowed(v)),
+                "duet" => parsed.duet = parse_bool(v),
+                "video" => parsed.video = parse_bool(v),
+                "lang" => parsed.language = Some(v),
+                "genre" => parsed.genre = Some(v),
+                "year" => parsed.year = Some(v),
+                _ => {}
+            }
+        }
+
+        parsed
+    }
+
+    /// Whether the query contains any "fuzzy" query parameters.
+    pub fn has_fuzzy_parameters(&self) -> bool {
+        [&self.plain, &self.title, &self.artist]
+            .iter()
+            .any(|p| p.is_some())
+    }
+}
+
+fn parse_bool(s: &str) -> Option<bool> {
+    match s {
+        "true" | "yes" | "y" => Some(true),
+        "false" | "no" | "n" => Some(false),
+        _ => None,
+    }
+}
+
+fn extract_plain(s: &str) -> Option<Cow<'_, str>> {
+    let plain: String =
+        s.split(' ')
+            .filter(|word| !word.contains(':'))
+            .fold(String::new(), |mut a, b| {
+                if !a.is_empty() {
+                    a.push(' ');
+                }
+                a.push_str(b);
+                a
+            });
+
+    plain.is_empty().not().then_some(Cow::Owned(plain))
+}
+
+fn extract_key_values(s: &str) -> impl Iterator<Item = (&str, &str)> {
+    s.split_whitespace().filter_map(|s| s.split_once(':'))
+}
+
+/// Compare a base string to a user-input search
+pub fn compare<B, S>(base: B, search: S) -> FuzzyScore
+where
+    B: Iterator<Item = char> + Clone,
+    S: IntoIterator<Item = char>,
+{
+    let mut base = base.into_iter().enumerate();
+
+    let mut score = 0;
+
+    for sc in search {
+        let sc = sc.to_ascii_lowercase();
+        let mut add = 3;
+        let mut base_tmp = base.clone();
+        while let Some((_j, bc)) = base_tmp.next() {
+            let bc = bc.to_ascii_lowercase();
+            if bc == sc {
+                score += add;
+                base = base_tmp;
+                break;
+            } else {
+                add = 2;
+            }
+        }
+    }
+
+    score
+}
+
+pub fn max_score(query: &str) -> FuzzyScore {
+    compare(query.chars(), query.chars())
+}
+
+pub fn fuzzy_compare(song: &impl Searchable, query: &ParsedQuery) -> FuzzyScore {
+    let bad: FuzzyScore = -1;
+
+    let filter_strs = |query: Option<&str>, item: Option<&str>| {
+        if let Some(query) = query {
+            match item {
+                Some(item) => {
+                    let query_no_whitespace = query.replace(char::is_whitespace, "");
+                    let item_no_whitespace = item.replace(char::is_whitespace, "");
+                    let score = compare(item_no_whitespace.chars(), query_no_whitespace.chars());
+                    score == max_score(&query_no_whitespace)
+                }
+                None => false,
+            }
+        } else {
+            true
+        }
+    };
+
+    let filter_bool = |query: Option<bool>, item| !matches!(query, Some(query) if query != item);
+
+    let filters: &[&dyn Fn() -> bool] = &[
+        &|| filter_bool(query.duet, song.is_duet()),
+        &|| filter_bool(query.video, song.has_video()),
+        &|| filter_strs(query.language, song.language()),
+        &|| filter_strs(query.genre, song.genre()),
+        &|| filter_strs(query.year, song.year()),
+    ];
+
+    if !filters.iter().all(|f| f()) {
+        return bad;
+    }
+
+    let mut score = FuzzyScore::default();
+    if let Some(plain) = &query.plain {
+        let title_score = compare(song.title().chars(), plain.chars());
+        let artist_score = compare(song.artist().chars(), plain.chars());
+        score = max(title_score, artist_score);
+    }
+
+    if let Some(title) = &query.title {
+        let new_score = compare(song.title().chars(), title.chars());
+        score = max(score, new_score);
+    }
+
+    if let Some(artist) = &query.artist {
+        let new_score = compare(song.artist().chars(), artist.chars());
+        score = max(score, new_score);
+    }
+
+    score
+}
+
+/// Score every song for a query, and sort them by their scores. `songs` holds the scores and the
+/// indices of the songs, and is kept between searches.
+pub fn update_song_list(
+    songs: &mut [(Reverse<FuzzyScore>, usize)],
+    library: &[impl Searchable],
+    query: &str,
+) {
+    let query = ParsedQuery::parse(query);
+
+    for (score, song) in songs.iter_mut() {
+        *score = Reverse(fuzzy_compare(&library[*song], &query));
+    }
+
+    if query.has_fuzzy_parameters() {
+        songs.sort_unstable();
+    } else {
+        // the web UI shuffles the songs that aren't hidden instead, which costs about as much
+        songs.sort_unstable_by_key(|(score, _)| *score);
+    }
+}
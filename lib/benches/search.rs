@@ -0,0 +1,171 @@
+//! Compares searching with a [SearchIndex] to how the web UI searched before it had one, see
+//! [baseline], on a generated library and queries typed one char at a time.
+//!
+//! Run with `cargo bench -p singit_lib`.
+
+use std::cmp::Reverse;
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use singit_lib::fuzzy::FuzzyScore;
+use singit_lib::index::SearchIndex;
+use singit_lib::query::ParsedQuery;
+use singit_lib::search::Searchable;
+
+mod baseline;
+
+/// About twice as many songs as the song list of the division.
+const SONGS: usize = 5000;
+
+const WORDS: &str = "love dancing queen night heart fire dream summer baby rock roll mamma mia \
+    bohemian rhapsody don't stop me now take on the a of in my you forever young wonderwall sång \
+    kärlek hjärta natt sommar Crüe Beyoncé Alizée Björk Mötley café mañana abba";
+
+const LANGUAGES: &str = "English Swedish Japanese Spanish French Korean";
+
+const GENRES: &str = "Pop Rock Anime Schlager R&B Disco Metal Gyckel";
+
+struct Song {
+    title: String,
+    artist: String,
+    language: Option<String>,
+    genre: Option<String>,
+    year: Option<String>,
+    bpm: String,
+    video: bool,
+    duet: bool,
+}
+
+impl Searchable for Song {
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn artist(&self) -> &str {
+        &self.artist
+    }
+
+    fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    fn genre(&self) -> Option<&str> {
+        self.genre.as_deref()
+    }
+
+    fn year(&self) -> Option<&str> {
+        self.year.as_deref()
+    }
+
+    fn bpm(&self) -> &str {
+        &self.bpm
+    }
+
+    fn has_video(&self) -> bool {
+        self.video
+    }
+
+    fn is_duet(&self) -> bool {
+        self.duet
+    }
+}
+
+/// Xorshift, so that the generated songs are the same every time.
+struct Random(u64);
+
+impl Random {
+    /// A number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 as usize % n
+    }
+
+    /// Up to `max` of the words in a list.
+    fn words(&mut self, words: &str, max: usize) -> String {
+        let words: Vec<&str> = words.split_whitespace().collect();
+        let count = 1 + self.below(max);
+        let picked: Vec<&str> = (0..count).map(|_| words[self.below(words.len())]).collect();
+        picked.join(" ")
+    }
+}
+
+/// Generate a library of songs.
+fn songs() -> Vec<Song> {
+    let mut random = Random(0x2545_f491_4f6c_dd1d);
+
+    (0..SONGS)
+        .map(|_| Song {
+            title: random.words(WORDS, 5),
+            artist: random.words(WORDS, 3),
+            language: (random.below(4) > 0).then(|| random.words(LANGUAGES, 1)),
+            genre: (random.below(3) > 0).then(|| random.words(GENRES, 1)),
+            year: (random.below(3) > 0).then(|| (1960 + random.below(65)).to_string()),
+            bpm: format!("{},{}", 80 + random.below(420), random.below(100)),
+            video: random.below(5) == 0,
+            duet: random.below(10) == 0,
+        })
+        .collect()
+}
+
+/// Every prefix of a query, like when it's typed into the search field.
+fn typed(query: &str) -> Vec<&str> {
+    let ends = query.char_indices().map(|(i, c)| i + c.len_utf8());
+    ends.map(|end| &query[..end]).collect()
+}
+
+fn bench_search(c: &mut Criterion) {
+    let songs = songs();
+
+    c.bench_function("build index", |b| {
+        b.iter(|| SearchIndex::new(black_box(&songs)))
+    });
+
+    // queries that mean the same with the query language of the baseline
+    let queries = [
+        "bohemain rhapsody",
+        "abba lang:english",
+        "dancing queen genre:pop",
+        "motley crue duet:yes",
+    ];
+
+    for query in queries {
+        let typed = typed(query);
+
+        let mut order: Vec<(Reverse<FuzzyScore>, usize)> =
+            (0..songs.len()).map(|i| (Reverse(0), i)).collect();
+        c.bench_function(&format!("type {query:?} / baseline"), |b| {
+            b.iter(|| {
+                for query in &typed {
+                    baseline::update_song_list(&mut order, &songs, query);
+                    black_box(&order);
+                }
+            })
+        });
+
+        // like the web UI, which also scores custom lists and lyrics, but not in this benchmark
+        let mut index = SearchIndex::new(&songs);
+        c.bench_function(&format!("type {query:?} / search index"), |b| {
+            b.iter(|| {
+                for query in &typed {
+                    let query = ParsedQuery::parse(query);
+                    let scores = index.scores(&query);
+                    for (score, song) in order.iter_mut() {
+                        *score = Reverse(scores[*song].unwrap_or(-1));
+                    }
+
+                    if query.has_fuzzy_parameters() {
+                        order.sort_unstable();
+                    } else {
+                        order.sort_unstable_by_key(|(score, _)| *score);
+                    }
+                    black_box(&order);
+                }
+            })
+        });
+    }
+}
+
+criterion_group!(benches, bench_search);
+criterion_main!(benches);
@@ -0,0 +1,378 @@
+//! Fuzzy matching of user-input searches against song titles, artists and the like.
+//!
+//! Strings are folded before they're compared, so that case and diacritics don't matter, e.g.
+//! "alizee" matches "Alizée" and "motley crue" matches "Mötley Crüe". The search is then aligned
+//! with the base string, where its chars have to appear in order but not necessarily next to each
+//! other. Chars that come right after the previous match, or at the start of a word, score extra.
+//! A typo (a wrong, missing, extra or transposed char) costs a little, but doesn't break up a run
+//! of matching chars.
+
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+pub type FuzzyScore = i32;
+
+/// The score of a matched char.
+const MATCH: FuzzyScore = 2;
+
+/// The extra score of a char that was matched right after the previous one.
+const CONSECUTIVE_BONUS: FuzzyScore = 2;
+
+/// The extra score of a char that was matched at the start of a word.
+const WORD_START_BONUS: FuzzyScore = 2;
+
+/// The cost of a typo in a run of matching chars. Less than [CONSECUTIVE_BONUS], so that a run
+/// with a typo in it scores more than two runs.
+const TYPO_PENALTY: FuzzyScore = 1;
+
+/// The score of an impossible alignment, low enough to not overflow when added to.
+const IMPOSSIBLE: FuzzyScore = FuzzyScore::MIN / 2;
+
+/// How a search was matched with a base string.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: FuzzyScore,
+
+    /// The indices of the chars in the base string that matched chars of the search, in order.
+    pub positions: Vec<usize>,
+}
+
+/// A string folded for comparison, to lowercase without diacritics.
+///
+/// Folding is a large part of comparing short strings, so strings that are compared many times,
+/// e.g. by a [SearchIndex](crate::index::SearchIndex), can be folded once.
+#[derive(Debug, Clone)]
+pub struct Folded {
+    chars: Vec<char>,
+
+    /// The index of the char in the original string that each folded char comes from.
+    sources: Vec<usize>,
+}
+
+/// Compare a base string to a user-input search
+///
+/// A search that doesn't match at all scores 0, and one that matches better scores more.
+pub fn compare(base: &str, search: &str) -> FuzzyScore {
+    compare_folded(&Folded::new(base), &Folded::new(search))
+}
+
+/// Compare a folded base string to a folded search, like [compare].
+pub fn compare_folded(base: &Folded, search: &Folded) -> FuzzyScore {
+    score(&base.chars, &search.chars)
+}
+
+/// Compare a base string to a user-input search, like [compare], and find the chars that matched.
+pub fn find(base: &str, search: &str) -> FuzzyMatch {
+    let base = Folded::new(base);
+    let mut found = align(&base, &Folded::new(search));
+
+    // chars that fold into more than one char, like "æ", may have matched more than once
+    found.positions = found.positions.iter().map(|&i| base.sources[i]).collect();
+    found.positions.dedup();
+
+    found
+}
+
+/// Whether a base string starts with a search, ignoring whitespace, case and diacritics.
+pub fn starts_with(base: &str, search: &str) -> bool {
+    let without_whitespace = |s: &str| -> Vec<char> {
+        let folded = Folded::new(s).chars;
+        folded.into_iter().filter(|c| !c.is_whitespace()).collect()
+    };
+
+    without_whitespace(base).starts_with(&without_whitespace(search))
+}
+
+/// Whether a base string contains a search, ignoring case and diacritics.
+pub fn contains(base: &str, search: &str) -> bool {
+    Folded::new(base).contains(&Folded::new(search))
+}
+
+impl Folded {
+    pub fn new(s: &str) -> Self {
+        let mut folded = Folded {
+            chars: Vec::with_capacity(s.len()),
+            sources: Vec::with_capacity(s.len()),
+        };
+
+        for (i, c) in s.chars().enumerate() {
+            if c.is_ascii() {
+                folded.chars.push(c.to_ascii_lowercase());
+                folded.sources.push(i);
+                continue;
+            }
+
+            for c in c.nfd().filter(|&c| !is_combining_mark(c)) {
+                // letters that aren't a base letter with a diacritic, but are written like one
+                let replacement = match c {
+                    'ß' | 'ẞ' => Some("ss"),
+                    'æ' | 'Æ' => Some("ae"),
+                    'œ' | 'Œ' => Some("oe"),
+                    'þ' | 'Þ' => Some("th"),
+                    'ø' | 'Ø' => Some("o"),
+                    'đ' | 'Đ' | 'ð' | 'Ð' => Some("d"),
+                    'ł' | 'Ł' => Some("l"),
+                    'ı' => Some("i"),
+                    _ => None,
+                };
+
+                let mut push = |c| {
+                    folded.chars.push(c);
+                    folded.sources.push(i);
+                };
+                match replacement {
+                    Some(replacement) => replacement.chars().for_each(&mut push),
+                    None => c.to_lowercase().for_each(&mut push),
+                }
+            }
+        }
+
+        folded
+    }
+
+    pub fn chars(&self) -> &[char] {
+        &self.chars
+    }
+
+    /// Whether the string contains a folded search.
+    pub fn contains(&self, search: &Folded) -> bool {
+        search.chars.is_empty()
+            || (self.chars.windows(search.chars.len())).any(|window| window == search.chars)
+    }
+}
+
+/// Find the best alignment of a search with a base string.
+fn align(base: &Folded, search: &Folded) -> FuzzyMatch {
+    let alignment = Alignment::new(&base.chars, &search.chars);
+
+    let end = State {
+        i: search.chars.len(),
+        j: base.chars.len(),
+        run: alignment.score(search.chars.len(), base.chars.len(), true)
+            > alignment.score(search.chars.len(), base.chars.len(), false),
+    };
+
+    FuzzyMatch {
+        score: alignment.score(end.i, end.j, end.run),
+        positions: alignment.positions(end),
+    }
+}
+
+/// The score of the best alignment of a search with a base string.
+///
+/// This takes the same steps as [Alignment], but only keeps the rows of it that later rows are
+/// built from, since comparing is most of searching a large song list.
+fn score(base: &[char], search: &[char]) -> FuzzyScore {
+    let width = base.len() + 1;
+
+    // the free and run scores of the rows `i - 2`, `i - 1` and `i`, one after another
+    let mut rows = vec![IMPOSSIBLE; 6 * width];
+    let row = |i: usize, run: bool| (2 * (i % 3) + run as usize) * width;
+
+    for i in 0..=search.len() {
+        for j in 0..=base.len() {
+            let at = |rows: &[FuzzyScore], i: usize, j: usize, run: bool| rows[row(i, run) + j];
+
+            // ignore a char of the search, or skip a char of the base
+            let mut free = if (i, j) == (0, 0) { 0 } else { IMPOSSIBLE };
+            for run in [false, true] {
+                if i > 0 {
+                    free = free.max(at(&rows, i - 1, j, run));
+                }
+                if j > 0 {
+                    free = free.max(at(&rows, i, j - 1, run));
+                }
+            }
+
+            let mut run = IMPOSSIBLE;
+
+            // an extra or a missing char in the search
+            if i > 0 {
+                run = run.max(at(&rows, i - 1, j, true) - TYPO_PENALTY);
+            }
+            if j > 0 {
+                run = run.max(at(&rows, i, j - 1, true) - TYPO_PENALTY);
+            }
+
+            if i > 0 && j > 0 {
+                let (i, j) = (i - 1, j - 1);
+                if search[i] == base[j] {
+                    let word_start = j == 0 || !base[j - 1].is_alphanumeric();
+                    let score = MATCH + if word_start { WORD_START_BONUS } else { 0 };
+                    run = run.max(at(&rows, i, j, false) + score);
+                    run = run.max(at(&rows, i, j, true) + score + CONSECUTIVE_BONUS);
+                } else {
+                    // a wrong char in the search
+                    run = run.max(at(&rows, i, j, true) - TYPO_PENALTY);
+                }
+            }
+
+            let transposed = i > 1
+                && j > 1
+                && search[i - 1] != search[i - 2]
+                && search[i - 1] == base[j - 2]
+                && search[i - 2] == base[j - 1];
+            if transposed {
+                let score = 2 * MATCH + CONSECUTIVE_BONUS - TYPO_PENALTY;
+                let before = at(&rows, i - 2, j - 2, false).max(at(&rows, i - 2, j - 2, true));
+                run = run.max(before + score);
+            }
+
+            rows[row(i, false) + j] = free;
+            rows[row(i, true) + j] = run;
+        }
+    }
+
+    let i = search.len();
+    rows[row(i, false) + base.len()].max(rows[row(i, true) + base.len()])
+}
+
+/// The best scores of aligning the start of a search with the start of a base string.
+///
+/// This is dynamic programming over the number of chars of the search (`i`) and base (`j`) that
+/// have been aligned, and whether the last aligned chars were part of a run.
+struct Alignment<'a> {
+    base: &'a [char],
+    search: &'a [char],
+
+    /// The scores of states that aren't in a run, laid out as `[i][j]`.
+    free: Vec<FuzzyScore>,
+
+    /// The scores of states in a run, laid out as `[i][j]`.
+    run: Vec<FuzzyScore>,
+}
+
+#[derive(Clone, Copy)]
+struct State {
+    i: usize,
+    j: usize,
+    run: bool,
+}
+
+impl<'a> Alignment<'a> {
+    fn new(base: &'a [char], search: &'a [char]) -> Self {
+        let states = (search.len() + 1) * (base.len() + 1);
+        let mut alignment = Alignment {
+            base,
+            search,
+            free: vec![IMPOSSIBLE; states],
+            run: vec![IMPOSSIBLE; states],
+        };
+
+        alignment.free[0] = 0;
+
+        for i in 0..=search.len() {
+            for j in 0..=base.len() {
+                for run in [false, true] {
+                    let mut best = alignment.score(i, j, run);
+                    alignment.steps(State { i, j, run }, |from, add, _| {
+                        best = best.max(alignment.score(from.i, from.j, from.run) + add);
+                    });
+
+                    let k = alignment.index(i, j);
+                    match run {
+                        false => alignment.free[k] = best,
+                        true => alignment.run[k] = best,
+                    }
+                }
+            }
+        }
+
+        alignment
+    }
+
+    fn index(&self, i: usize, j: usize) -> usize {
+        i * (self.base.len() + 1) + j
+    }
+
+    fn score(&self, i: usize, j: usize, run: bool) -> FuzzyScore {
+        match run {
+            false => self.free[self.index(i, j)],
+            true => self.run[self.index(i, j)],
+        }
+    }
+
+    /// Call `step` with every step that leads to a state, with the state that it's taken from, the
+    /// score that it adds, and the indices of the chars in the base that it matches.
+    fn steps(&self, to: State, mut step: impl FnMut(State, FuzzyScore, &[usize])) {
+        let State { i, j, run } = to;
+        let (base, search) = (self.base, self.search);
+
+        if !run {
+            // ignore a char of the search, or skip a char of the base
+            for run in [false, true] {
+                if i > 0 {
+                    step(State { i: i - 1, j, run }, 0, &[]);
+                }
+                if j > 0 {
+                    step(State { i, j: j - 1, run }, 0, &[]);
+                }
+            }
+            return;
+        }
+
+        // an extra char in the search
+        if i > 0 {
+            step(State { i: i - 1, j, run }, -TYPO_PENALTY, &[]);
+        }
+
+        // a missing char in the search
+        if j > 0 {
+            step(State { i, j: j - 1, run }, -TYPO_PENALTY, &[]);
+        }
+
+        if i > 0 && j > 0 {
+            let (i, j) = (i - 1, j - 1);
+            if search[i] == base[j] {
+                let word_start = j == 0 || !base[j - 1].is_alphanumeric();
+                let score = MATCH + if word_start { WORD_START_BONUS } else { 0 };
+                step(State { i, j, run: false }, score, &[j]);
+                step(State { i, j, run: true }, score + CONSECUTIVE_BONUS, &[j]);
+            } else {
+                // a wrong char in the search
+                step(State { i, j, run: true }, -TYPO_PENALTY, &[]);
+            }
+        }
+
+        let transposed = i > 1
+            && j > 1
+            && search[i - 1] != search[i - 2]
+            && search[i - 1] == base[j - 2]
+            && search[i - 2] == base[j - 1];
+        if transposed {
+            let score = 2 * MATCH + CONSECUTIVE_BONUS - TYPO_PENALTY;
+            for run in [false, true] {
+                step(
+                    State {
+                        i: i - 2,
+                        j: j - 2,
+                        run,
+                    },
+                    score,
+                    &[j - 2, j - 1],
+                );
+            }
+        }
+    }
+
+    /// Find the chars of the base that were matched on the best way to a state.
+    fn positions(&self, mut state: State) -> Vec<usize> {
+        let mut positions = vec![];
+
+        while state.i > 0 || state.j > 0 {
+            let score = self.score(state.i, state.j, state.run);
+
+            let mut previous = None;
+            self.steps(state, |from, add, matched| {
+                if previous.is_none() && self.score(from.i, from.j, from.run) + add == score {
+                    positions.extend(matched.iter().rev());
+                    previous = Some(from);
+                }
+            });
+
+            state = previous.expect("every state but the first is reached by some step");
+        }
+
+        positions.reverse();
+        positions
+    }
+}
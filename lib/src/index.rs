@@ -0,0 +1,591 @@
+//! An index of songs, to search them as the user types without rescoring and refiltering every
+//! song from scratch on every keystroke.
+//!
+//! Titles and artists are folded once, when the index is built. Fuzzy searches are only compared to
+//! the songs that contain the [NGRAM] chars long parts of them. Each part narrows the songs down,
+//! unless fewer than [MIN_CANDIDATES] songs would be left, since then it probably has a typo in it.
+//! While a search is typed, the songs of what was typed before are narrowed down further.
+//!
+//! Filters are evaluated once per distinct language or genre and combined as bitsets, and the fuzzy
+//! scores of the last search are kept while only its filters change, e.g. while typing
+//! `lang:english` after a title.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::fuzzy::{self, Folded, FuzzyScore};
+use crate::query::{parse_number, Filter, NumberRange, ParsedQuery, TextField};
+use crate::search::Searchable;
+
+/// The length of the n-grams that songs are looked up by.
+const NGRAM: usize = 3;
+
+type Ngram = [char; NGRAM];
+
+/// The fewest songs that an n-gram of a fuzzy search narrows the songs that it's compared to down
+/// to.
+const MIN_CANDIDATES: usize = 20;
+
+#[derive(Default)]
+pub struct SearchIndex {
+    titles: Vec<Folded>,
+    artists: Vec<Folded>,
+
+    /// The songs whose title or artist contains each n-gram, in order.
+    ngrams: HashMap<Ngram, Vec<u32>>,
+
+    duets: Bitset,
+    videos: Bitset,
+
+    /// The songs with each language.
+    languages: Vec<(String, Bitset)>,
+
+    /// The songs with each genre.
+    genres: Vec<(String, Bitset)>,
+
+    years: Vec<Option<f64>>,
+    bpms: Vec<Option<f64>>,
+
+    /// The fuzzy scores of the last search, as far as they've been needed.
+    last_search: Option<(FuzzySearches, Vec<Option<FuzzyScore>>)>,
+
+    /// The songs that each fuzzy search of the last search was compared to.
+    last_candidates: Vec<(Vec<char>, Bitset)>,
+}
+
+/// The fuzzy parameters of a query, by what they're compared to.
+#[derive(PartialEq)]
+struct FuzzySearches {
+    /// Searches in titles: the plain query and the titles.
+    titles: Vec<String>,
+
+    /// Searches in artists: the plain query and the artists.
+    artists: Vec<String>,
+}
+
+/// A set of songs, by their position in the index.
+#[derive(Clone, Default)]
+struct Bitset {
+    words: Vec<u64>,
+}
+
+impl SearchIndex {
+    /// Index some songs. They're identified by their position in `songs`.
+    pub fn new<'s, S: Searchable + 's>(songs: impl IntoIterator<Item = &'s S>) -> Self {
+        let songs: Vec<&S> = songs.into_iter().collect();
+        let len = songs.len();
+
+        let mut index = SearchIndex {
+            titles: Vec::with_capacity(len),
+            artists: Vec::with_capacity(len),
+            ngrams: HashMap::new(),
+            duets: Bitset::empty(len),
+            videos: Bitset::empty(len),
+            languages: vec![],
+            genres: vec![],
+            years: Vec::with_capacity(len),
+            bpms: Vec::with_capacity(len),
+            last_search: None,
+            last_candidates: vec![],
+        };
+
+        let mut languages: HashMap<&str, Bitset> = HashMap::new();
+        let mut genres: HashMap<&str, Bitset> = HashMap::new();
+
+        for (i, song) in songs.into_iter().enumerate() {
+            let title = Folded::new(song.title());
+            let artist = Folded::new(song.artist());
+
+            let mut ngrams: Vec<Ngram> = ngrams(title.chars())
+                .chain(ngrams(artist.chars()))
+                .collect();
+            ngrams.sort_unstable();
+            ngrams.dedup();
+            for ngram in ngrams {
+                index.ngrams.entry(ngram).or_default().push(i as u32);
+            }
+
+            index.titles.push(title);
+            index.artists.push(artist);
+
+            if song.is_duet() {
+                index.duets.insert(i);
+            }
+            if song.has_video() {
+                index.videos.insert(i);
+            }
+
+            if let Some(language) = song.language() {
+                let songs = languages.entry(language);
+                songs.or_insert_with(|| Bitset::empty(len)).insert(i);
+            }
+            if let Some(genre) = song.genre() {
+                let songs = genres.entry(genre);
+                songs.or_insert_with(|| Bitset::empty(len)).insert(i);
+            }
+
+            index.years.push(song.year().and_then(parse_number));
+            index.bpms.push(parse_number(song.bpm()));
+        }
+
+        let owned = |values: HashMap<&str, Bitset>| {
+            let values = values.into_iter();
+            values
+                .map(|(value, songs)| (value.to_string(), songs))
+                .collect()
+        };
+        index.languages = owned(languages);
+        index.genres = owned(genres);
+
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.titles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.titles.is_empty()
+    }
+
+    /// Match every song against the filters of a query, and score them by its fuzzy parameters.
+    ///
+    /// Returns the scores in the order that the songs were indexed, the same as
+    /// [search::score](crate::search::score) would, except that fuzzy searches score 0 for the
+    /// songs that they aren't compared to, see the [module docs](self).
+    pub fn scores(&mut self, query: &ParsedQuery) -> Vec<Option<FuzzyScore>> {
+        let filtered = self.filter(query);
+
+        let with_plain = |searches: &[Cow<str>]| -> Vec<String> {
+            let searches = query.plain.iter().chain(searches);
+            searches.map(|search| search.to_string()).collect()
+        };
+        let searches = FuzzySearches {
+            titles: with_plain(&query.title),
+            artists: with_plain(&query.artist),
+        };
+
+        let mut last_search = match self.last_search.take() {
+            Some((last, scores)) if last == searches => (last, scores),
+            _ => (searches, vec![None; self.len()]),
+        };
+
+        let (searches, cached) = &mut last_search;
+        let last_candidates = std::mem::take(&mut self.last_candidates);
+        let mut fold = |searches: &[String]| -> Vec<(Folded, Option<Bitset>)> {
+            let searches = searches.iter().map(|search| Folded::new(search));
+            searches
+                .map(|search| {
+                    let candidates = self.candidates(&search, &last_candidates);
+                    (search, candidates)
+                })
+                .collect()
+        };
+        let title_searches = fold(&searches.titles);
+        let artist_searches = fold(&searches.artists);
+
+        let best = |base: &Folded, searches: &[(Folded, Option<Bitset>)], i| {
+            let searches = searches.iter();
+            let candidates = searches.filter(|(_, c)| c.as_ref().is_none_or(|c| c.contains(i)));
+            let scores = candidates.map(|(search, _)| fuzzy::compare_folded(base, search));
+            scores.max().unwrap_or_default()
+        };
+
+        let scores = (0..self.len())
+            .map(|i| {
+                if !filtered.contains(i) {
+                    return None;
+                }
+
+                let score = *cached[i].get_or_insert_with(|| {
+                    let title = best(&self.titles[i], &title_searches, i);
+                    let artist = best(&self.artists[i], &artist_searches, i);
+                    title.max(artist)
+                });
+
+                Some(score)
+            })
+            .collect();
+
+        self.last_search = Some(last_search);
+        scores
+    }
+
+    /// The songs that a fuzzy search is compared to, or `None` if it's too short to have n-grams and
+    /// is compared to every song.
+    ///
+    /// Also remembers them, for the next search.
+    fn candidates(
+        &mut self,
+        search: &Folded,
+        last_candidates: &[(Vec<char>, Bitset)],
+    ) -> Option<Bitset> {
+        let search = search.chars();
+        ngrams(search).next()?;
+
+        // while a search is typed, narrow down the songs of what was typed before
+        let typed_before = last_candidates
+            .iter()
+            .find(|(last, _)| search.starts_with(last) && ngrams(last).next().is_some());
+        let (mut candidates, new_ngrams) = match typed_before {
+            Some((last, candidates)) => {
+                let new_chars = &search[(last.len() + 1).saturating_sub(NGRAM)..];
+                (candidates.clone(), ngrams(new_chars))
+            }
+            None => (Bitset::full(self.len()), ngrams(search)),
+        };
+
+        for ngram in new_ngrams {
+            let mut narrowed = Bitset::empty(self.len());
+            for &i in self.ngrams.get(&ngram).into_iter().flatten() {
+                narrowed.insert(i as usize);
+            }
+            narrowed.intersect_with(&candidates);
+
+            // otherwise the n-gram probably has a typo in it
+            if narrowed.len() >= MIN_CANDIDATES {
+                candidates = narrowed;
+            }
+        }
+
+        self.last_candidates
+            .push((search.to_vec(), candidates.clone()));
+        Some(candidates)
+    }
+
+    /// Find the songs that pass the filters of a query.
+    fn filter(&self, query: &ParsedQuery) -> Bitset {
+        let len = self.len();
+        let mut songs = Bitset::full(len);
+
+        let filter_bool = |songs: &mut Bitset, query: Option<bool>, matching: &Bitset| {
+            if let Some(query) = query {
+                let mut matching = matching.clone();
+                if !query {
+                    matching.invert(len);
+                }
+                songs.intersect_with(&matching);
+            }
+        };
+        filter_bool(&mut songs, query.duet, &self.duets);
+        filter_bool(&mut songs, query.video, &self.videos);
+
+        let values = [
+            (&query.language, &self.languages),
+            (&query.genre, &self.genres),
+        ];
+        for (filters, values) in values {
+            for filter in filters {
+                let mut matching = Bitset::empty(len);
+                for (value, value_songs) in values {
+                    let mut alternatives = filter.alternatives.iter();
+                    if alternatives.any(|alt| fuzzy::starts_with(value, alt)) {
+                        matching.union_with(value_songs);
+                    }
+                }
+                songs.intersect_with(&negate(filter, matching, len));
+            }
+        }
+
+        let numbers = [(&query.year, &self.years), (&query.bpm, &self.bpms)];
+        for (filters, numbers) in numbers {
+            for filter in filters {
+                let mut matching = Bitset::empty(len);
+                for (i, n) in numbers.iter().enumerate() {
+                    let in_range = |range: &NumberRange| n.is_some_and(|n| range.contains(n));
+                    if filter.alternatives.iter().any(in_range) {
+                        matching.insert(i);
+                    }
+                }
+                songs.intersect_with(&negate(filter, matching, len));
+            }
+        }
+
+        for exclusion in &query.excluded {
+            if exclusion.text.is_empty() {
+                continue;
+            }
+
+            let text = Folded::new(&exclusion.text);
+            for i in 0..len {
+                let title = || self.titles[i].contains(&text);
+                let artist = || self.artists[i].contains(&text);
+                let excluded = match exclusion.field {
+                    TextField::Any => title() || artist(),
+                    TextField::Title => title(),
+                    TextField::Artist => artist(),
+                };
+                if excluded {
+                    songs.remove(i);
+                }
+            }
+        }
+
+        songs
+    }
+}
+
+/// The n-grams of a folded string, except those with whitespace in them.
+fn ngrams(chars: &[char]) -> impl Iterator<Item = Ngram> + '_ {
+    let windows = chars.windows(NGRAM);
+    let windows = windows.filter(|window| !window.iter().any(|c| c.is_whitespace()));
+    windows.map(|window| window.try_into().expect("windows are NGRAM long"))
+}
+
+/// The songs that pass a filter, given the songs that match one of its alternatives.
+fn negate<T>(filter: &Filter<T>, mut matching: Bitset, len: usize) -> Bitset {
+    if filter.negated {
+        matching.invert(len);
+    }
+    matching
+}
+
+impl Bitset {
+    fn empty(len: usize) -> Self {
+        Bitset {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    fn full(len: usize) -> Self {
+        let mut set = Bitset::empty(len);
+        set.invert(len);
+        set
+    }
+
+    fn insert(&mut self, i: usize) {
+        self.words[i / 64] |= 1 << (i % 64);
+    }
+
+    fn remove(&mut self, i: usize) {
+        self.words[i / 64] &= !(1 << (i % 64));
+    }
+
+    fn contains(&self, i: usize) -> bool {
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    /// The number of songs in the set.
+    fn len(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    fn union_with(&mut self, other: &Bitset) {
+        for (word, other) in self.words.iter_mut().zip(&other.words) {
+            *word |= other;
+        }
+    }
+
+    fn intersect_with(&mut self, other: &Bitset) {
+        for (word, other) in self.words.iter_mut().zip(&other.words) {
+            *word &= other;
+        }
+    }
+
+    /// Invert the set, of songs `0..len`.
+    fn invert(&mut self, len: usize) {
+        for word in &mut self.words {
+            *word = !*word;
+        }
+
+        // clear the bits after the last song
+        let tail = len % 64;
+        if tail > 0 {
+            if let Some(last) = self.words.last_mut() {
+                *last &= (1 << tail) - 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search;
+
+    struct Song {
+        title: String,
+        artist: String,
+        language: Option<&'static str>,
+        year: Option<&'static str>,
+        duet: bool,
+    }
+
+    impl Searchable for Song {
+        fn title(&self) -> &str {
+            &self.title
+        }
+
+        fn artist(&self) -> &str {
+            &self.artist
+        }
+
+        fn language(&self) -> Option<&str> {
+            self.language
+        }
+
+        fn genre(&self) -> Option<&str> {
+            None
+        }
+
+        fn year(&self) -> Option<&str> {
+            self.year
+        }
+
+        fn bpm(&self) -> &str {
+            "120"
+        }
+
+        fn has_video(&self) -> bool {
+            false
+        }
+
+        fn is_duet(&self) -> bool {
+            self.duet
+        }
+    }
+
+    fn songs() -> Vec<Song> {
+        let song = |title: &str, artist: &str, language, year, duet| Song {
+            title: title.to_string(),
+            artist: artist.to_string(),
+            language,
+            year,
+            duet,
+        };
+
+        vec![
+            song(
+                "Toxic",
+                "Britney Spears",
+                Some("English"),
+                Some("2003"),
+                false,
+            ),
+            song("Hello", "Adele", Some("English"), Some("2015"), false),
+            song("Africa", "Toto", Some("English"), Some("1982"), false),
+            song("Dancing Queen", "ABBA", Some("English"), Some("1976"), true),
+            song("Bohemian Rhapsody", "Queen", None, Some("1975"), false),
+            song("Sommartider", "Gyllene Tider", Some("Swedish"), None, true),
+        ]
+    }
+
+    /// More songs than [MIN_CANDIDATES] that contain "love song".
+    fn love_songs() -> Vec<Song> {
+        let love_songs = (1..=MIN_CANDIDATES).map(|n| Song {
+            title: format!("Love Song {n}"),
+            artist: format!("Lover {n}"),
+            language: Some("English"),
+            year: None,
+            duet: false,
+        });
+        songs().into_iter().chain(love_songs).collect()
+    }
+
+    /// Check that the songs that were compared to a query scored like with [search::score], and
+    /// that the others only were because they don't contain the query, and scored 0.
+    fn assert_scores_like_search_score(songs: &[Song], scores: &[Option<FuzzyScore>], query: &str) {
+        let query = ParsedQuery::parse(query);
+        for (song, &score) in songs.iter().zip(scores) {
+            let expected = search::score(song, &query);
+            let not_compared = score == Some(0) && expected.is_some();
+            assert!(score == expected || not_compared, "{query}: {}", song.title);
+        }
+
+        let best = |scores: &[Option<FuzzyScore>]| (0..songs.len()).max_by_key(|&i| scores[i]);
+        let expected: Vec<_> = songs.iter().map(|s| search::score(s, &query)).collect();
+        assert_eq!(best(scores), best(&expected), "{query}");
+    }
+
+    #[test]
+    fn scores_match_search_score() {
+        let songs = love_songs();
+        let mut index = SearchIndex::new(&songs);
+
+        // typed one after another, so that the cached scores are used as the filters change
+        let queries = [
+            "tixoc",
+            "adlee",
+            "otto",
+            "abab",
+            "bohemain rhapsody",
+            "queen",
+            "queen lang:english",
+            "queen lang:english duet:yes",
+            "queen -artist:abba",
+            "title:hello|africa",
+            "year:1970..1990 -lang:swedish",
+            "love",
+            "love sogn",
+            "love lang:english",
+            "artist:lover -title:song",
+            "a",
+            "",
+        ];
+
+        for query in queries {
+            let scores = index.scores(&ParsedQuery::parse(query));
+            assert_scores_like_search_score(&songs, &scores, query);
+        }
+    }
+
+    #[test]
+    fn only_ngram_matches_are_compared() {
+        let songs = love_songs();
+        let mut index = SearchIndex::new(&songs);
+
+        let query = ParsedQuery::parse("love song");
+        let scores = index.scores(&query);
+        for (song, score) in songs.iter().zip(scores) {
+            if song.title.starts_with("Love Song") {
+                assert_eq!(score, search::score(song, &query));
+            } else {
+                assert_eq!(score, Some(0), "{}", song.title);
+            }
+        }
+    }
+
+    #[test]
+    fn typed_searches_narrow_like_new_ones() {
+        let songs = love_songs();
+        let mut index = SearchIndex::new(&songs);
+
+        let typed = "love song 1"
+            .char_indices()
+            .map(|(i, _)| &"love song 1"[..=i]);
+        let edited = [
+            "love sang",
+            "love sang 1",
+            "love",
+            "lover 1",
+            "bohemian",
+            "bohemain",
+            "love so",
+        ];
+        for query in typed.chain(edited) {
+            let query = ParsedQuery::parse(query);
+            let expected = SearchIndex::new(&songs).scores(&query);
+            assert_eq!(index.scores(&query), expected, "{query}");
+        }
+    }
+
+    #[test]
+    fn typos_match() {
+        let songs = love_songs();
+        let mut index = SearchIndex::new(&songs);
+
+        for (query, song) in [("tixoc", 0), ("adlee", 1), ("otto", 2), ("abab", 3)] {
+            let scores = index.scores(&ParsedQuery::parse(query));
+            let best = (0..songs.len()).max_by_key(|&i| scores[i]);
+            assert_eq!(
+                best,
+                Some(song),
+                "{query} should find {}",
+                songs[song].title
+            );
+        }
+    }
+}
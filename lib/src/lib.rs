@@ -1,20 +1,119 @@
+pub mod fuzzy;
+pub mod index;
+pub mod query;
+pub mod search;
+
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::fuzzy::FuzzyScore;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserInfo {
     pub cid: String,
     pub nick: String,
 }
 
-/// Response to `PUT /songs`
+/// Response to `PUT /songs` and `PATCH /songs`
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PutSongs {
     /// Number of songs that were not previously in the list.
     pub songs_added: usize,
 
-    /// Number of songs that were deleted from the list.
+    /// Number of songs that were removed from the list.
+    ///
+    /// They are kept for custom lists that contain them, and restored if they're added again.
     pub songs_deleted: usize,
 
     /// Number of songs that were already in the list, and *may* have had their metadata updated.
     pub songs_updated: usize,
 }
+
+/// Response to `PUT /songs?dry_run=true`, i.e. the changes that the upload would make.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SongsDiff {
+    /// Songs that are not in the list, and would be added.
+    pub added: Vec<DiffSong>,
+
+    /// Songs in the list that are missing from the upload, and would be removed.
+    pub removed: Vec<DiffSong>,
+
+    /// Songs in the list that would have some of their metadata changed.
+    pub changed: Vec<ChangedSong>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiffSong {
+    pub song_hash: String,
+    pub title: String,
+    pub artist: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangedSong {
+    #[serde(flatten)]
+    pub song: DiffSong,
+
+    pub changes: Vec<FieldChange>,
+}
+
+/// A field of a song that would be changed, e.g. `year` going from `"1999"` to `"2000"`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// A song as described to `POST /songs/sync`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SongDigest {
+    pub song_hash: String,
+
+    /// A hash of the metadata and cover of the song. Should change if anything about the song does.
+    pub content_hash: String,
+
+    /// A hash of the cover image, if the song has one.
+    pub cover_hash: Option<String>,
+}
+
+/// Response to `POST /songs/sync`, i.e. what the server needs to be in sync with the uploader.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SyncPlan {
+    /// Hashes of songs that are new or changed, and should be sent with `PATCH /songs`.
+    pub songs: Vec<String>,
+
+    /// Hashes of songs whose cover should be uploaded with `PUT /images/songs/{song_hash}`.
+    pub covers: Vec<String>,
+
+    /// Hashes of songs that the uploader doesn't have, and should be removed with `PATCH /songs`.
+    pub removed: Vec<String>,
+}
+
+/// Response to `GET /images/songs`, i.e. the song covers that are stored on the server.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CoverManifest {
+    /// The hash of the cover of every song that has one, by song hash.
+    ///
+    /// `None` for covers that were uploaded before the server stored them by hash.
+    pub covers: HashMap<String, Option<String>>,
+}
+
+/// Response to `GET /songs/search`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchResults<S> {
+    /// Number of songs that matched. `hits` only contains the requested page of them.
+    pub total: usize,
+
+    /// The matching songs, best match first.
+    pub hits: Vec<SearchHit<S>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchHit<S> {
+    /// How well the song matched the fuzzy parameters of the query. Higher is better.
+    pub score: FuzzyScore,
+
+    pub song: S,
+}
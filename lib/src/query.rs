@@ -0,0 +1,563 @@
+use std::borrow::Cow;
+use std::fmt::{self, Display, Formatter};
+
+/// A search query, e.g. `dancing artist:abba -genre:pop year:1975..1980`.
+///
+/// Plain words are searched for in titles and artists, and the other terms are `key:value`.
+/// Values can be quoted, e.g. `artist:"Earth, Wind & Fire"`, and terms negated with a leading
+/// `-`. Some keys take alternatives, e.g. `lang:english|swedish`, and `year` and `bpm` take ranges,
+/// e.g. `year:1980..1989`, `bpm:..100` or `year:1999`.
+///
+/// Terms that can't be interpreted, e.g. `year:abc`, `-list:3` or unknown keys, are searched for
+/// as plain text, just as they were written.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct ParsedQuery<'a> {
+    /// Unspecified query (fuzzy).
+    pub plain: Option<Cow<'a, str>>,
+
+    /// Query specific titles (fuzzy). The best matching alternative counts.
+    pub title: Vec<Cow<'a, str>>,
+
+    /// Query specific artists (fuzzy). The best matching alternative counts.
+    pub artist: Vec<Cow<'a, str>>,
+
+    /// Whether the song is a duet (filter).
+    pub duet: Option<bool>,
+
+    /// Whether the song has a video (filter).
+    pub video: Option<bool>,
+
+    /// Query specific languages (filter).
+    pub language: Vec<Filter<Cow<'a, str>>>,
+
+    /// Query specific genres (filter).
+    pub genre: Vec<Filter<Cow<'a, str>>>,
+
+    /// Query songs from some years (filter).
+    pub year: Vec<Filter<NumberRange>>,
+
+    /// Query songs with some BPM (filter).
+    pub bpm: Vec<Filter<NumberRange>>,
+
+    /// Text that songs may not contain, from negated plain words, titles and artists (filter).
+    pub excluded: Vec<Exclusion<'a>>,
+
+    /// Query songs from the specified custom list (filter).
+    pub list: Option<Cow<'a, str>>,
+
+    /// Query the lyrics of songs (full-text search on the server).
+    pub lyrics: Option<Cow<'a, str>>,
+}
+
+/// A filter on a field of songs, e.g. `-lang:english|swedish`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter<T> {
+    /// Match the songs that match none of the alternatives, instead of any of them.
+    pub negated: bool,
+
+    pub alternatives: Vec<T>,
+}
+
+/// An inclusive range of numbers, e.g. `1980..1989`. Either end may be open.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NumberRange {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// Text that songs may not contain, e.g. `-live` or `-artist:abba`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exclusion<'a> {
+    pub field: TextField,
+    pub text: Cow<'a, str>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextField {
+    /// Either the title or the artist, i.e. a plain word.
+    Any,
+    Title,
+    Artist,
+}
+
+/// A term of a query, before its values have been interpreted.
+#[derive(Debug)]
+struct Term<'a> {
+    /// Whether the term started with `-`.
+    negated: bool,
+
+    /// The key of `key:value` terms. `None` for plain words.
+    key: Option<&'a str>,
+
+    /// The alternatives of the value. Only keys that take alternatives have more than one.
+    values: Vec<Cow<'a, str>>,
+
+    /// The whole term as it was written.
+    source: &'a str,
+}
+
+/// The keys of `key:value` terms.
+const KEYS: &[&str] = &[
+    "title", "artist", "duet", "video", "lang", "genre", "year", "bpm", "list", "lyrics",
+];
+
+impl<'a> ParsedQuery<'a> {
+    pub fn parse(s: &'a str) -> Self {
+        let mut parsed = ParsedQuery::default();
+        let mut plain = vec![];
+
+        for Term {
+            negated,
+            key,
+            values,
+            source,
+        } in tokenize(s)
+        {
+            let valid = match (key, negated) {
+                (None, _) => true,
+                (Some("duet" | "video"), _) => parse_bool(&values[0]).is_some(),
+                (Some("year" | "bpm"), _) => {
+                    (values.iter()).all(|v| NumberRange::parse(v).is_some())
+                }
+                (Some("list" | "lyrics"), negated) => !negated,
+                (Some(key), _) => KEYS.contains(&key),
+            };
+            if !valid {
+                plain.push(Cow::Borrowed(source));
+                continue;
+            }
+
+            let exclude = |field, values: Vec<Cow<'a, str>>| {
+                (values.into_iter()).map(move |text| Exclusion { field, text })
+            };
+            let filter = |alternatives| Filter {
+                negated,
+                alternatives,
+            };
+            let ranges = |values: Vec<Cow<str>>| {
+                let alternatives = values.iter().map(|value| NumberRange::parse(value));
+                Some(Filter {
+                    negated,
+                    alternatives: alternatives.collect::<Option<_>>()?,
+                })
+            };
+
+            match (key, negated) {
+                (None, false) => plain.extend(values),
+                (None, true) => parsed.excluded.extend(exclude(TextField::Any, values)),
+                (Some("title"), false) => parsed.title.extend(values),
+                (Some("title"), true) => parsed.excluded.extend(exclude(TextField::Title, values)),
+                (Some("artist"), false) => parsed.artist.extend(values),
+                (Some("artist"), true) => {
+                    parsed.excluded.extend(exclude(TextField::Artist, values))
+                }
+                (Some("duet"), _) => parsed.duet = parse_bool(&values[0]).map(|b| b != negated),
+                (Some("video"), _) => parsed.video = parse_bool(&values[0]).map(|b| b != negated),
+                (Some("lang"), _) => parsed.language.push(filter(values)),
+                (Some("genre"), _) => parsed.genre.push(filter(values)),
+                (Some("year"), _) => parsed.year.extend(ranges(values)),
+                (Some("bpm"), _) => parsed.bpm.extend(ranges(values)),
+                (Some("list"), false) => parsed.list = values.into_iter().next(),
+                (Some("lyrics"), false) => parsed.lyrics = values.into_iter().next(),
+                _ => {}
+            }
+        }
+
+        parsed.plain = match plain.len() {
+            0 => None,
+            1 => plain.pop(),
+            _ => Some(Cow::Owned(plain.join(" "))),
+        };
+
+        parsed
+    }
+
+    /// Clear all "fuzzy" query parameters, but leave filters
+    pub fn clear_fuzzy_parameters(&mut self) {
+        self.plain = None;
+        self.title.clear();
+        self.artist.clear();
+        self.lyrics = None;
+    }
+
+    /// Whether the query contains any "fuzzy" query parameters.
+    pub fn has_fuzzy_parameters(&self) -> bool {
+        self.plain.is_some()
+            || !self.title.is_empty()
+            || !self.artist.is_empty()
+            || self.lyrics.is_some()
+    }
+}
+
+impl NumberRange {
+    /// A range of only `n`.
+    pub fn exactly(n: f64) -> Self {
+        NumberRange {
+            min: Some(n),
+            max: Some(n),
+        }
+    }
+
+    /// Parse `min..max`, where either end may be left out, or a single number.
+    pub fn parse(s: &str) -> Option<Self> {
+        let Some((min, max)) = s.split_once("..") else {
+            return parse_number(s).map(NumberRange::exactly);
+        };
+
+        let end = |s: &str| match s {
+            "" => Some(None),
+            s => parse_number(s).map(Some),
+        };
+
+        Some(NumberRange {
+            min: end(min)?,
+            max: end(max)?,
+        })
+    }
+
+    pub fn contains(&self, n: f64) -> bool {
+        self.min.is_none_or(|min| min <= n) && self.max.is_none_or(|max| n <= max)
+    }
+}
+
+/// Parse a number, with either a decimal point or a decimal comma like in the BPM of some songs.
+pub fn parse_number(s: &str) -> Option<f64> {
+    let n: f64 = s.trim().replace(',', ".").parse().ok()?;
+    n.is_finite().then_some(n)
+}
+
+fn parse_bool(s: &str) -> Option<bool> {
+    match s {
+        "true" | "yes" | "y" => Some(true),
+        "false" | "no" | "n" => Some(false),
+        _ => None,
+    }
+}
+
+/// Whether `|` separates alternatives in the values of a key, rather than being part of them.
+fn takes_alternatives(key: &str) -> bool {
+    matches!(key, "title" | "artist" | "lang" | "genre" | "year" | "bpm")
+}
+
+/// Split a query into terms.
+fn tokenize(s: &str) -> Vec<Term<'_>> {
+    let mut terms = vec![];
+    let mut rest = s.trim_start();
+
+    while !rest.is_empty() {
+        let start = rest;
+
+        // a lone `-` is a plain word
+        let negated =
+            rest.len() > 1 && rest.starts_with('-') && !rest[1..].starts_with(char::is_whitespace);
+        if negated {
+            rest = &rest[1..];
+        }
+
+        // keys are the part before the first `:` of an unquoted word
+        let word_end = rest
+            .find(|c: char| c.is_whitespace() || c == '"')
+            .unwrap_or(rest.len());
+        let key = rest[..word_end].split_once(':').map(|(key, _)| key);
+        if let Some(key) = key {
+            rest = &rest[key.len() + 1..];
+        }
+
+        let alternatives = key.is_some_and(takes_alternatives);
+        let mut values = vec![];
+        loop {
+            let (value, after_value) = read_value(rest, alternatives);
+            values.push(value);
+            rest = after_value;
+
+            match rest.strip_prefix('|') {
+                Some(after_bar) if alternatives => rest = after_bar,
+                _ => break,
+            }
+        }
+
+        terms.push(Term {
+            negated,
+            key,
+            values,
+            source: &start[..start.len() - rest.len()],
+        });
+        rest = rest.trim_start();
+    }
+
+    terms
+}
+
+/// Read a quoted or unquoted value from the start of `s`, and return it and the rest of `s`.
+///
+/// Unquoted values end at whitespace, and at `|` if the key takes alternatives. Quoted values may
+/// contain `\"` and `\\`, and end at the end of `s` if the quote isn't closed.
+fn read_value(s: &str, alternatives: bool) -> (Cow<'_, str>, &str) {
+    let Some(quoted) = s.strip_prefix('"') else {
+        let end = s
+            .find(|c: char| c.is_whitespace() || (alternatives && c == '|'))
+            .unwrap_or(s.len());
+        return (Cow::Borrowed(&s[..end]), &s[end..]);
+    };
+
+    let mut value = String::new();
+    let mut escaped = false;
+    for (i, c) in quoted.char_indices() {
+        match c {
+            _ if escaped => {
+                value.push(c);
+                escaped = false;
+            }
+            '\\' => escaped = true,
+            '"' => return (Cow::Owned(value), &quoted[i + 1..]),
+            _ => value.push(c),
+        }
+    }
+
+    (Cow::Owned(value), "")
+}
+
+/// Quote a value if it would be parsed as something else unquoted.
+///
+/// `plain` is for plain words, which may not contain `:` or start with `-`, and `alternatives` for
+/// values of keys that take alternatives, which may not contain `|`.
+pub fn quote(value: &str, plain: bool, alternatives: bool) -> Cow<'_, str> {
+    let needs_quotes = value.is_empty()
+        || value.contains(|c: char| c.is_whitespace() || c == '"')
+        || (plain && (value.contains(':') || value.starts_with('-')))
+        || (alternatives && value.contains('|'));
+
+    if !needs_quotes {
+        return Cow::Borrowed(value);
+    }
+
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+    Cow::Owned(format!("\"{escaped}\""))
+}
+
+impl Display for NumberRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) if min == max => write!(f, "{min}"),
+            (min, max) => {
+                if let Some(min) = min {
+                    write!(f, "{min}")?;
+                }
+                write!(f, "..")?;
+                if let Some(max) = max {
+                    write!(f, "{max}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Display for ParsedQuery<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        let mut w = |negated: bool, key: &str, values: &[String]| -> fmt::Result {
+            if first {
+                first = false;
+            } else {
+                write!(f, " ")?;
+            }
+
+            if negated {
+                write!(f, "-")?;
+            }
+            write!(f, "{key}{}", values.join("|"))
+        };
+
+        let text = |key: &str, value: &str| {
+            let plain = key.is_empty();
+            quote(
+                value,
+                plain,
+                !plain && takes_alternatives(&key[..key.len() - 1]),
+            )
+            .into_owned()
+        };
+        let texts = |key: &str, values: &[Cow<str>]| -> Vec<String> {
+            values.iter().map(|value| text(key, value)).collect()
+        };
+        let bool = |b: bool| String::from(if b { "yes" } else { "no" });
+
+        if let Some(plain) = &self.plain {
+            for word in plain.split(' ') {
+                w(false, "", &[text("", word)])?;
+            }
+        }
+
+        if !self.title.is_empty() {
+            w(false, "title:", &texts("title:", &self.title))?;
+        }
+        if !self.artist.is_empty() {
+            w(false, "artist:", &texts("artist:", &self.artist))?;
+        }
+        if let Some(duet) = self.duet {
+            w(false, "duet:", &[bool(duet)])?;
+        }
+        if let Some(video) = self.video {
+            w(false, "video:", &[bool(video)])?;
+        }
+        for filter in &self.language {
+            w(
+                filter.negated,
+                "lang:",
+                &texts("lang:", &filter.alternatives),
+            )?;
+        }
+        for filter in &self.genre {
+            w(
+                filter.negated,
+                "genre:",
+                &texts("genre:", &filter.alternatives),
+            )?;
+        }
+        for (key, filters) in [("year:", &self.year), ("bpm:", &self.bpm)] {
+            for filter in filters {
+                let ranges: Vec<String> =
+                    filter.alternatives.iter().map(|r| r.to_string()).collect();
+                w(filter.negated, key, &ranges)?;
+            }
+        }
+        for exclusion in &self.excluded {
+            let key = match exclusion.field {
+                TextField::Any => "",
+                TextField::Title => "title:",
+                TextField::Artist => "artist:",
+            };
+            w(true, key, &[text(key, &exclusion.text)])?;
+        }
+        if let Some(list) = &self.list {
+            w(false, "list:", &[text("list:", list)])?;
+        }
+        if let Some(lyrics) = &self.lyrics {
+            w(false, "lyrics:", &[text("lyrics:", lyrics)])?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(min: Option<f64>, max: Option<f64>) -> NumberRange {
+        NumberRange { min, max }
+    }
+
+    #[test]
+    fn quoted_values() {
+        let query = ParsedQuery::parse(r#"artist:"Earth, Wind & Fire" "a \"b\" \\c" title:"open"#);
+        assert_eq!(query.artist, ["Earth, Wind & Fire"]);
+        assert_eq!(query.plain.as_deref(), Some(r#"a "b" \c"#));
+        assert_eq!(query.title, ["open"]);
+    }
+
+    #[test]
+    fn negated_terms() {
+        let query = ParsedQuery::parse("- -live -artist:abba -lang:english|swedish -duet:yes");
+        assert_eq!(query.plain.as_deref(), Some("-"));
+        assert_eq!(
+            query.excluded,
+            [
+                Exclusion {
+                    field: TextField::Any,
+                    text: "live".into(),
+                },
+                Exclusion {
+                    field: TextField::Artist,
+                    text: "abba".into(),
+                },
+            ]
+        );
+        assert_eq!(
+            query.language,
+            [Filter {
+                negated: true,
+                alternatives: vec!["english".into(), "swedish".into()],
+            }]
+        );
+        assert_eq!(query.duet, Some(false));
+    }
+
+    #[test]
+    fn alternatives() {
+        let query = ParsedQuery::parse(r#"genre:pop|"hip|hop" title:a|b list:a|b"#);
+        assert_eq!(
+            query.genre,
+            [Filter {
+                negated: false,
+                alternatives: vec!["pop".into(), "hip|hop".into()],
+            }]
+        );
+        assert_eq!(query.title, ["a", "b"]);
+        assert_eq!(query.list.as_deref(), Some("a|b"));
+    }
+
+    #[test]
+    fn ranges() {
+        let query = ParsedQuery::parse("year:1980..1989|1999 bpm:..100,5 -year:1985..");
+        assert_eq!(
+            query.year,
+            [
+                Filter {
+                    negated: false,
+                    alternatives: vec![
+                        range(Some(1980.0), Some(1989.0)),
+                        NumberRange::exactly(1999.0)
+                    ],
+                },
+                Filter {
+                    negated: true,
+                    alternatives: vec![range(Some(1985.0), None)],
+                },
+            ]
+        );
+        assert_eq!(
+            query.bpm,
+            [Filter {
+                negated: false,
+                alternatives: vec![range(None, Some(100.5))],
+            }]
+        );
+    }
+
+    #[test]
+    fn invalid_terms_are_plain_text() {
+        let query =
+            ParsedQuery::parse("toxic year:abc bpm:1..x -list:3 -lyrics:love duet:maybe AC/DC:");
+        assert_eq!(
+            query.plain.as_deref(),
+            Some("toxic year:abc bpm:1..x -list:3 -lyrics:love duet:maybe AC/DC:")
+        );
+        assert!(query.year.is_empty() && query.bpm.is_empty());
+        assert_eq!(query.list, None);
+        assert_eq!(query.lyrics, None);
+        assert_eq!(query.duet, None);
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for s in [
+            "dancing queen",
+            r#"artist:"Earth, Wind & Fire"|abba title:"a \"b\"""#,
+            r#""-live" "x:y" -live -title:"hip hop""#,
+            r#"-lang:english|swedish genre:"hip|hop""#,
+            "year:1980..1989|1999 -bpm:..100.5 year:2000..",
+            "duet:yes video:no list:3 lyrics:love",
+            r#"year:abc "-list:3""#,
+        ] {
+            let query = ParsedQuery::parse(s);
+            let displayed = query.to_string();
+            assert_eq!(
+                ParsedQuery::parse(&displayed),
+                query,
+                "{s} was shown as {displayed}"
+            );
+        }
+    }
+}
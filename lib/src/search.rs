@@ -0,0 +1,108 @@
+use std::borrow::Cow;
+use std::cmp::max;
+
+use crate::fuzzy::{self, FuzzyScore};
+use crate::query::{parse_number, Exclusion, Filter, NumberRange, ParsedQuery, TextField};
+
+/// The fields of a song that queries are matched against.
+pub trait Searchable {
+    fn title(&self) -> &str;
+    fn artist(&self) -> &str;
+    fn language(&self) -> Option<&str>;
+    fn genre(&self) -> Option<&str>;
+    fn year(&self) -> Option<&str>;
+    fn bpm(&self) -> &str;
+    fn has_video(&self) -> bool;
+    fn is_duet(&self) -> bool;
+}
+
+/// Match a song against the filters of a query, and score it by its fuzzy parameters.
+///
+/// Returns `None` if the song doesn't pass the filters. The `list` and `lyrics` parameters aren't
+/// handled here, since they need data that only the caller has.
+pub fn score(song: &impl Searchable, query: &ParsedQuery) -> Option<FuzzyScore> {
+    let filter_strs = |filter: &Filter<Cow<str>>, item: Option<&str>| {
+        let matches = item.is_some_and(|item| {
+            (filter.alternatives.iter()).any(|alternative| fuzzy::starts_with(item, alternative))
+        });
+        matches != filter.negated
+    };
+
+    let filter_numbers = |filter: &Filter<NumberRange>, item: Option<&str>| {
+        let matches = item
+            .and_then(parse_number)
+            .is_some_and(|n| (filter.alternatives.iter()).any(|range| range.contains(n)));
+        matches != filter.negated
+    };
+
+    let filter_bool = |query: Option<bool>, item| !matches!(query, Some(query) if query != item);
+
+    let excluded = |exclusion: &Exclusion| {
+        let contains = |item: &str| fuzzy::contains(item, &exclusion.text);
+        !exclusion.text.is_empty()
+            && match exclusion.field {
+                TextField::Any => contains(song.title()) || contains(song.artist()),
+                TextField::Title => contains(song.title()),
+                TextField::Artist => contains(song.artist()),
+            }
+    };
+
+    let filters: &[&dyn Fn() -> bool] = &[
+        &|| filter_bool(query.duet, song.is_duet()),
+        &|| filter_bool(query.video, song.has_video()),
+        &|| (query.language.iter()).all(|f| filter_strs(f, song.language())),
+        &|| (query.genre.iter()).all(|f| filter_strs(f, song.genre())),
+        &|| (query.year.iter()).all(|f| filter_numbers(f, song.year())),
+        &|| (query.bpm.iter()).all(|f| filter_numbers(f, Some(song.bpm()))),
+        &|| !query.excluded.iter().any(excluded),
+    ];
+
+    if !filters.iter().all(|f| f()) {
+        return None;
+    }
+
+    let mut score = FuzzyScore::default();
+    if let Some(plain) = &query.plain {
+        let title_score = fuzzy::compare(song.title(), plain);
+        let artist_score = fuzzy::compare(song.artist(), plain);
+        score = max(title_score, artist_score);
+    }
+
+    for title in &query.title {
+        let new_score = fuzzy::compare(song.title(), title);
+        score = max(score, new_score);
+    }
+
+    for artist in &query.artist {
+        let new_score = fuzzy::compare(song.artist(), artist);
+        score = max(score, new_score);
+    }
+
+    Some(score)
+}
+
+/// The chars of a song's title and artist that matched the fuzzy parameters of a query.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Highlights {
+    pub title: Vec<usize>,
+    pub artist: Vec<usize>,
+}
+
+/// Find the chars of a song's title and artist that matched the fuzzy parameters of a query, to
+/// highlight them.
+///
+/// Only the best matching of the plain query and the specific titles or artists is highlighted.
+pub fn highlights(song: &impl Searchable, query: &ParsedQuery) -> Highlights {
+    let best = |base: &str, searches: &[Cow<str>]| {
+        let searches = query.plain.iter().chain(searches);
+        let found = searches.map(|search| fuzzy::find(base, search));
+        (found.max_by_key(|found| found.score))
+            .map(|found| found.positions)
+            .unwrap_or_default()
+    };
+
+    Highlights {
+        title: best(song.title(), &query.title),
+        artist: best(song.artist(), &query.artist),
+    }
+}
@@ -1,15 +1,21 @@
-use std::{fs::create_dir, path::PathBuf, sync::Arc, vec};
+mod ultrastar;
+
+use std::{
+    collections::HashSet,
+    fs::create_dir,
+    path::{Path, PathBuf},
+    sync::Arc,
+    vec,
+};
 
 use clap::{Parser, Subcommand};
 use eyre::{bail, eyre, WrapErr};
 use rust_fuzzy_search::fuzzy_compare;
 use serde::Serialize;
-use tokio::{
-    fs::{self, File},
-    io::{AsyncBufReadExt, BufReader},
-    sync::mpsc,
-    task,
-};
+use singit_lib::{CoverManifest, PutSongs, SongDigest, SongsDiff, SyncPlan};
+use tokio::{fs, sync::mpsc, task};
+use ultrastar::{Body, ParseError};
+
 #[derive(Parser)]
 struct Opt {
     songs_dir: PathBuf,
@@ -22,9 +28,27 @@ enum Action {
     Save {
         output: PathBuf,
     },
+    /// Upload the song list, and the covers that are missing or have changed on the server.
+    ///
+    /// Covers of songs that are no longer in the list are deleted from the server.
     Post {
         server: String,
-        #[clap(short, long)]
+        /// An API token, with the scopes that the command needs.
+        #[clap(short, long, env = "SINGIT_API_TOKEN")]
+        token: String,
+    },
+    /// Upload only the songs and covers that have changed since the last sync.
+    Sync {
+        server: String,
+        /// An API token, with the scopes that the command needs.
+        #[clap(short, long, env = "SINGIT_API_TOKEN")]
+        token: String,
+    },
+    /// Show what `post` would change on the server, without changing anything.
+    Diff {
+        server: String,
+        /// An API token, with the scopes that the command needs.
+        #[clap(short, long, env = "SINGIT_API_TOKEN")]
         token: String,
     },
     Admin {},
@@ -81,61 +105,204 @@ async fn main() -> eyre::Result<()> {
             }
         }
         Action::Post { server, token } => {
+            for song in &mut songs {
+                song.digest().await?;
+            }
+
             let client = reqwest::Client::new();
+            let res = client.get(format!("{server}/images/songs")).send().await?;
+
+            let manifest: CoverManifest = match res.error_for_status() {
+                Ok(res) => res
+                    .json()
+                    .await
+                    .wrap_err("Failed to deserialize cover manifest")?,
+                Err(e) => {
+                    println!("Error listing covers on {server}, {e:?}");
+                    return Ok(());
+                }
+            };
 
-            println!("Deleting old song covers on {server}");
+            let mut failed_covers = 0;
+            for song in &mut songs {
+                let (Some(cover_from), Some(cover_hash)) = (song.cover_path(), &song.cover_hash)
+                else {
+                    continue;
+                };
+
+                let stored_hash = manifest.covers.get(&song.song_hash);
+                if stored_hash.is_some_and(|hash| hash.as_ref() == Some(cover_hash)) {
+                    continue;
+                }
+
+                println!("Uploading song cover {:?} to {server}", song.cover);
+                if let Err(e) = upload_cover(&client, &server, &token, song, &cover_from).await {
+                    println!("Error sending image {cover_from:?}, {e:?}");
+                    failed_covers += 1;
+
+                    // record the cover that the server still has, and no content hash, so that
+                    // the next sync uploads both the cover and the song again
+                    song.cover_hash = stored_hash.cloned().flatten();
+                    song.content_hash = None;
+                }
+            }
+
+            println!("Uploading {} songs to {server}", songs.len());
             let res = client
-                .delete(format!("{server}/images/songs?token={token}"))
+                .put(format!("{server}/songs"))
+                .bearer_auth(&token)
+                .json(&songs)
                 .send()
                 .await?;
 
             if let Err(e) = res.error_for_status() {
-                println!("Error deleting old covers, {e:?}");
+                println!("Error uploading song list, {e:?}");
                 return Ok(());
             }
 
+            let with_cover: HashSet<&str> = (songs.iter())
+                .filter(|s| s.cover_hash.is_some())
+                .map(|s| s.song_hash.as_str())
+                .collect();
+
+            for song_hash in manifest.covers.keys() {
+                if with_cover.contains(song_hash.as_str()) {
+                    continue;
+                }
+
+                println!("Deleting orphaned song cover {song_hash} on {server}");
+                let res = client
+                    .delete(format!("{server}/images/songs/{song_hash}"))
+                    .bearer_auth(&token)
+                    .send()
+                    .await?;
+
+                if let Err(e) = res.error_for_status() {
+                    println!("Error deleting song cover {song_hash}, {e:?}");
+                }
+            }
+
+            if failed_covers > 0 {
+                bail!("Failed to upload {failed_covers} song covers");
+            }
+        }
+        Action::Sync { server, token } => {
+            let mut digests = vec![];
             for song in &mut songs {
-                if let Some(cover) = &song.cover {
-                    let mut cover_from = song.path.clone();
-                    cover_from.pop();
-                    cover_from = cover_from.join(cover);
-                    //let cover_to = output.join(&song.song_hash);
-                    if cover_from.is_file() {
-                        let img = tokio::fs::File::open(&cover_from)
-                            .await
-                            .wrap_err(eyre!("Failed to open {:?}", cover_from))?;
-
-                        println!("Uploading song cover {cover:?} to {server}");
-                        let img_res = client
-                            .put(format!(
-                                "{server}/images/songs/{}?token={token}",
-                                song.song_hash
-                            ))
-                            .body(img)
-                            .send()
-                            .await?;
-                        if let Err(e) = img_res.error_for_status() {
-                            println!("Error sending image {cover_from:?}, {e:?}")
-                        }
-                    } else {
-                        println!("invalid cover {cover_from:?}, song: {:?}", song.title);
-                        song.cover = None;
-                    }
+                digests.push(song.digest().await?);
+            }
+
+            let client = reqwest::Client::new();
+            let res = client
+                .post(format!("{server}/songs/sync"))
+                .bearer_auth(&token)
+                .json(&digests)
+                .send()
+                .await?;
+
+            let plan: SyncPlan = match res.error_for_status() {
+                Ok(res) => res
+                    .json()
+                    .await
+                    .wrap_err("Failed to deserialize sync plan")?,
+                Err(e) => {
+                    println!("Error comparing song lists, {e:?}");
+                    return Ok(());
+                }
+            };
+
+            let covers: HashSet<&str> = plan.covers.iter().map(String::as_str).collect();
+            let mut failed_covers: HashSet<&str> = HashSet::new();
+            for song in songs
+                .iter()
+                .filter(|s| covers.contains(s.song_hash.as_str()))
+            {
+                let Some(cover_from) = song.cover_path() else {
+                    continue;
+                };
+
+                println!("Uploading song cover {:?} to {server}", song.cover);
+                if let Err(e) = upload_cover(&client, &server, &token, song, &cover_from).await {
+                    println!("Error sending image {cover_from:?}, {e:?}");
+                    failed_covers.insert(&song.song_hash);
                 }
             }
 
-            println!("Uploading {} songs to {server}", songs.len());
+            // songs whose cover failed to upload are left as they are on the server, so that the
+            // next sync tries again
+            let changed: HashSet<&str> = plan.songs.iter().map(String::as_str).collect();
+            let patch = SongsPatch {
+                upsert: (songs.iter())
+                    .filter(|s| changed.contains(s.song_hash.as_str()))
+                    .filter(|s| !failed_covers.contains(s.song_hash.as_str()))
+                    .collect(),
+                remove: plan.removed,
+            };
+
+            println!(
+                "Uploading {} changed songs to {server}, removing {}",
+                patch.upsert.len(),
+                patch.remove.len()
+            );
             let res = client
-                .put(format!("{server}/songs?token={token}"))
-                .json(&songs)
+                .patch(format!("{server}/songs"))
+                .bearer_auth(&token)
+                .json(&patch)
                 .send()
                 .await?;
 
-            if let Err(e) = res.error_for_status() {
-                println!("Error uploading song list, {e:?}");
-                return Ok(());
+            let response: PutSongs = match res.error_for_status() {
+                Ok(res) => res
+                    .json()
+                    .await
+                    .wrap_err("Failed to deserialize response")?,
+                Err(e) => {
+                    println!("Error uploading songs, {e:?}");
+                    return Ok(());
+                }
+            };
+
+            println!(
+                "{} songs added, {} updated and {} removed",
+                response.songs_added, response.songs_updated, response.songs_deleted
+            );
+
+            if !failed_covers.is_empty() {
+                bail!(
+                    "Failed to upload {} song covers, their songs weren't updated",
+                    failed_covers.len()
+                );
             }
         }
+        Action::Diff { server, token } => {
+            // covers that can't be found aren't uploaded by `post` either
+            for song in &mut songs {
+                if song.cover_path().is_some_and(|cover| !cover.is_file()) {
+                    song.cover = None;
+                }
+            }
+
+            let client = reqwest::Client::new();
+            let res = client
+                .put(format!("{server}/songs?dry_run=true"))
+                .bearer_auth(&token)
+                .json(&songs)
+                .send()
+                .await?;
+
+            let diff: SongsDiff = match res.error_for_status() {
+                Ok(res) => res
+                    .json()
+                    .await
+                    .wrap_err("Failed to deserialize song diff")?,
+                Err(e) => {
+                    println!("Error diffing song list, {e:?}");
+                    return Ok(());
+                }
+            };
+
+            print_diff(&diff);
+        }
         Action::Admin {} => {
             /* fs::write(output, jsongs)
             .await
@@ -143,6 +310,7 @@ async fn main() -> eyre::Result<()> {
             let mut no_video = vec![];
             let mut no_cover = vec![];
             let mut no_genre = vec![];
+            let mut invalid_body = vec![];
             for song in songs {
                 if song.cover.is_none() {
                     let s = SmallSong {
@@ -168,6 +336,15 @@ async fn main() -> eyre::Result<()> {
                     };
                     no_genre.push(s)
                 }
+                if let Some(error) = &song.body_error {
+                    let s = InvalidSong {
+                        path: song.path.clone(),
+                        title: song.title.clone(),
+                        artist: song.artist.clone(),
+                        error: error.to_string(),
+                    };
+                    invalid_body.push(s)
+                }
             }
             let jno_video = serde_json::to_string_pretty(&no_video)
                 .with_context(|| "failed to serialize song list")?;
@@ -175,6 +352,8 @@ async fn main() -> eyre::Result<()> {
                 .with_context(|| "failed to serialize song list")?;
             let jno_genre = serde_json::to_string_pretty(&no_genre)
                 .with_context(|| "failed to serialize song list")?;
+            let jinvalid_body = serde_json::to_string_pretty(&invalid_body)
+                .with_context(|| "failed to serialize song list")?;
             fs::write("no_video.json", jno_video)
                 .await
                 .with_context(|| "failed to write no_video to file")?;
@@ -184,6 +363,9 @@ async fn main() -> eyre::Result<()> {
             fs::write("no_genre.json", jno_genre)
                 .await
                 .with_context(|| "failed to write no_cover to file")?;
+            fs::write("invalid_body.json", jinvalid_body)
+                .await
+                .with_context(|| "failed to write invalid_body to file")?;
         }
         Action::Duplicates { output } => {
             let mut dup_songs = vec![];
@@ -210,7 +392,110 @@ async fn main() -> eyre::Result<()> {
 
     Ok(())
 }
+
+fn print_diff(diff: &SongsDiff) {
+    let value = |value: &Option<String>| match value {
+        Some(value) => format!("{value:?}"),
+        None => "none".to_string(),
+    };
+
+    println!("{} songs would be added", diff.added.len());
+    for song in &diff.added {
+        println!("  + {} - {}", song.artist, song.title);
+    }
+
+    println!("{} songs would be removed", diff.removed.len());
+    for song in &diff.removed {
+        println!("  - {} - {}", song.artist, song.title);
+    }
+
+    println!("{} songs would be changed", diff.changed.len());
+    for changed in &diff.changed {
+        println!("  ~ {} - {}", changed.song.artist, changed.song.title);
+        for change in &changed.changes {
+            // lyrics are too long to print
+            if change.field == "lyrics" {
+                println!("      lyrics changed");
+            } else {
+                let (old, new) = (value(&change.old), value(&change.new));
+                println!("      {}: {old} -> {new}", change.field);
+            }
+        }
+    }
+}
+
+/// Upload the cover of a song, found at `cover_from`.
+async fn upload_cover(
+    client: &reqwest::Client,
+    server: &str,
+    token: &str,
+    song: &Song,
+    cover_from: &Path,
+) -> eyre::Result<()> {
+    let img = tokio::fs::File::open(cover_from)
+        .await
+        .wrap_err(eyre!("Failed to open {:?}", cover_from))?;
+
+    client
+        .put(format!("{server}/images/songs/{}", song.song_hash))
+        .bearer_auth(token)
+        .body(img)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
+
 impl Song {
+    /// The path of the cover image of the song, if it has one.
+    fn cover_path(&self) -> Option<PathBuf> {
+        let cover = self.cover.as_ref()?;
+        let mut cover_path = self.path.clone();
+        cover_path.pop();
+        Some(cover_path.join(cover))
+    }
+
+    /// Hash the song and its cover, to let the server tell if it has changed.
+    ///
+    /// Sets `content_hash` and `cover_hash`, and clears `cover` if the cover can't be found.
+    async fn digest(&mut self) -> eyre::Result<SongDigest> {
+        self.cover_hash = None;
+        if let Some(cover_path) = self.cover_path() {
+            if cover_path.is_file() {
+                let img = fs::read(&cover_path)
+                    .await
+                    .wrap_err(eyre!("Failed to read {:?}", cover_path))?;
+                self.cover_hash = Some(format!("{:?}", md5::compute(img)));
+            } else {
+                println!("invalid cover {cover_path:?}, song: {:?}", self.title);
+                self.cover = None;
+            }
+        }
+
+        // everything that is uploaded to the server, except for the song hash
+        let content = serde_json::to_vec(&(
+            &self.title,
+            &self.artist,
+            &self.language,
+            &self.genre,
+            &self.year,
+            &self.cover,
+            &self.video,
+            &self.bpm,
+            &self.lyrics,
+            &self.cover_hash,
+        ))?;
+        let content_hash = format!("{:?}", md5::compute(content));
+        self.content_hash = Some(content_hash.clone());
+
+        Ok(SongDigest {
+            song_hash: self.song_hash.clone(),
+            content_hash,
+            cover_hash: self.cover_hash.clone(),
+        })
+    }
+
     fn fuzzy_song_compare(&self, song: &Song) -> f32 {
         if self.path == song.path {
             return 0.0;
@@ -258,7 +543,11 @@ fn explore_dir(path: PathBuf, tx: Arc<mpsc::Sender<Song>>) {
                 if !file_name.ends_with(".txt") {
                     continue;
                 }
-                parse_file(entry.path(), Arc::clone(&tx)).await?;
+
+                let path = entry.path();
+                if let Err(e) = parse_file(path.clone(), Arc::clone(&tx)).await {
+                    println!("Error parsing {path:?}, {e:?}");
+                }
             }
         }
 
@@ -268,6 +557,13 @@ fn explore_dir(path: PathBuf, tx: Arc<mpsc::Sender<Song>>) {
     task::spawn(inner(path, tx));
 }
 
+/// Body of `PATCH /songs`.
+#[derive(Serialize)]
+struct SongsPatch<'a> {
+    upsert: Vec<&'a Song>,
+    remove: Vec<String>,
+}
+
 #[derive(Debug, Default, Serialize, Clone)]
 struct SmallSong {
     path: PathBuf,
@@ -275,6 +571,14 @@ struct SmallSong {
     artist: Option<String>,
 }
 
+#[derive(Debug, Default, Serialize, Clone)]
+struct InvalidSong {
+    path: PathBuf,
+    title: Option<String>,
+    artist: Option<String>,
+    error: String,
+}
+
 #[derive(Debug, Default, Serialize, Clone)]
 struct Song {
     path: PathBuf,
@@ -290,11 +594,48 @@ struct Song {
     bpm: Option<String>,
     gap: Option<String>,
     bg: Option<String>,
+
+    /// The lyrics of the song, one line of lyrics per line.
+    lyrics: Option<String>,
+
+    /// A hash of the song, see [Song::digest].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_hash: Option<String>,
+
+    /// A hash of the cover image, see [Song::digest].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cover_hash: Option<String>,
+
+    /// The time in seconds from the start of the audio to the end of the last note. `None` if
+    /// the body, `#BPM` or `#GAP` couldn't be parsed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    length: Option<f64>,
+
+    /// The reason that the body failed to parse, if it did.
+    #[serde(skip)]
+    body_error: Option<ParseError>,
+}
+
+/// Parse a number from a song header, which may use a decimal comma.
+fn parse_decimal(s: &str) -> Option<f64> {
+    s.trim().replace(',', ".").parse().ok()
+}
+
+/// Decode a song file. Files that aren't UTF-8 are usually Windows-1252, and are read as Latin-1,
+/// which only differs from it in a few rarely used chars.
+fn decode(file: Vec<u8>) -> String {
+    match String::from_utf8(file) {
+        Ok(file) => file,
+        Err(e) => e.into_bytes().into_iter().map(char::from).collect(),
+    }
 }
 
 async fn parse_file(path: PathBuf, tx: Arc<mpsc::Sender<Song>>) -> eyre::Result<()> {
-    let file = BufReader::new(File::open(&path).await?);
-    let mut lines = file.lines();
+    let file = fs::read(&path)
+        .await
+        .wrap_err(eyre!("Failed to read {:?}", path))?;
+    let file = decode(file);
+    let mut lines = (1..).zip(file.lines()).peekable();
 
     let file_name = path.file_name().expect("file has a filename");
     //println!("hashing {file_name:?}");
@@ -308,11 +649,7 @@ async fn parse_file(path: PathBuf, tx: Arc<mpsc::Sender<Song>>) -> eyre::Result<
         ..Default::default()
     };
 
-    loop {
-        let Some(line) = lines.next_line().await? else {
-            break;
-        };
-
+    while let Some(&(_, line)) = lines.peek() {
         let Some(line) = line.strip_prefix('#') else {
             break;
         };
@@ -320,6 +657,7 @@ async fn parse_file(path: PathBuf, tx: Arc<mpsc::Sender<Song>>) -> eyre::Result<
         let Some((key, value)) = line.split_once(':') else {
             break;
         };
+        lines.next();
 
         let field = match key {
             "TITLE" => &mut song.title,
@@ -339,9 +677,33 @@ async fn parse_file(path: PathBuf, tx: Arc<mpsc::Sender<Song>>) -> eyre::Result<
         *field = Some(value.to_string());
     }
 
+    match Body::parse(lines) {
+        Ok(body) => {
+            let lyrics = body.lyrics();
+            song.lyrics = (!lyrics.is_empty()).then_some(lyrics);
+
+            let bpm = song.bpm.as_deref().and_then(parse_decimal);
+            let gap = song.gap.as_deref().map_or(Some(0.0), parse_decimal);
+            let length = bpm.zip(gap).and_then(|(bpm, gap)| body.length(bpm, gap));
+            song.length = length.map(|length| length.as_secs_f64());
+        }
+        Err(e) => song.body_error = Some(e),
+    }
+
     if song.title.is_some() {
         tx.send(song).await?;
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_latin1() {
+        assert_eq!(decode("#TITLE:Sång".into()), "#TITLE:Sång");
+        assert_eq!(decode(b"#TITLE:S\xe5ng".to_vec()), "#TITLE:Sång");
+    }
+}
@@ -0,0 +1,364 @@
+//! Parser for the body of an UltraStar song file, i.e. everything after the `#KEY:VALUE` header.
+//!
+//! ```text
+//! : 0 4 5 Hel
+//! : 4 4 7 lo
+//! * 8 4 9  world
+//! - 14
+//! P2
+//! F 16 2 0 Yeah
+//! E
+//! ```
+
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+use serde::Serialize;
+
+/// The notes and lyrics of a song.
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct Body {
+    /// One track per singer. Songs that aren't duets only have a single track.
+    pub tracks: Vec<Track>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct Track {
+    /// The player marker of the track, e.g. `1` for `P1`. `None` if the song isn't a duet.
+    pub player: Option<u8>,
+
+    pub lines: Vec<Line>,
+}
+
+/// A line of lyrics, as shown on screen at the same time.
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct Line {
+    pub notes: Vec<Note>,
+
+    /// The beat of the line break that ended this line, if any.
+    pub line_break: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Note {
+    pub kind: NoteKind,
+
+    /// The beat that the note starts on.
+    pub start: i32,
+
+    /// The length of the note, in beats.
+    pub length: i32,
+
+    /// The pitch of the note, in half-tones relative to C4.
+    pub pitch: i32,
+
+    /// The syllable that is sung. Includes leading/trailing spaces that separate words.
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum NoteKind {
+    /// `:`
+    Normal,
+    /// `*`
+    Golden,
+    /// `F`
+    Freestyle,
+    /// `R`
+    Rap,
+    /// `G`
+    GoldenRap,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ParseError {
+    /// Line number in the song file, starting at 1.
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub enum ParseErrorKind {
+    /// A line that didn't start with any known marker.
+    UnknownLine(String),
+
+    /// A note or line break with a missing or non-numeric field.
+    InvalidNumber(String),
+
+    /// The file ended without an `E` line.
+    MissingEnd,
+}
+
+impl Body {
+    /// Parse the body of a song file.
+    ///
+    /// `lines` should yield the lines after the header, together with their line numbers.
+    pub fn parse<'a>(
+        lines: impl IntoIterator<Item = (usize, &'a str)>,
+    ) -> Result<Self, ParseError> {
+        let mut body = Body {
+            tracks: vec![Track::default()],
+        };
+        let mut last_line = 0;
+
+        for (n, line) in lines {
+            last_line = n;
+            let error = |kind| ParseError { line: n, kind };
+
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let track = body.tracks.last_mut().expect("body has at least one track");
+            let (marker, rest) = line.split_at(line.chars().next().map_or(0, char::len_utf8));
+
+            let kind = match marker {
+                ":" => NoteKind::Normal,
+                "*" => NoteKind::Golden,
+                "F" => NoteKind::Freestyle,
+                "R" => NoteKind::Rap,
+                "G" => NoteKind::GoldenRap,
+                "E" => return Ok(body),
+                "-" => {
+                    let mut rest = rest;
+                    let beat = parse_number(&mut rest).map_err(error)?;
+                    track.current_line().line_break = Some(beat);
+                    continue;
+                }
+                "P" => {
+                    let player = rest
+                        .trim()
+                        .parse()
+                        .map_err(|_| error(ParseErrorKind::UnknownLine(line.to_string())))?;
+
+                    // the first marker replaces the implicit track, unless notes came before it
+                    if track.player.is_none() && track.lines.is_empty() {
+                        track.player = Some(player);
+                    } else {
+                        body.tracks.push(Track {
+                            player: Some(player),
+                            lines: vec![],
+                        });
+                    }
+                    continue;
+                }
+                _ => return Err(error(ParseErrorKind::UnknownLine(line.to_string()))),
+            };
+
+            let mut rest = rest;
+            let start = parse_number(&mut rest).map_err(error)?;
+            let length = parse_number(&mut rest).map_err(error)?;
+            let pitch = parse_number(&mut rest).map_err(error)?;
+
+            track.current_line().notes.push(Note {
+                kind,
+                start,
+                length,
+                pitch,
+                text: rest.to_string(),
+            });
+        }
+
+        Err(ParseError {
+            line: last_line,
+            kind: ParseErrorKind::MissingEnd,
+        })
+    }
+
+    /// Get the lyrics as plain text, with one line of lyrics per line.
+    ///
+    /// Duets have the lyrics of each track after one another.
+    pub fn lyrics(&self) -> String {
+        let mut lyrics = String::new();
+        for line in self.tracks.iter().flat_map(|track| &track.lines) {
+            let text: String = line
+                .notes
+                .iter()
+                .flat_map(|note| note.text.chars())
+                // `~` marks a syllable that is held from the previous note
+                .filter(|&c| c != '~')
+                .collect();
+
+            let text = text.trim();
+            if text.is_empty() {
+                continue;
+            }
+
+            if !lyrics.is_empty() {
+                lyrics.push('\n');
+            }
+            lyrics.push_str(text);
+        }
+        lyrics
+    }
+}
+
+impl Body {
+    /// Get the beat that the last note of the song ends on, in any track.
+    pub fn last_beat(&self) -> Option<i32> {
+        let notes = self.tracks.iter().flat_map(|track| &track.lines);
+        let notes = notes.flat_map(|line| &line.notes);
+        notes.map(|note| note.start + note.length).max()
+    }
+
+    /// Get the time from the start of the audio to the end of the last note.
+    ///
+    /// `bpm` and `gap` are the `#BPM` and `#GAP` headers of the song. A beat in the body is a
+    /// quarter of a beat of `bpm`, and `gap` is the time in milliseconds before the first beat.
+    pub fn length(&self, bpm: f64, gap: f64) -> Option<Duration> {
+        let valid = bpm.is_finite() && bpm > 0.0 && gap.is_finite();
+        if !valid {
+            return None;
+        }
+
+        let beats = f64::from(self.last_beat()?);
+        let seconds = gap / 1000.0 + beats * 60.0 / (bpm * 4.0);
+        Duration::try_from_secs_f64(seconds).ok()
+    }
+}
+
+impl Track {
+    /// Get the line that notes are currently being added to.
+    fn current_line(&mut self) -> &mut Line {
+        if self
+            .lines
+            .last()
+            .is_none_or(|line| line.line_break.is_some())
+        {
+            self.lines.push(Line::default());
+        }
+        self.lines.last_mut().unwrap()
+    }
+}
+
+/// Parse a whitespace-separated number from the start of `s`, and advance `s` past it and the
+/// single whitespace character that follows it.
+fn parse_number(s: &mut &str) -> Result<i32, ParseErrorKind> {
+    let trimmed = s.trim_start();
+    let (number, rest) = match trimmed.find(char::is_whitespace) {
+        Some(i) => {
+            let separator = trimmed[i..].chars().next().map_or(0, char::len_utf8);
+            (&trimmed[..i], &trimmed[i + separator..])
+        }
+        None => (trimmed, ""),
+    };
+
+    let number = number
+        .parse()
+        .map_err(|_| ParseErrorKind::InvalidNumber(number.to_string()))?;
+    *s = rest;
+    Ok(number)
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ParseErrorKind::UnknownLine(line) => write!(f, "unknown line {line:?}"),
+            ParseErrorKind::InvalidNumber(n) => write!(f, "invalid number {n:?}"),
+            ParseErrorKind::MissingEnd => write!(f, "missing end marker 'E'"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUET: &str = "\
+P1
+: 0 4 5 Hel
+: 4 4 7 lo
+* 8 4 9  world
+- 14
+F 16 2 0 Yeah
+- 20
+R 20 4 0 ~
+P2
+G 24 8 -2 Hey
+: 32 4 3  you
+E
+ignored after the end
+";
+
+    fn parse(body: &str) -> Result<Body, ParseError> {
+        Body::parse((1..).zip(body.lines()))
+    }
+
+    #[test]
+    fn notes_and_line_breaks() {
+        let body = parse(DUET).unwrap();
+        assert_eq!(body.tracks.len(), 2);
+
+        let first = &body.tracks[0];
+        assert_eq!(first.player, Some(1));
+        assert_eq!(first.lines.len(), 3);
+        assert_eq!(first.lines[0].line_break, Some(14));
+        assert_eq!(first.lines[1].line_break, Some(20));
+        assert_eq!(first.lines[2].line_break, None);
+
+        let kinds: Vec<NoteKind> = first.lines[0].notes.iter().map(|n| n.kind).collect();
+        assert_eq!(
+            kinds,
+            [NoteKind::Normal, NoteKind::Normal, NoteKind::Golden]
+        );
+
+        let world = &first.lines[0].notes[2];
+        assert_eq!((world.start, world.length, world.pitch), (8, 4, 9));
+        assert_eq!(world.text, " world");
+
+        assert_eq!(first.lines[1].notes[0].kind, NoteKind::Freestyle);
+        assert_eq!(first.lines[2].notes[0].kind, NoteKind::Rap);
+    }
+
+    #[test]
+    fn duet_tracks() {
+        let body = parse(DUET).unwrap();
+
+        assert_eq!(body.tracks[1].player, Some(2));
+        let hey = &body.tracks[1].lines[0].notes[0];
+        assert_eq!((hey.kind, hey.pitch), (NoteKind::GoldenRap, -2));
+
+        // notes before the first marker stay in their own track
+        let body = parse(": 0 1 0 a\nP2\n: 0 1 0 b\nE").unwrap();
+        let players: Vec<_> = body.tracks.iter().map(|track| track.player).collect();
+        assert_eq!(players, [None, Some(2)]);
+    }
+
+    #[test]
+    fn lyrics() {
+        let body = parse(DUET).unwrap();
+        assert_eq!(body.lyrics(), "Hello world\nYeah\nHey you");
+    }
+
+    #[test]
+    fn length() {
+        let body = parse(DUET).unwrap();
+        assert_eq!(body.last_beat(), Some(36));
+
+        // 36 quarter beats at 90 bpm is 6 seconds, after a gap of half a second
+        let length = body.length(90.0, 500.0).unwrap();
+        assert_eq!(length, Duration::from_millis(6500));
+
+        assert_eq!(body.length(0.0, 500.0), None);
+        assert_eq!(parse("E").unwrap().length(90.0, 0.0), None);
+    }
+
+    #[test]
+    fn errors() {
+        let error = parse(": 0 4 5 a\n: 4 x 5 b\nE").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(matches!(error.kind, ParseErrorKind::InvalidNumber(n) if n == "x"));
+
+        let error = parse(": 0 4 5 a\nhello\nE").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(matches!(error.kind, ParseErrorKind::UnknownLine(_)));
+
+        let error = parse(": 0 4 5 a\n- 4").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(matches!(error.kind, ParseErrorKind::MissingEnd));
+    }
+}